@@ -1,6 +1,6 @@
 "use client";
 
-import { useState, useEffect, useCallback } from "react";
+import { useState, useEffect, useCallback, useRef } from "react";
 import { Sidebar } from "@/components/sidebar";
 import { MonacoEditor } from "@/components/monacoeditor";
 import { CreateProjectModal } from "@/components/create-project-modal";
@@ -15,6 +15,17 @@ type Project = {
   path: string;
 };
 
+type FileVersion = {
+  hash: string;
+  modified: number | null;
+};
+
+type OpenedFile = {
+  content: string;
+  encoding: string;
+  version: FileVersion;
+};
+
 type EditorTabState = {
   project_name: string;
   tabs: EditorTab[];
@@ -33,6 +44,8 @@ const [isSidebarOpen, setIsSidebarOpen] = useState(false);
   const [editorTabs, setEditorTabs] = useState<Record<string, EditorTab[]>>({});
   const [activeTabId, setActiveTabId] = useState<Record<string, string | null>>({});
   const [showTerminal, setShowTerminal] = useState<Record<string, boolean>>({});
+  // hash of what's on disk for each open file tab, sent back with saves
+  const fileHashes = useRef<Record<string, string>>({});
 const uniqueProjectNames = Array.from(
   new Set([...projects.map(p => p.name), ...recentProjects.map(p => p.name)])
 );
@@ -173,6 +186,27 @@ const updateFolderChildren = useCallback(
   [currentProject]
 );
 
+ // ---------------- Track restored tabs ----------------
+// restored tabs need the on-disk version before their first save
+const trackRestoredTabs = useCallback(async (project: string, tabs: EditorTab[]) => {
+  for (const tab of tabs) {
+    if (tab.path.startsWith("postman://") || fileHashes.current[tab.id]) continue;
+    try {
+      const opened: OpenedFile = await invoke("open_file", { path: tab.path });
+      fileHashes.current[tab.id] = opened.version.hash;
+      // a tab without unsaved edits shows what's on disk now
+      if (tab.saved !== false) {
+        setEditorTabs(prev => ({
+          ...prev,
+          [project]: (prev[project] || []).map(t => (t.id === tab.id ? { ...t, content: opened.content } : t)),
+        }));
+      }
+    } catch (err) {
+      console.error("Failed to open restored tab:", tab.path, err);
+    }
+  }
+}, []);
+
  // ---------------- Select project (recent or normal) ----------------
 const handleSelectProject = useCallback(
   async (name: string, path: string) => {
@@ -221,6 +255,7 @@ const handleSelectProject = useCallback(
       if (projectState) {
         setEditorTabs(prev => ({ ...prev, [name]: projectState.tabs }));
         setActiveTabId(prev => ({ ...prev, [name]: projectState.active_tab_id || null }));
+        trackRestoredTabs(name, projectState.tabs);
       } else {
         setEditorTabs(prev => ({ ...prev, [name]: [] }));
         setActiveTabId(prev => ({ ...prev, [name]: null }));
@@ -237,7 +272,7 @@ const handleSelectProject = useCallback(
     // Persist recent projects in Rust
     await invoke("write_recent_projects", { projects: updatedRecent });
   },
-  [projectFiles, recentProjects]
+  [projectFiles, recentProjects, trackRestoredTabs]
 );
 
 
@@ -278,18 +313,19 @@ const handleFileSelect = useCallback(
       return;
     }
 
-    console.log(`[LOG] read_file invoked for path: ${node.path}`);
-    const content: string = await invoke("read_file", { path: node.path });
-    console.log(`[LOG] read_file returned content length: ${content.length} for path: ${node.path}`);
+    console.log(`[LOG] open_file invoked for path: ${node.path}`);
+    const opened: OpenedFile = await invoke("open_file", { path: node.path });
+    console.log(`[LOG] open_file returned content length: ${opened.content.length} for path: ${node.path}`);
 
     const newTab: EditorTab = {
       id: crypto.randomUUID(),
       name: node.name,
       path: node.path,
-      content,
+      content: opened.content,
       saved: true,
       type: "file",
     };
+    fileHashes.current[newTab.id] = opened.version.hash;
 
     setEditorTabs(prev => ({
       ...prev,
@@ -335,6 +371,7 @@ useEffect(() => {
       const updatedTabs = projectTabs.filter(tab => tab.id !== tabId);
 
       setEditorTabs(prev => ({ ...prev, [currentProject]: updatedTabs }));
+      delete fileHashes.current[tabId];
       if (activeTabId[currentProject] === tabId) {
         setActiveTabId(prev => ({ ...prev, [currentProject]: updatedTabs.length ? updatedTabs[updatedTabs.length - 1].id : null }));
       }
@@ -343,9 +380,39 @@ useEffect(() => {
   );
 
 const autoSave = useCallback(
-  debounce(async (tabPath: string, content: string) => {
+  debounce(async (project: string, tabId: string, tabPath: string, content: string) => {
+    const save = (expectedHash?: string): Promise<FileVersion> =>
+      invoke("save_file", { path: tabPath, content, expectedHash });
+
     try {
-      await invoke("save_file", { path: tabPath, content });
+      if (!fileHashes.current[tabId]) {
+        try {
+          const opened: OpenedFile = await invoke("open_file", { path: tabPath });
+          fileHashes.current[tabId] = opened.version.hash;
+        } catch {
+          // not on disk (yet), the save creates it
+        }
+      }
+
+      let version: FileVersion;
+      try {
+        version = await save(fileHashes.current[tabId]);
+      } catch (err: any) {
+        if (err?.kind !== "Conflict") throw err;
+        if (!confirm(`${err.message}. Save your changes anyway?\n\nCancel reloads the file from disk.`)) {
+          const opened: OpenedFile = await invoke("open_file", { path: tabPath });
+          fileHashes.current[tabId] = opened.version.hash;
+          setEditorTabs(prev => ({
+            ...prev,
+            [project]: (prev[project] || []).map(tab =>
+              tab.id === tabId ? { ...tab, content: opened.content, saved: true } : tab
+            ),
+          }));
+          return;
+        }
+        version = await save();
+      }
+      fileHashes.current[tabId] = version.hash;
       console.log("Auto-saved:", tabPath);
     } catch (err) {
       console.error("Auto-save failed:", err);
@@ -378,7 +445,7 @@ const handleContentChange = useCallback(
       // Auto-save to disk
       const activeTab = projectTabs.find(tab => tab.id === tabId);
       if (activeTab && activeTab.type === "file") {
-        autoSave(activeTab.path, content);
+        autoSave(currentProject, activeTab.id, activeTab.path, content);
       }
 
       return { ...prev, [currentProject]: updatedTabs };
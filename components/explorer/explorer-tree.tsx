@@ -388,16 +388,17 @@ const handleRenameSubmit = async () => {
 
   } catch (err: any) {
     console.error("Rename error:", err);
+    const message: string = err?.message ?? String(err);
     
     // User-friendly error messages
-    if (err.toString().includes("Target already exists")) {
+    if (err?.kind === "AlreadyExists") {
       alert(`A file/folder with name "${newName}" already exists in this location.`);
-    } else if (err.toString().includes("read-only")) {
+    } else if (message.includes("read-only")) {
       alert(`Cannot rename. File/folder is read-only.`);
-    } else if (err.toString().includes("Permission denied")) {
+    } else if (message.includes("Permission denied")) {
       alert(`Permission denied. Cannot rename "${node.name}".`);
     } else {
-      alert(`Failed to rename "${node.name}": ${err}`);
+      alert(`Failed to rename "${node.name}": ${message}`);
     }
   }
 };
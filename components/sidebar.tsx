@@ -55,7 +55,51 @@ export function Sidebar({
   // Get project node for current project
 
 
+  // The backend lists one folder level at a time, so a folder's entries
+  // are fetched with list_directory each time it is opened.
+  const loadFolder = useCallback(async (folderId: string) => {
+    const findNode = (nodes: ExplorerNode[]): ExplorerNode | null => {
+      for (const node of nodes) {
+        if (node.id === folderId) return node;
+        const found = node.children ? findNode(node.children) : null;
+        if (found) return found;
+      }
+      return null;
+    };
+    const folder = findNode(files);
+    if (!folder || folder.type !== "folder") return;
+
+    try {
+      const entries = await invoke<ExplorerNode[]>("list_directory", { path: folder.path });
+      const children = entries.map(entry => ({
+        ...entry,
+        children: entry.children ?? undefined,
+        parentId: folder.id,
+        isOpen: false,
+      }));
+
+      const replace = (nodes: ExplorerNode[]): ExplorerNode[] =>
+        nodes.map(node => {
+          if (node.id === folderId) {
+            // keep subfolders that are already loaded
+            const merged = children.map(child => {
+              const existing = node.children?.find(c => c.id === child.id);
+              return existing?.children && !child.children ? { ...child, children: existing.children } : child;
+            });
+            return { ...node, children: merged };
+          }
+          return node.children ? { ...node, children: replace(node.children) } : node;
+        });
+      setFiles(prev => replace(prev));
+    } catch (err) {
+      console.error("Failed to list folder:", err);
+    }
+  }, [files, setFiles]);
+
   const toggleFolder = useCallback((folderId: string) => {
+    if (!openFolders.has(folderId)) {
+      loadFolder(folderId);
+    }
     setOpenFolders(prev => {
       const next = new Set(prev);
       if (next.has(folderId)) {
@@ -65,7 +109,7 @@ export function Sidebar({
       }
       return next;
     });
-  }, []);
+  }, [openFolders, loadFolder]);
 const safeProjects = Array.isArray(projects) ? projects : [];
 console.log("Projects:", safeProjects);
 
@@ -0,0 +1,107 @@
+use std::path::Path;
+use tauri::{command, Manager, Window};
+
+use crate::models::arduino::{
+    ArduinoSettings, InstalledLibrary, InstalledPlatform, LibraryLocation, LibraryRelease, PlatformRelease,
+};
+use crate::commands::workspace::sandbox;
+use crate::models::sketch::PreprocessedSketch;
+use crate::services::frameworks::arduino;
+use crate::services::{arduino_index, arduino_packages, ino_preprocessor, project_settings};
+use crate::state::app_state::AppState;
+
+#[command]
+pub fn get_project_fqbn(project_path: String, window: Window) -> Result<Option<String>, String> {
+    Ok(project_settings::load(&sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?).fqbn)
+}
+
+/// Sets the board a sketch is compiled for, e.g. `esp32:esp32:esp32s3`.
+#[command]
+pub fn set_project_fqbn(project_path: String, fqbn: String, window: Window) -> Result<(), String> {
+    arduino::validate_fqbn(&fqbn)?;
+
+    let project = sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?;
+    let mut settings = project_settings::load(&project);
+    settings.fqbn = Some(fqbn);
+    project_settings::save(&project, &settings)
+}
+
+/// The C++ the sketch's tabs turn into, with generated prototypes and
+/// `#line` directives back to the tabs.
+#[command]
+pub fn preprocess_sketch(project_path: String, window: Window) -> Result<PreprocessedSketch, String> {
+    ino_preprocessor::preprocess(&sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?)
+}
+
+#[command]
+pub fn get_arduino_settings() -> ArduinoSettings {
+    arduino_index::load_settings()
+}
+
+/// Sets the mirror folder (and optionally where libraries, cores and tools
+/// are installed) used by the library and board manager.
+#[command]
+pub fn set_arduino_settings(settings: ArduinoSettings) -> Result<(), String> {
+    if settings.mirror_dir.is_some() {
+        arduino_index::mirror_dir(&settings)?;
+    }
+    arduino_index::save_settings(&settings)
+}
+
+#[command]
+pub fn search_arduino_libraries(query: String) -> Result<Vec<LibraryRelease>, String> {
+    let mirror = arduino_index::mirror_dir(&arduino_index::load_settings())?;
+    arduino_index::search_libraries(&mirror, &query)
+}
+
+/// Libraries of the project (when given) and the shared folder, flagged
+/// when the mirror has a newer version.
+#[command]
+pub fn list_arduino_libraries(project_path: Option<String>) -> Result<Vec<InstalledLibrary>, String> {
+    arduino_packages::list_libraries(&arduino_index::load_settings(), project_path.as_deref().map(Path::new))
+}
+
+/// Installs a library and its dependencies from the mirror; installing a
+/// newer version over an installed one updates it. Returns what was
+/// installed.
+#[command]
+pub async fn install_arduino_library(
+    name: String,
+    version: Option<String>,
+    location: LibraryLocation,
+    project_path: Option<String>,
+) -> Result<Vec<InstalledLibrary>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        arduino_packages::install_library(
+            &arduino_index::load_settings(),
+            &name,
+            version.as_deref(),
+            location,
+            project_path.as_deref().map(Path::new),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[command]
+pub fn search_arduino_platforms(query: String) -> Result<Vec<PlatformRelease>, String> {
+    let mirror = arduino_index::mirror_dir(&arduino_index::load_settings())?;
+    arduino_index::search_platforms(&mirror, &query)
+}
+
+#[command]
+pub fn list_arduino_platforms() -> Result<Vec<InstalledPlatform>, String> {
+    arduino_packages::list_platforms(&arduino_index::load_settings())
+}
+
+/// Installs a board core (`packager:architecture`) and its tools from the
+/// mirror.
+#[command]
+pub async fn install_arduino_platform(id: String, version: Option<String>) -> Result<InstalledPlatform, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        arduino_packages::install_platform(&arduino_index::load_settings(), &id, version.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -1,9 +1,20 @@
-use tauri::command;
-use std::path::Path;
-use crate::utils::fs::find_bins;
+use tauri::{command, Manager, Window};
+use crate::commands::workspace::sandbox;
+use crate::models::flash::FlashLayout;
+use crate::services::frameworks;
+use crate::services::artifacts::{artifacts_dir, build_artifacts_dir, find_bins};
+use crate::state::app_state::AppState;
 
 #[command]
-pub fn get_build_artifacts(project_path: String) -> Vec<crate::models::artifact::Artifact> {
-    let build_dir = Path::new(&project_path).join("build");
-    find_bins(&build_dir)
+pub fn get_build_artifacts(project_path: String, window: Window) -> Result<Vec<crate::models::artifact::Artifact>, String> {
+    let dir = artifacts_dir(&sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?);
+    Ok(find_bins(&dir))
+}
+
+/// Flash offsets of a build's output files; latest build for `None`.
+#[command]
+pub fn get_flash_layout(project_path: String, build_number: Option<u32>, window: Window) -> Result<FlashLayout, String> {
+    let project = sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?;
+    let dir = build_artifacts_dir(&project, build_number)?;
+    frameworks::for_project(&project)?.flash_layout(&dir)
 }
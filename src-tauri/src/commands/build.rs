@@ -1,100 +1,255 @@
-use tauri::Window;
-use std::process::{Command, Stdio};
-use std::io::{BufRead, BufReader};
+use tauri::{Manager, State, Window};
 use std::thread;
+use uuid::Uuid;
+
+use crate::commands::workspace::sandbox;
+use crate::models::build::{BuildJobInfo, BuildStatus};
+use crate::models::diagnostic::Diagnostic;
+use crate::services::diagnostics::DiagnosticParser;
+use crate::services::frameworks;
+use crate::services::process_runner::{self, strip_ansi, LineSplitter};
+use crate::state::app_state::{AppState, BuildJob};
+use crate::utils::process::kill_process_group;
 
 #[tauri::command]
-pub fn build_project(project_path: String, window: Window) -> Result<(), String> {
-    thread::spawn(move || {
-        let home = match dirs::home_dir() {
-            Some(h) => h,
-            None => {
-                let _ = window.emit("build-log", "Home directory not found");
-                return;
-            }
-        };
+pub fn build_project(
+    project_path: String,
+    clean: Option<bool>,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    // the build cleans and runs tools in here
+    let project = sandbox(&window, &state)?.resolve(&project_path)?;
+    let job_id = Uuid::new_v4().to_string();
 
-        let esp_idf = home.join("esp/esp-idf/export.sh");
+    {
+        let mut builds = state.builds.lock().map_err(|e| e.to_string())?;
 
-        if !esp_idf.exists() {
-            let _ = window.emit("build-log", "ESP-IDF export.sh not found");
-            let _ = window.emit("build-finished", "Build failed");
-            return;
+        if builds
+            .values()
+            .any(|j| j.project_path == project_path && j.status == BuildStatus::Building)
+        {
+            return Err("A build is already running for this project".into());
         }
 
-        let _ = window.emit("build-log", " Starting ESP-IDF build...");
-let command = format!(
-r#"
-set -e
-source "{}"
-
-idf.py build
-idf.py merge-bin -o merged.bin
-
-# keep only merged.bin inside build
-find build -mindepth 1 ! -name 'merged.bin' -exec rm -rf {{}} +
-"#,
-esp_idf.display()
-);
-
-
+        // only the latest job per project is kept around for get_build_status
+        builds.retain(|_, j| j.project_path != project_path);
+        let job = BuildJob {
+            project_path: project_path.clone(),
+            status: BuildStatus::Building,
+            pid: None,
+            exit_code: None,
+        };
+        let _ = window.emit("build-status", job.info(&job_id));
+        builds.insert(job_id.clone(), job);
+    }
 
+    state
+        .diagnostics
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(project_path.clone(), Vec::new());
 
+    let id = job_id.clone();
+    thread::spawn(move || {
+        let job_id = id;
+        let project = project.as_path();
 
+        let backend = match frameworks::for_project(project) {
+            Ok(b) => b,
+            Err(e) => return fail_build(&window, &job_id, format!("❌ {}", e)),
+        };
 
-        let mut child = match Command::new("bash")
-            .arg("-lc")
-            .arg(command)
-            .current_dir(&project_path)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-        {
-            Ok(c) => c,
-            Err(e) => {
-                let _ = window.emit("build-log", format!("❌ Failed to start build: {}", e));
-                let _ = window.emit("build-finished", "Build failed");
-                return;
+        if clean.unwrap_or(false) {
+            let _ = window.emit("build-log", " Cleaning build output...");
+            if let Err(e) = backend.clean(project) {
+                return fail_build(&window, &job_id, format!("❌ {}", e));
             }
-        };
+        }
 
-        let stdout = child.stdout.take().unwrap();
-        let stderr = child.stderr.take().unwrap();
+        let opts = match backend.build_command(project, &mut |line| {
+            let _ = window.emit("build-log", line);
+        }) {
+            Ok(opts) => opts.pty(true),
+            Err(e) => return fail_build(&window, &job_id, format!("❌ {}", e)),
+        };
 
-        let win_out = window.clone();
-        let win_err = window.clone();
+        let process = match process_runner::spawn(opts) {
+            Ok(p) => p,
+            Err(e) => return fail_build(&window, &job_id, format!("❌ Failed to start build: {}", e)),
+        };
 
-        // stdout streaming
-        thread::spawn(move || {
-            let reader = BufReader::new(stdout);
-            for line in reader.lines().flatten() {
-                let _ = win_out.emit("build-log", line);
+        if let Some(pid) = process.pid() {
+            if !register_pid(&window, &job_id, pid) {
+                // cancelled before the child was up
+                let _ = kill_process_group(pid);
             }
-        });
+        }
+
+        let mut parser = DiagnosticParser::new(project);
+        let mut lines = LineSplitter::default();
 
-        // stderr streaming
-        thread::spawn(move || {
-            let reader = BufReader::new(stderr);
-            for line in reader.lines().flatten() {
-                let _ = win_err.emit("build-log", format!("⚠ {}", line));
+        // raw chunks keep colours for terminal views, build-log stays one plain line per event
+        let exit = process.wait(|chunk| {
+            for line in lines.push(&chunk.data) {
+                handle_log_line(&window, &project_path, &mut parser, line);
             }
+            let _ = window.emit("build-output", chunk);
         });
+        if let Some(line) = lines.finish() {
+            handle_log_line(&window, &project_path, &mut parser, line);
+        }
+        if let Some(d) = parser.finish() {
+            record_diagnostic(&window, &project_path, d);
+        }
 
-        let status = child.wait();
+        let (status, code) = match exit {
+            Ok(e) if e.success => (BuildStatus::Success, e.code),
+            Ok(e) => (BuildStatus::Failed, e.code),
+            Err(_) => (BuildStatus::Failed, None),
+        };
 
-        match status {
-            Ok(s) if s.success() => {
-                let _ = window.emit("build-log", "✅ Build complete (only merged.bin kept)");
+        match finish_job(&window, &job_id, status, code) {
+            BuildStatus::Success => {
+                match backend.collect_artifacts(project) {
+                    Ok(dir) => {
+                        let _ = window.emit("build-log", format!("📦 Artifacts saved to {}", dir.display()));
+                    }
+                    Err(e) => {
+                        let _ = window.emit("build-log", format!("⚠ Failed to collect artifacts: {}", e));
+                    }
+                }
+                let _ = window.emit("build-log", "✅ Build complete");
                 let _ = window.emit("build-finished", "Build successful");
                 let _ = window.emit("refresh-project-files", project_path);
             }
+            BuildStatus::Cancelled => {
+                let _ = window.emit("build-log", "⛔ Build cancelled");
+                let _ = window.emit("build-finished", "Build cancelled");
+            }
             _ => {
                 let _ = window.emit("build-finished", "Build failed");
             }
         }
     });
 
-    Ok(())
+    Ok(job_id)
+}
+
+fn handle_log_line(window: &Window, project_path: &str, parser: &mut DiagnosticParser, line: String) {
+    let line = strip_ansi(&line);
+    let diag = parser.push_line(&line);
+    let _ = window.emit("build-log", line);
+    if let Some(d) = diag {
+        record_diagnostic(window, project_path, d);
+    }
+}
+
+/// Stores a parsed diagnostic for `get_last_build_diagnostics` and pushes it
+/// to the editor as `build-diagnostic`. Repeats of the same diagnostic (one
+/// header compiled into several objects) are dropped.
+fn record_diagnostic(window: &Window, project_path: &str, diag: Diagnostic) {
+    let state = window.state::<AppState>();
+    let mut all = state.diagnostics.lock().unwrap();
+    let list = all.entry(project_path.to_string()).or_default();
+
+    if list.contains(&diag) {
+        return;
+    }
+
+    let _ = window.emit("build-diagnostic", diag.clone());
+    list.push(diag);
+}
+
+/// Ends a build that failed before its process ran.
+fn fail_build(window: &Window, job_id: &str, message: String) {
+    let _ = window.emit("build-log", message);
+    let _ = window.emit("build-finished", "Build failed");
+    finish_job(window, job_id, BuildStatus::Failed, None);
+}
+
+/// Records the child's pid on the job. Returns false if the job was
+/// cancelled while the child was still being spawned.
+fn register_pid(window: &Window, job_id: &str, pid: u32) -> bool {
+    let state = window.state::<AppState>();
+    let mut builds = state.builds.lock().unwrap();
+
+    match builds.get_mut(job_id) {
+        Some(job) if job.status == BuildStatus::Building => {
+            job.pid = Some(pid);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Moves the job to its final status and emits `build-status`. A job that was
+/// cancelled stays `Cancelled` even though its child exits with a failure.
+fn finish_job(
+    window: &Window,
+    job_id: &str,
+    status: BuildStatus,
+    exit_code: Option<i32>,
+) -> BuildStatus {
+    let state = window.state::<AppState>();
+    let mut builds = state.builds.lock().unwrap();
+
+    let job = match builds.get_mut(job_id) {
+        Some(j) => j,
+        None => return status,
+    };
+
+    job.pid = None;
+    job.exit_code = exit_code;
+    if job.status != BuildStatus::Cancelled {
+        job.status = status;
+    }
+
+    let _ = window.emit("build-status", job.info(job_id));
+    job.status.clone()
+}
+
+#[tauri::command]
+pub fn cancel_build(
+    job_id: String,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut builds = state.builds.lock().map_err(|e| e.to_string())?;
+    let job = builds.get_mut(&job_id).ok_or("Unknown build job")?;
+
+    if job.status != BuildStatus::Building {
+        return Err(format!("Build is not running ({:?})", job.status));
+    }
+
+    job.status = BuildStatus::Cancelled;
+    let _ = window.emit("build-status", job.info(&job_id));
+
+    match job.pid {
+        Some(pid) => kill_process_group(pid),
+        None => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub fn get_build_status(
+    job_id: String,
+    state: State<'_, AppState>,
+) -> Result<BuildJobInfo, String> {
+    let builds = state.builds.lock().map_err(|e| e.to_string())?;
+    builds
+        .get(&job_id)
+        .map(|j| j.info(&job_id))
+        .ok_or_else(|| "Unknown build job".into())
+}
+
+#[tauri::command]
+pub fn get_last_build_diagnostics(
+    project_path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<Diagnostic>, String> {
+    let all = state.diagnostics.lock().map_err(|e| e.to_string())?;
+    Ok(all.get(&project_path).cloned().unwrap_or_default())
 }
 
 #[tauri::command]
@@ -1,7 +1,18 @@
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::fs;
+use std::io::ErrorKind;
 use std::path::PathBuf;
-use tauri::command;
+use tauri::{command, State, Window};
+
+use crate::commands::watcher::record_own_write;
+use crate::commands::explorer::read_text;
+use crate::commands::workspace::sandbox;
+use crate::models::editor::{FileVersion, OpenedFile, TextEncoding};
+use crate::models::workspace::{FsError, FsErrorKind};
+use crate::services::{encoding, history};
+use crate::state::app_state::AppState;
+use crate::utils::fs::{content_hash, match_line_endings, modified_ms, write_atomic};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct EditorTabState {
@@ -45,8 +56,79 @@ pub fn load_editor_state() -> Result<Vec<EditorTabState>, String> {
     let state: Vec<EditorTabState> = serde_json::from_str(&data).map_err(|e| e.to_string())?;
     Ok(state)
 }
+/// Opens a text file for editing, along with its encoding and the version
+/// to send back with `save_file`.
 #[command]
-pub fn save_file(path: String, content: String) -> Result<(), String> {
-    std::fs::write(path, content)
-        .map_err(|e| e.to_string())
+pub fn open_file(path: String, window: Window, state: State<'_, AppState>) -> Result<OpenedFile, FsError> {
+    let path = sandbox(&window, &state)?.resolve(&path)?;
+    let (data, content, encoding) = read_text(&path)?;
+    Ok(OpenedFile {
+        content,
+        encoding,
+        version: FileVersion {
+            hash: content_hash(&data),
+            modified: fs::metadata(&path).ok().and_then(|m| modified_ms(&m)),
+        },
+    })
+}
+
+/// Writes `content` through a temporary file and a rename, keeping the
+/// file's encoding (unless `encoding` is given), line endings and
+/// permissions. With `expected_hash` (from
+/// `open_file` or the previous save) the save is refused with a `Conflict`
+/// error if the file changed or was deleted since; without it the file is
+/// overwritten. The saved content is added to the file's history. Returns
+/// the new version.
+#[command]
+pub fn save_file(
+    path: String,
+    content: String,
+    expected_hash: Option<String>,
+    encoding: Option<TextEncoding>,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<FileVersion, FsError> {
+    let sandbox = sandbox(&window, &state)?;
+    let path = sandbox.resolve(&path)?;
+    let existing = match fs::read(&path) {
+        Ok(data) => Some(data),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(FsError::io(&path, e)),
+    };
+    let existing_modified = fs::metadata(&path).ok().and_then(|m| modified_ms(&m));
+
+    if let Some(expected) = &expected_hash {
+        let message = match &existing {
+            Some(existing) if &content_hash(existing) != expected => Some("changed on disk since it was opened"),
+            Some(_) => None,
+            None => Some("was deleted since it was opened"),
+        };
+        if let Some(message) = message {
+            return Err(FsError::new(FsErrorKind::Conflict, &path, format!("{} {}", path.display(), message)));
+        }
+    }
+
+    let existing_encoding = existing.as_deref().and_then(encoding::detect);
+    let existing_text = existing
+        .as_deref()
+        .zip(existing_encoding)
+        .and_then(|(data, detected)| encoding::decode(data, detected));
+    let content = match &existing_text {
+        Some(existing) => match_line_endings(&content, existing.as_bytes()),
+        None => Cow::Borrowed(content.as_str()),
+    };
+    let data = encoding::encode(&content, encoding.or(existing_encoding).unwrap_or_default())?;
+
+    write_atomic(&path, &data)?;
+    record_own_write(&state, &path, &data);
+    if let Some(root) = sandbox.root_of(&path) {
+        if let Err(e) = history::record_save(root, &path, existing.as_deref(), existing_modified, &data) {
+            eprintln!("Failed to record history of {}: {}", path.display(), e);
+        }
+    }
+
+    Ok(FileVersion {
+        hash: content_hash(&data),
+        modified: fs::metadata(&path).ok().and_then(|m| modified_ms(&m)),
+    })
 }
@@ -1,209 +1,468 @@
-use tauri::command;
-use std::fs::{self, File};
-
-#[derive(serde::Serialize, Clone, Debug)]
-pub struct ExplorerNode {
-    pub id: String,
-    pub name: String,
-    pub path: String,
-     #[serde(rename = "type")] 
-    pub node_type: String, 
-    pub children: Option<Vec<ExplorerNode>>,
-}
+use tauri::{command, Manager, State, Window};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+
+use crate::commands::watcher::record_own_removal;
+use crate::commands::workspace::{open_project, sandbox};
+use crate::models::editor::{FileChunk, TextEncoding};
+use crate::models::explorer::{ConflictPolicy, ExplorerNode, ExplorerOperation, MovedPath};
+use crate::models::trash::TrashEntry;
+use crate::models::workspace::{FsError, FsErrorKind};
+use crate::services::workspace::Sandbox;
+use crate::services::transfer::{self, Progress};
+use crate::services::{encoding, file_tree, project_settings, search, trash};
+use crate::state::app_state::AppState;
+
+/// Operations kept for undo per window.
+const HISTORY_LIMIT: usize = 50;
+
+/// Largest slice `read_file_bytes` returns at once.
+const MAX_CHUNK: usize = 64 * 1024;
+
+/// Opens the project in this window and returns its root with the first
+/// level listed; deeper folders are listed with `list_directory`.
 #[tauri::command]
-pub fn list_project_files(project_path: String) -> Result<Vec<ExplorerNode>, String> {
-    let root = std::path::Path::new(&project_path);
-
-    let project_name = root
-        .file_name()
-        .ok_or("Invalid project path")?
-        .to_string_lossy()
-        .to_string();
-
-    let mut entries = read_dir_recursive(root)?;
-
-   entries = entries
-        .into_iter()
-        .flat_map(|node| {
-            if node.node_type == "folder" && node.name == project_name {
-           
-                node.children.unwrap_or_default()
-            } else {
-                vec![node]
-            }
-        })
-        .collect();
-
-    Ok(vec![ExplorerNode {
-        id: project_path.clone(),
-        name: project_name.clone(),
-        path: project_path.clone(),
-        node_type: "folder".into(),
-        children: Some(entries),
-    }])
+pub fn list_project_files(
+    project_path: String,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<Vec<ExplorerNode>, FsError> {
+    let root = open_project(project_path.clone(), window, state)?;
+    let root = Path::new(&root);
+    let shown = Path::new(&project_path);
+
+    let meta = fs::metadata(root).map_err(|e| FsError::io(shown, e))?;
+    let mut node = file_tree::node(shown, &meta);
+    node.children = Some(file_tree::list(root, root, shown)?);
+    Ok(vec![node])
 }
 
-
-fn read_dir_recursive(path: &std::path::Path) -> Result<Vec<ExplorerNode>, String> {
-    let mut nodes = Vec::new();
-
-    for entry in std::fs::read_dir(path).map_err(|e| e.to_string())? {
-        let entry = entry.map_err(|e| e.to_string())?;
-        let entry_path = entry.path();
-        let is_dir = entry_path.is_dir();
-
-        nodes.push(ExplorerNode {
-            id: entry_path.to_string_lossy().to_string(),
-            name: entry.file_name().to_string_lossy().to_string(),
-            path: entry_path.to_string_lossy().to_string(),
-            node_type: if is_dir { "folder".into() } else { "file".into() },
-            children: if is_dir {
-                Some(read_dir_recursive(&entry_path)?)
-            } else {
-                None
-            },
-        });
+/// The entries of one folder, folders first, without .git, the editor's
+/// own folder and the project's `explorer_excludes`.
+#[tauri::command]
+pub fn list_directory(path: String, window: Window, state: State<'_, AppState>) -> Result<Vec<ExplorerNode>, FsError> {
+    let sandbox = sandbox(&window, &state)?;
+    let dir = sandbox.resolve(&path)?;
+    if !dir.is_dir() {
+        return Err(FsError::new(FsErrorKind::InvalidPath, Path::new(&path), format!("{} is not a folder", path)));
     }
+    let root = sandbox.root_of(&dir).unwrap_or(&dir);
+    file_tree::list(root, &dir, Path::new(&path))
+}
 
-    Ok(nodes)
+/// Replaces the globs hidden from the project tree.
+#[command]
+pub fn set_explorer_excludes(
+    project_path: String,
+    excludes: Vec<String>,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<(), FsError> {
+    search::glob_set(&excludes)?;
+    let project = sandbox(&window, &state)?.resolve(&project_path)?;
+    let mut settings = project_settings::load(&project);
+    settings.explorer_excludes = excludes;
+    Ok(project_settings::save(&project, &settings)?)
 }
 
+/// A text file's content, decoded from whatever encoding it uses. Binary
+/// files fail with a `Binary` error.
 #[command]
-pub fn read_file(path: String) -> Result<String, String> {
-    let p = std::path::Path::new(&path);
+pub fn read_file(path: String, window: Window, state: State<'_, AppState>) -> Result<String, FsError> {
+    let p = sandbox(&window, &state)?.resolve(&path)?;
+    let (_, text, _) = read_text(&p)?;
+    Ok(text)
+}
 
+/// The raw bytes of `path` and the decoded text, with its encoding.
+pub fn read_text(p: &Path) -> Result<(Vec<u8>, String, TextEncoding), FsError> {
     if p.is_dir() {
-        return Err("Cannot open a directory".into());
+        return Err(FsError::new(FsErrorKind::InvalidPath, p, "Cannot open a directory"));
     }
 
+    let data = fs::read(p).map_err(|e| FsError::io(p, e))?;
+    let binary = || FsError::new(FsErrorKind::Binary, p, "Binary file – cannot be opened as text");
+    let encoding = encoding::detect(&data).ok_or_else(binary)?;
+    let text = encoding::decode(&data, encoding).ok_or_else(binary)?;
+    Ok((data, text, encoding))
+}
 
-    if p.extension().and_then(|e| e.to_str()) == Some("bin") {
-        return Err("Binary file – cannot be opened as text".into());
+/// Up to `length` bytes (64 KiB at most) of `path` from `offset`, for the
+/// hex viewer. Reading past the end gives fewer or no bytes.
+#[command]
+pub fn read_file_bytes(
+    path: String,
+    offset: u64,
+    length: Option<usize>,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<FileChunk, FsError> {
+    let p = sandbox(&window, &state)?.resolve(&path)?;
+    let length = length.unwrap_or(MAX_CHUNK).min(MAX_CHUNK);
+
+    let mut file = fs::File::open(&p).map_err(|e| FsError::io(&p, e))?;
+    let meta = file.metadata().map_err(|e| FsError::io(&p, e))?;
+    if meta.is_dir() {
+        return Err(FsError::new(FsErrorKind::InvalidPath, &p, "Cannot open a directory"));
     }
 
-    std::fs::read_to_string(p)
-        .map_err(|e| e.to_string())
-}
-
+    let mut bytes = Vec::with_capacity(length.min(meta.len().saturating_sub(offset) as usize));
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.by_ref().take(length as u64).read_to_end(&mut bytes))
+        .map_err(|e| FsError::io(&p, e))?;
 
+    Ok(FileChunk {
+        offset,
+        bytes,
+        file_size: meta.len(),
+    })
+}
 
 #[command]
-pub fn create_file(full_path: String) -> Result<String, String> {
-    let path = std::path::Path::new(&full_path);
+pub fn create_file(full_path: String, window: Window, state: State<'_, AppState>) -> Result<String, FsError> {
+    let path = sandbox(&window, &state)?.resolve(&full_path)?;
 
     if path.exists() {
-        return Err("File already exists".into());
+        return Err(FsError::new(FsErrorKind::AlreadyExists, &path, "File already exists"));
     }
 
+    let created = outermost_missing(&path);
     if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        fs::create_dir_all(parent).map_err(|e| FsError::io(parent, e))?;
     }
 
-    std::fs::File::create(path).map_err(|e| e.to_string())?;
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| FsError::io(&path, e))?;
+    record_operation(&window, &state, ExplorerOperation::Create { path: path_string(&created), replaced: None });
 
     Ok(format!("Created: {}", path.display()))
 }
 
 #[command]
-pub fn create_folder(full_path: String) -> Result<String, String> {
-    let path = std::path::Path::new(&full_path);
+pub fn create_folder(full_path: String, window: Window, state: State<'_, AppState>) -> Result<String, FsError> {
+    let path = sandbox(&window, &state)?.resolve(&full_path)?;
 
     if path.exists() {
-        return Err("Folder already exists".into());
+        return Err(FsError::new(FsErrorKind::AlreadyExists, &path, "Folder already exists"));
     }
 
-    std::fs::create_dir_all(path).map_err(|e| e.to_string())?;
+    let created = outermost_missing(&path);
+    fs::create_dir_all(&path).map_err(|e| FsError::io(&path, e))?;
+    record_operation(&window, &state, ExplorerOperation::Create { path: path_string(&created), replaced: None });
 
     Ok(format!("Folder created: {}", path.display()))
 }
 
+/// Renames `old_path` within its folder; `new_name` can't contain a path
+/// separator. Returns the new path.
 #[command]
-pub fn rename_path(old_path: String, new_name: String) -> Result<String, String> {
-    use std::fs;
-    use std::path::PathBuf;
-    use std::path::Path;
-
-    let old = PathBuf::from(&old_path);
-    
-    // Debug logging
-    println!("Renaming: {} -> {}", old_path, new_name);
-    
-    if !old.exists() {
-        return Err(format!("Path does not exist: {}", old_path));
+pub fn rename_path(
+    old_path: String,
+    new_name: String,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<String, FsError> {
+    let old = sandbox(&window, &state)?.resolve_entry(&old_path)?;
+
+    let mut components = Path::new(&new_name).components();
+    if !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+        return Err(FsError::new(FsErrorKind::InvalidPath, Path::new(&new_name), format!("Invalid name: {}", new_name)));
     }
 
-    let parent = old.parent().ok_or("Invalid path - no parent directory")?;
-    let new_path = parent.join(&new_name);
-
-    if new_path.exists() {
-        return Err(format!("Target already exists: {}", new_path.display()));
+    let metadata = fs::symlink_metadata(&old).map_err(|e| FsError::io(&old, e))?;
+    if metadata.permissions().readonly() {
+        return Err(FsError::new(FsErrorKind::Io, &old, "Source is read-only"));
     }
 
-    // Check if we have write permissions to both source and destination
-    let metadata = fs::metadata(&old).map_err(|e| e.to_string())?;
-    if metadata.permissions().readonly() {
-        return Err("Source is read-only".into());
+    let new_path = old.with_file_name(&new_name);
+    if fs::symlink_metadata(&new_path).is_ok() {
+        return Err(FsError::new(
+            FsErrorKind::AlreadyExists,
+            &new_path,
+            format!("Target already exists: {}", new_path.display()),
+        ));
     }
 
-    // Actually rename the file/folder
-    fs::rename(&old, &new_path).map_err(|e| {
-        println!("Rename error: {}", e);
-        format!("Failed to rename: {}", e)
-    })?;
+    record_own_removal(&state, &old);
+    fs::rename(&old, &new_path).map_err(|e| FsError::io(&old, e))?;
+    record_operation(
+        &window,
+        &state,
+        ExplorerOperation::Rename {
+            from: path_string(&old),
+            to: path_string(&new_path),
+        },
+    );
+
+    Ok(Path::new(&old_path).with_file_name(&new_name).to_string_lossy().to_string())
+}
 
-    println!("Successfully renamed to: {}", new_path.display());
-    Ok(new_path.to_string_lossy().to_string())
+/// Moves `path` into the project's trash, from where `restore_from_trash`
+/// or `undo_explorer_operation` can bring it back.
+#[tauri::command]
+pub fn delete_path(path: String, window: Window, state: State<'_, AppState>) -> Result<TrashEntry, FsError> {
+    let sandbox = sandbox(&window, &state)?;
+    let p = sandbox.resolve_entry(&path)?;
+    let root = sandbox.root_of(&p).unwrap_or(&p);
+
+    record_own_removal(&state, &p);
+    let entry = trash::move_to_trash(root, &p)?;
+    record_operation(
+        &window,
+        &state,
+        ExplorerOperation::Delete {
+            project_path: path_string(root),
+            trash_id: entry.id.clone(),
+            path: path_string(&p),
+        },
+    );
+    Ok(entry)
 }
 
+/// Moves `path` into `target_dir` and returns its new path, `None` if it
+/// was skipped because of a name conflict. Moves to another filesystem copy
+/// the tree and report `transfer-progress`.
 #[tauri::command]
-pub fn delete_path(path: String) -> Result<(), String> {
-    use std::fs;
-    use std::path::Path;
+pub async fn move_path(
+    path: String,
+    target_dir: String,
+    policy: Option<ConflictPolicy>,
+    window: Window,
+) -> Result<Option<String>, FsError> {
+    tauri::async_runtime::spawn_blocking(move || transfer(&window, &path, &target_dir, policy.unwrap_or_default(), true))
+        .await
+        .map_err(|e| FsError::from(e.to_string()))?
+}
 
-    let p = Path::new(&path);
-    
-    // Debug logging
-    println!("Deleting path: {}", path);
+/// Copies `path` (recursively for folders) into `target_dir`, reporting
+/// `transfer-progress`. Returns the copy's path, `None` if it was skipped
+/// because of a name conflict.
+#[tauri::command]
+pub async fn copy_path(
+    path: String,
+    target_dir: String,
+    policy: Option<ConflictPolicy>,
+    window: Window,
+) -> Result<Option<String>, FsError> {
+    tauri::async_runtime::spawn_blocking(move || transfer(&window, &path, &target_dir, policy.unwrap_or_default(), false))
+        .await
+        .map_err(|e| FsError::from(e.to_string()))?
+}
 
-    if !p.exists() {
-        return Err(format!("Path does not exist: {}", path));
-    }
+/// Copies `path` next to itself as `<name> copy` and returns the copy's
+/// path.
+#[tauri::command]
+pub async fn duplicate_path(path: String, window: Window) -> Result<String, FsError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let parent = Path::new(&path).parent().map(path_string).unwrap_or_default();
+        let copied = transfer(&window, &path, &parent, ConflictPolicy::Rename, false)?;
+        Ok(copied.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| FsError::from(e.to_string()))?
+}
 
-    // Check permissions first
-    let metadata = fs::metadata(p).map_err(|e| e.to_string())?;
-    if metadata.permissions().readonly() {
-        return Err("Path is read-only".into());
+fn transfer(
+    window: &Window,
+    path: &str,
+    target_dir: &str,
+    policy: ConflictPolicy,
+    is_move: bool,
+) -> Result<Option<String>, FsError> {
+    let state = window.state::<AppState>();
+    let sandbox = sandbox(window, &state)?;
+    let source = sandbox.resolve_entry(path)?;
+    let target = sandbox.resolve(target_dir)?;
+
+    fs::symlink_metadata(&source).map_err(|e| FsError::io(&source, e))?;
+    if !target.is_dir() {
+        return Err(FsError::new(FsErrorKind::InvalidPath, &target, format!("{} is not a folder", target_dir)));
+    }
+    if target.starts_with(&source) {
+        return Err(FsError::new(FsErrorKind::InvalidPath, &source, "Cannot move or copy a folder into itself"));
     }
 
-    // Check if it's a symbolic link
-    if p.is_symlink() {
-        return Err("Cannot delete symbolic links through this interface".into());
+    let mut destination = target.join(source.file_name().unwrap_or_default());
+    if is_move && destination == source {
+        return Ok(Some(path_string(&source)));
+    }
+    let mut replaced = None;
+    if fs::symlink_metadata(&destination).is_ok() {
+        match policy {
+            ConflictPolicy::Skip => return Ok(None),
+            ConflictPolicy::Rename => destination = transfer::free_path(&destination),
+            // replacing an entry with itself (or a folder containing it) would lose it
+            ConflictPolicy::Overwrite if destination == source || source.starts_with(&destination) => {
+                destination = transfer::free_path(&destination)
+            }
+            ConflictPolicy::Overwrite => {
+                record_own_removal(&state, &destination);
+                let entry = trash::move_to_trash(sandbox.root_of(&destination).unwrap_or(&destination), &destination)?;
+                replaced = Some(entry.id);
+            }
+        }
     }
 
-    if p.is_dir() {
-        // Additional check: is directory empty?
-        let mut entries = fs::read_dir(p).map_err(|e| e.to_string())?;
-        if entries.next().is_some() {
-            // Directory is not empty - ask for confirmation on frontend
-            fs::remove_dir_all(p).map_err(|e| {
-                println!("Delete dir error: {}", e);
-                format!("Failed to delete directory: {}", e)
-            })?;
-        } else {
-            // Directory is empty
-            fs::remove_dir(p).map_err(|e| {
-                println!("Delete empty dir error: {}", e);
-                format!("Failed to delete directory: {}", e)
-            })?;
+    let mut progress = Progress::new(&source, &destination, |report| {
+        let _ = window.emit("transfer-progress", report.clone());
+    });
+    if is_move {
+        record_own_removal(&state, &source);
+        transfer::move_entry(&source, &destination, &mut progress)?;
+    } else {
+        transfer::copy(&source, &destination, &mut progress)?;
+    }
+    progress.finish();
+
+    let operation = if is_move {
+        ExplorerOperation::Move {
+            moves: vec![MovedPath {
+                from: path_string(&source),
+                to: path_string(&destination),
+                replaced,
+            }],
         }
     } else {
-        // It's a file
-        fs::remove_file(p).map_err(|e| {
-            println!("Delete file error: {}", e);
-            format!("Failed to delete file: {}", e)
-        })?;
+        ExplorerOperation::Create {
+            path: path_string(&destination),
+            replaced,
+        }
+    };
+    record_operation(window, &state, operation);
+    Ok(Some(path_string(&destination)))
+}
+
+/// What was deleted from the project, most recent first.
+#[tauri::command]
+pub fn list_trash(project_path: String, window: Window, state: State<'_, AppState>) -> Result<Vec<TrashEntry>, FsError> {
+    let sandbox = sandbox(&window, &state)?;
+    let p = sandbox.resolve(&project_path)?;
+    Ok(trash::list(sandbox.root_of(&p).unwrap_or(&p)))
+}
+
+/// Puts a trashed item back where it was deleted from and returns its path.
+#[tauri::command]
+pub fn restore_from_trash(
+    project_path: String,
+    id: String,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<String, FsError> {
+    let sandbox = sandbox(&window, &state)?;
+    let p = sandbox.resolve(&project_path)?;
+    let restored = trash::restore(sandbox.root_of(&p).unwrap_or(&p), &id)?;
+    Ok(path_string(&restored))
+}
+
+/// Deletes trashed items for good: those in `ids`, or everything.
+#[tauri::command]
+pub fn empty_trash(
+    project_path: String,
+    ids: Option<Vec<String>>,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<(), FsError> {
+    let sandbox = sandbox(&window, &state)?;
+    let p = sandbox.resolve(&project_path)?;
+    trash::empty(sandbox.root_of(&p).unwrap_or(&p), ids.as_deref())
+}
+
+/// Reverts the window's most recent explorer operation and returns it,
+/// `None` when there is nothing left to undo. Created items go to the
+/// trash, and whatever a copy or move overwrote comes back from it. An
+/// operation that can't be reverted (say, its old name is taken again) is
+/// dropped with an error.
+#[tauri::command]
+pub fn undo_explorer_operation(
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<Option<ExplorerOperation>, FsError> {
+    let operation = match state
+        .explorer_history
+        .lock()
+        .unwrap()
+        .get_mut(window.label())
+        .and_then(|history| history.pop())
+    {
+        Some(operation) => operation,
+        None => return Ok(None),
+    };
+    let sandbox = sandbox(&window, &state)?;
+
+    match &operation {
+        ExplorerOperation::Create { path, replaced } => {
+            let p = sandbox.resolve_entry(path)?;
+            let root = sandbox.root_of(&p).unwrap_or(&p);
+            record_own_removal(&state, &p);
+            trash::move_to_trash(root, &p)?;
+            if let Some(id) = replaced {
+                trash::restore(root, id)?;
+            }
+        }
+        ExplorerOperation::Delete { project_path, trash_id, .. } => {
+            trash::restore(&sandbox.resolve(project_path)?, trash_id)?;
+        }
+        ExplorerOperation::Rename { from, to } => move_back(&window, &sandbox, &state, from, to)?,
+        ExplorerOperation::Move { moves } => {
+            for moved in moves.iter().rev() {
+                move_back(&window, &sandbox, &state, &moved.from, &moved.to)?;
+                if let Some(id) = &moved.replaced {
+                    let to = sandbox.resolve(&moved.to)?;
+                    trash::restore(sandbox.root_of(&to).unwrap_or(&to), id)?;
+                }
+            }
+        }
+    }
+    Ok(Some(operation))
+}
+
+/// Remembers `operation` for `undo_explorer_operation`.
+pub fn record_operation(window: &Window, state: &AppState, operation: ExplorerOperation) {
+    let mut history = state.explorer_history.lock().unwrap();
+    let operations = history.entry(window.label().to_string()).or_default();
+    operations.push(operation);
+    if operations.len() > HISTORY_LIMIT {
+        operations.remove(0);
     }
+}
 
-    println!("Successfully deleted: {}", path);
+/// Moves what is now at `to` back to `from`, copying across filesystems
+/// like `move_path` does.
+fn move_back(window: &Window, sandbox: &Sandbox, state: &AppState, from: &str, to: &str) -> Result<(), FsError> {
+    let from = sandbox.resolve_entry(from)?;
+    let to = sandbox.resolve_entry(to)?;
+    if fs::symlink_metadata(&from).is_ok() {
+        return Err(FsError::new(
+            FsErrorKind::AlreadyExists,
+            &from,
+            format!("{} already exists", from.display()),
+        ));
+    }
+    if let Some(parent) = from.parent() {
+        fs::create_dir_all(parent).map_err(|e| FsError::io(parent, e))?;
+    }
+    record_own_removal(state, &to);
+    let mut progress = Progress::new(&to, &from, |report| {
+        let _ = window.emit("transfer-progress", report.clone());
+    });
+    transfer::move_entry(&to, &from, &mut progress)?;
+    progress.finish();
     Ok(())
-}
\ No newline at end of file
+}
+
+/// The outermost of `path` and its folders that doesn't exist yet.
+fn outermost_missing(path: &Path) -> PathBuf {
+    path.ancestors()
+        .take_while(|p| fs::symlink_metadata(p).is_err())
+        .last()
+        .unwrap_or(path)
+        .to_path_buf()
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
@@ -0,0 +1,86 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tauri::{State, Window};
+
+use crate::commands::watcher::record_own_write;
+use crate::commands::workspace::sandbox;
+use crate::models::editor::FileVersion;
+use crate::models::history::{DiffHunk, HistoryEntry};
+use crate::models::workspace::FsError;
+use crate::services::{encoding, history};
+use crate::state::app_state::AppState;
+use crate::utils::fs::{content_hash, modified_ms, write_atomic};
+
+/// The file and the project it belongs to, both canonical.
+fn locate(path: &str, window: &Window, state: &AppState) -> Result<(PathBuf, PathBuf), FsError> {
+    let sandbox = sandbox(window, state)?;
+    let path = sandbox.resolve(path)?;
+    let root = sandbox.root_of(&path).unwrap_or(&path).to_path_buf();
+    Ok((root, path))
+}
+
+/// The saved versions of a file, newest first.
+#[tauri::command]
+pub fn list_file_history(path: String, window: Window, state: State<'_, AppState>) -> Result<Vec<HistoryEntry>, FsError> {
+    let (root, path) = locate(&path, &window, &state)?;
+    history::list(&root, &path)
+}
+
+#[tauri::command]
+pub fn read_file_version(
+    path: String,
+    version_id: String,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<String, FsError> {
+    let (root, path) = locate(&path, &window, &state)?;
+    let data = history::read(&root, &path, &version_id)?;
+    Ok(encoding::decode_any(&data))
+}
+
+/// Changes from a saved version to what the file holds now (nothing if it
+/// was deleted).
+#[tauri::command]
+pub fn diff_file_version(
+    path: String,
+    version_id: String,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<Vec<DiffHunk>, FsError> {
+    let (root, path) = locate(&path, &window, &state)?;
+    let old = history::read(&root, &path, &version_id)?;
+    let current = match fs::read(&path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(FsError::io(&path, e)),
+    };
+    Ok(history::diff(&encoding::decode_any(&old), &encoding::decode_any(&current)))
+}
+
+/// Puts a saved version back, recreating the file if it was deleted. This
+/// is a save of its own, so the version it replaces stays in the history.
+#[tauri::command]
+pub fn restore_file_version(
+    path: String,
+    version_id: String,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<FileVersion, FsError> {
+    let (root, path) = locate(&path, &window, &state)?;
+    let data = history::read(&root, &path, &version_id)?;
+    let previous = fs::read(&path).ok();
+    let previous_modified = fs::metadata(&path).ok().and_then(|m| modified_ms(&m));
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| FsError::io(parent, e))?;
+    }
+    write_atomic(&path, &data)?;
+    record_own_write(&state, &path, &data);
+    history::record_save(&root, &path, previous.as_deref(), previous_modified, &data)?;
+
+    Ok(FileVersion {
+        hash: content_hash(&data),
+        modified: fs::metadata(&path).ok().and_then(|m| modified_ms(&m)),
+    })
+}
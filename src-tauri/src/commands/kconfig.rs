@@ -0,0 +1,69 @@
+use std::fs;
+use tauri::{command, State, Window};
+
+use crate::commands::workspace::sandbox;
+use crate::models::kconfig::{KconfigMenu, KconfigNode, KconfigSymbolState};
+use crate::services::kconfig::KconfigTree;
+use crate::services::toolchain;
+use crate::state::app_state::AppState;
+use crate::utils::fs::write_atomic;
+
+/// Parses the project's Kconfig tree with its current sdkconfig and keeps it
+/// loaded for the other `kconfig_*` commands until the next load.
+#[command]
+pub fn kconfig_load(project_path: String, window: Window, state: State<'_, AppState>) -> Result<KconfigMenu, String> {
+    let project = sandbox(&window, &state)?.resolve(&project_path)?;
+    let toolchain = toolchain::for_project(&project)?;
+    let tree = KconfigTree::load(&project, &toolchain)?;
+
+    let menu = KconfigMenu {
+        root: tree.menu_tree(),
+        warnings: tree.warnings.clone(),
+    };
+    state
+        .kconfig
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(project_path, tree);
+    Ok(menu)
+}
+
+#[command]
+pub fn kconfig_search(
+    project_path: String,
+    query: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<KconfigNode>, String> {
+    let trees = state.kconfig.lock().map_err(|e| e.to_string())?;
+    let tree = trees.get(&project_path).ok_or("Configuration is not loaded for this project")?;
+    Ok(tree.search(&query))
+}
+
+/// Changes one option in memory; `None` reverts it to its default. Nothing is
+/// written until `kconfig_save`.
+#[command]
+pub fn kconfig_set_value(
+    project_path: String,
+    name: String,
+    value: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<KconfigSymbolState>, String> {
+    let mut trees = state.kconfig.lock().map_err(|e| e.to_string())?;
+    let tree = trees.get_mut(&project_path).ok_or("Configuration is not loaded for this project")?;
+    tree.set_value(&name, value)
+}
+
+/// Writes the loaded configuration to the project's sdkconfig, keeping the
+/// previous file as sdkconfig.old like menuconfig does.
+#[command]
+pub fn kconfig_save(project_path: String, window: Window, state: State<'_, AppState>) -> Result<(), String> {
+    let project = sandbox(&window, &state)?.resolve(&project_path)?;
+    let trees = state.kconfig.lock().map_err(|e| e.to_string())?;
+    let tree = trees.get(&project_path).ok_or("Configuration is not loaded for this project")?;
+
+    let sdkconfig = project.join("sdkconfig");
+    if sdkconfig.exists() {
+        fs::copy(&sdkconfig, sdkconfig.with_extension("old")).map_err(|e| e.to_string())?;
+    }
+    write_atomic(&sdkconfig, tree.to_sdkconfig().as_bytes())
+}
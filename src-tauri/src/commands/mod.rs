@@ -11,4 +11,15 @@ pub mod editor_state;
 pub mod auth;
 pub mod refresh;
 pub mod select_controller;
-pub mod release_controller;
\ No newline at end of file
+pub mod release_controller;
+pub mod toolchain;
+pub mod profiles;
+pub mod terminal;
+pub mod partitions;
+pub mod kconfig;
+pub mod size;
+pub mod arduino;
+pub mod search;
+pub mod watcher;
+pub mod workspace;
+pub mod history;
\ No newline at end of file
@@ -0,0 +1,175 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{command, Manager, Window};
+
+use crate::commands::workspace::sandbox;
+use crate::models::partition::{IssueSeverity, PartitionEntry, PartitionIssue, PartitionTable};
+use crate::services::{firmware_image, partition_table, sdkconfig};
+use crate::services::artifacts::artifacts_dir;
+use crate::state::app_state::AppState;
+
+/// The IDF's built-in "Single factory app, no OTA" layout, offered as a
+/// starting point when a project has no table of its own yet.
+const DEFAULT_CSV: &str = "\
+nvs,      data, nvs,     0x9000,  0x6000,
+phy_init, data, phy,     0xf000,  0x1000,
+factory,  app,  factory, 0x10000, 1M,
+";
+
+/// Reads the project's custom `partitions.csv`, falling back to the binary
+/// table of the last build, then to the IDF default layout.
+#[command]
+pub fn read_partition_table(project_path: String, window: Window) -> Result<PartitionTable, String> {
+    let project = sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?;
+    let project = project.as_path();
+    let (table_offset, flash_size) = table_settings(project);
+
+    let csv = custom_csv_path(project);
+    let (entries, source) = if csv.is_file() {
+        let text = fs::read_to_string(&csv).map_err(|e| e.to_string())?;
+        (partition_table::parse_csv(&text, table_offset)?, csv.to_string_lossy().to_string())
+    } else if let Some(bin) = built_table(project) {
+        let data = fs::read(&bin).map_err(|e| e.to_string())?;
+        (partition_table::parse_binary(&data)?, bin.to_string_lossy().to_string())
+    } else {
+        (partition_table::parse_csv(DEFAULT_CSV, table_offset)?, "default".to_string())
+    };
+
+    let issues = partition_table::validate(&entries, table_offset, flash_size);
+    Ok(PartitionTable {
+        entries,
+        source,
+        flash_size,
+        issues,
+    })
+}
+
+#[command]
+pub fn validate_partition_table(
+    project_path: String,
+    entries: Vec<PartitionEntry>,
+    window: Window,
+) -> Result<Vec<PartitionIssue>, String> {
+    let (table_offset, flash_size) = table_settings(&sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?);
+    Ok(partition_table::validate(&entries, table_offset, flash_size))
+}
+
+/// Writes `entries` as the project's custom `partitions.csv` and switches
+/// sdkconfig over to it. Refused while the table has errors.
+#[command]
+pub fn save_partition_table(
+    project_path: String,
+    entries: Vec<PartitionEntry>,
+    window: Window,
+) -> Result<PartitionTable, String> {
+    let sandbox = sandbox(&window, &window.state::<AppState>())?;
+    let project = sandbox.resolve(&project_path)?;
+    let project = project.as_path();
+    let (table_offset, flash_size) = table_settings(project);
+
+    let issues = partition_table::validate(&entries, table_offset, flash_size);
+    let errors: Vec<String> = issues
+        .iter()
+        .filter(|i| i.severity == IssueSeverity::Error)
+        .map(|i| match &i.partition {
+            Some(p) => format!("{}: {}", p, i.message),
+            None => i.message.clone(),
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+
+    // the file name comes from sdkconfig, which could point anywhere
+    let csv = sandbox.resolve(&custom_csv_path(project).to_string_lossy())?;
+    fs::write(&csv, partition_table::to_csv(&entries)).map_err(|e| e.to_string())?;
+
+    let sdkconfig_file = project.join("sdkconfig");
+    if sdkconfig_file.is_file() {
+        let file_name = format!("\"{}\"", csv.strip_prefix(project).unwrap_or(&csv).display());
+        sdkconfig::set_values(
+            &sdkconfig_file,
+            &[
+                ("PARTITION_TABLE_SINGLE_APP", None),
+                ("PARTITION_TABLE_SINGLE_APP_LARGE", None),
+                ("PARTITION_TABLE_TWO_OTA", None),
+                ("PARTITION_TABLE_TWO_OTA_LARGE", None),
+                ("PARTITION_TABLE_CUSTOM", Some("y")),
+                ("PARTITION_TABLE_CUSTOM_FILENAME", Some(&file_name)),
+                ("PARTITION_TABLE_FILENAME", Some(&file_name)),
+            ],
+        )?;
+    }
+
+    Ok(PartitionTable {
+        entries,
+        source: csv.to_string_lossy().to_string(),
+        flash_size,
+        issues,
+    })
+}
+
+/// Converts between `partitions.csv` and the binary format, picking the
+/// direction from the input's extension.
+#[command]
+pub fn convert_partition_table(input_path: String, output_path: String, window: Window) -> Result<(), String> {
+    let sandbox = sandbox(&window, &window.state::<AppState>())?;
+    let input = sandbox.resolve(&input_path)?;
+    let input = input.as_path();
+    let output_path = sandbox.resolve(&output_path)?;
+    let table_offset = sandbox
+        .root_of(input)
+        .map_or(partition_table::DEFAULT_TABLE_OFFSET, |project| table_settings(project).0);
+
+    if input.extension().and_then(|e| e.to_str()) == Some("csv") {
+        let text = fs::read_to_string(input).map_err(|e| e.to_string())?;
+        let entries = partition_table::parse_csv(&text, table_offset)?;
+        if entries.is_empty() {
+            return Err(format!("{} has no partitions", input.display()));
+        }
+        let data = partition_table::to_binary(&entries)?;
+        fs::write(&output_path, data).map_err(|e| e.to_string())
+    } else {
+        let data = fs::read(input).map_err(|e| e.to_string())?;
+        // a merged image carries the table at its flash offset
+        let table = if firmware_image::is_merged_image(&data) {
+            data.get(table_offset as usize..)
+                .ok_or_else(|| format!("{} ends before the partition table at 0x{:x}", input.display(), table_offset))?
+        } else {
+            &data[..]
+        };
+        let entries = partition_table::parse_binary(table)?;
+        if entries.is_empty() {
+            return Err(format!("No partition table found in {}", input.display()));
+        }
+        fs::write(&output_path, partition_table::to_csv(&entries)).map_err(|e| e.to_string())
+    }
+}
+
+/// Table offset and flash size from sdkconfig, with the IDF defaults.
+fn table_settings(project: &Path) -> (u32, Option<u32>) {
+    let sdkconfig_file = project.join("sdkconfig");
+
+    let offset = sdkconfig::read_value(&sdkconfig_file, "PARTITION_TABLE_OFFSET")
+        .and_then(|v| u32::from_str_radix(v.trim_start_matches("0x"), 16).ok())
+        .unwrap_or(partition_table::DEFAULT_TABLE_OFFSET);
+    let flash_size = sdkconfig::read_value(&sdkconfig_file, "ESPTOOLPY_FLASHSIZE")
+        .and_then(|v| partition_table::parse_flash_size(&v));
+
+    (offset, flash_size)
+}
+
+fn custom_csv_path(project: &Path) -> PathBuf {
+    let name = sdkconfig::read_value(&project.join("sdkconfig"), "PARTITION_TABLE_CUSTOM_FILENAME")
+        .unwrap_or_else(|| "partitions.csv".into());
+    project.join(name)
+}
+
+fn built_table(project: &Path) -> Option<PathBuf> {
+    [
+        artifacts_dir(project).join("partition-table.bin"),
+        project.join("build/partition_table/partition-table.bin"),
+    ]
+    .into_iter()
+    .find(|p| p.is_file())
+}
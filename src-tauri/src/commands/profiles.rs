@@ -0,0 +1,45 @@
+use tauri::{command, Manager, Window};
+
+use crate::commands::workspace::sandbox;
+use crate::models::build::{BuildProfile, BuildProfileList};
+use crate::services::{build_profiles, project_settings};
+use crate::state::app_state::AppState;
+
+#[command]
+pub fn list_build_profiles(project_path: String, window: Window) -> Result<BuildProfileList, String> {
+    let settings = project_settings::load(&sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?);
+    Ok(BuildProfileList {
+        profiles: settings.profiles,
+        active: settings.active_profile,
+    })
+}
+
+/// Adds a profile, or replaces the one with the same name.
+#[command]
+pub fn create_build_profile(project_path: String, profile: BuildProfile, window: Window) -> Result<(), String> {
+    build_profiles::validate(&profile)?;
+
+    let project = sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?;
+    let mut settings = project_settings::load(&project);
+
+    settings.profiles.retain(|p| p.name != profile.name);
+    settings.profiles.push(profile);
+    project_settings::save(&project, &settings)
+}
+
+/// Makes `name` the profile `build_project` uses; `None` goes back to plain
+/// `idf.py build` with whatever the project is configured for.
+#[command]
+pub fn activate_build_profile(project_path: String, name: Option<String>, window: Window) -> Result<(), String> {
+    let project = sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?;
+    let mut settings = project_settings::load(&project);
+
+    if let Some(n) = &name {
+        if !settings.profiles.iter().any(|p| &p.name == n) {
+            return Err(format!("Unknown build profile: {}", n));
+        }
+    }
+
+    settings.active_profile = name;
+    project_settings::save(&project, &settings)
+}
@@ -1,9 +1,13 @@
-use tauri::command;
+use tauri::{command, Manager, Window};
 use std::fs;
-use std::process::Command;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use tauri::api::dialog::FileDialogBuilder;
+use crate::models::project::ProjectFramework;
+use crate::commands::workspace::{register_root, sandbox};
+use crate::services::frameworks::{self, CreateOptions};
+use crate::services::workspace;
+use crate::state::app_state::AppState;
 #[derive(Serialize, Deserialize, Clone , Debug)]
 pub struct Project {
     pub name: String,
@@ -49,46 +53,29 @@ pub fn write_recent_projects(projects: Vec<Project>) -> bool {
 }
 
 
+/// Creates a project in ~/esp-projects. `framework` defaults to ESP-IDF;
+/// `fqbn` picks the board for Arduino sketches.
 #[command]
-pub fn create_project(name: String) -> Result<String, String> {
+pub fn create_project(
+    name: String,
+    toolchain_id: Option<String>,
+    framework: Option<ProjectFramework>,
+    fqbn: Option<String>,
+    window: Window,
+) -> Result<String, String> {
     if name.trim().is_empty() {
         return Err("Project name cannot be empty".into());
     }
+    // the name becomes a folder under ~/esp-projects, which is then a sandbox root
+    frameworks::validate_project_name(&name)?;
 
     let home = dirs::home_dir().ok_or("Failed to find home directory")?;
     let base_path = home.join("esp-projects");
 
-   
+    let backend = frameworks::backend(framework.unwrap_or(ProjectFramework::EspIdf))?;
+    let options = CreateOptions { toolchain_id, fqbn };
+    let project_path = backend.create(&base_path, &name, &options)?;
 
-    let idf_path = home.join("esp/esp-idf");
-    let python = home.join(".espressif/python_env/idf6.0_py3.14_env/bin/python");
-    let idf_py = idf_path.join("tools/idf.py");
-
-    if !python.exists() {
-        return Err("ESP-IDF python environment not found".into());
-    }
-
-    // Run create-project in the base_path, idf.py will create the folder
-    let status = Command::new(&python)
-        .arg(idf_py)
-        .arg("create-project")
-        .arg(&name)             // use project name
-        .current_dir(&base_path) 
-        .env("IDF_PATH", &idf_path)
-        .env("PYTHONPATH", "")
-        .status()
-        .map_err(|e| format!("Failed to run idf.py: {}", e))?;
-
-    if !status.success() {
-        return Err(format!(
-            "idf.py create-project failed with exit code: {}",
-            status.code().unwrap_or(-1)
-        ));
-    }
-
-    let project_path = base_path.join(&name);
-
-    
     let mut recent = read_recent_projects();
     recent.retain(|p| p.name != name);
     recent.insert(0, Project {
@@ -100,12 +87,17 @@ pub fn create_project(name: String) -> Result<String, String> {
     }
     write_recent_projects(recent);
 
+    let root = workspace::project_root(&project_path.to_string_lossy())?;
+    register_root(&window, &window.state::<AppState>(), root);
+
     Ok(project_path.to_string_lossy().to_string())
 }
 
 
+/// Lets the user pick a project folder, which is opened in the window and
+/// remembered as one the webview may open again.
 #[tauri::command]
-pub async fn open_project_dialog() -> Result<Option<String>, String> {
+pub async fn open_project_dialog(window: Window) -> Result<Option<String>, String> {
     let (tx, rx) = std::sync::mpsc::channel();
 
     FileDialogBuilder::new()
@@ -113,5 +105,24 @@ pub async fn open_project_dialog() -> Result<Option<String>, String> {
             let _ = tx.send(folder.map(|p| p.to_string_lossy().to_string()));
         });
 
-    rx.recv().map_err(|e| e.to_string())
+    let folder = match rx.recv().map_err(|e| e.to_string())? {
+        Some(folder) => folder,
+        None => return Ok(None),
+    };
+    let root = workspace::project_root(&folder)?;
+    workspace::trust(&root)?;
+    register_root(&window, &window.state::<AppState>(), root);
+    Ok(Some(folder))
+}
+
+/// What kind of project a folder holds, or `None` if it isn't recognised.
+#[command]
+pub fn detect_project_framework(project_path: String, window: Window) -> Result<Option<ProjectFramework>, String> {
+    Ok(frameworks::detect(&sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?))
+}
+
+#[command]
+pub fn clean_project(project_path: String, window: Window) -> Result<(), String> {
+    let project = sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?;
+    frameworks::for_project(&project)?.clean(&project)
 }
@@ -0,0 +1,141 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tauri::{Manager, State, Window};
+use uuid::Uuid;
+
+use crate::models::search::{
+    FileReplacement, ReplacePreview, ReplaceResult, SearchFileResult, SearchOptions, SearchSummary,
+};
+use crate::commands::watcher::record_own_write;
+use crate::commands::workspace::sandbox;
+use crate::services::{replace, search};
+use crate::state::app_state::AppState;
+
+/// Searches the project's text files and returns the search id. Matches
+/// arrive per file as `search-result` events, then one `search-finished`.
+#[tauri::command]
+pub fn search_project(
+    project_path: String,
+    options: SearchOptions,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let root = sandbox(&window, &state)?.resolve(&project_path)?;
+    if !root.is_dir() {
+        return Err(format!("{} is not a folder", project_path));
+    }
+
+    // bad patterns and globs are reported here rather than as an event
+    let regex = search::build_regex(&options)?;
+    let files = search::project_files(&root, &options.include, &options.exclude)?;
+
+    let search_id = Uuid::new_v4().to_string();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    state
+        .searches
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(search_id.clone(), cancelled.clone());
+
+    let id = search_id.clone();
+    thread::spawn(move || {
+        let max_results = options.max_results.unwrap_or(search::DEFAULT_MAX_RESULTS);
+        let mut summary = SearchSummary {
+            search_id: id.clone(),
+            files_searched: 0,
+            files_matched: 0,
+            matches: 0,
+            truncated: false,
+            cancelled: false,
+        };
+
+        for path in files {
+            if cancelled.load(Ordering::Relaxed) {
+                summary.cancelled = true;
+                break;
+            }
+            let text = match search::read_text(&path) {
+                Some(text) => text,
+                None => continue,
+            };
+            summary.files_searched += 1;
+
+            let mut matches = search::find_matches(&text, &regex, options.context_lines);
+            let remaining = max_results - summary.matches;
+            if matches.len() > remaining {
+                matches.truncate(remaining);
+                summary.truncated = true;
+            }
+
+            if !matches.is_empty() {
+                summary.files_matched += 1;
+                summary.matches += matches.len();
+                let _ = window.emit(
+                    "search-result",
+                    SearchFileResult {
+                        search_id: id.clone(),
+                        path: path.to_string_lossy().to_string(),
+                        matches,
+                    },
+                );
+            }
+            if summary.truncated {
+                break;
+            }
+        }
+
+        window.state::<AppState>().searches.lock().unwrap().remove(&id);
+        let _ = window.emit("search-finished", summary);
+    });
+
+    Ok(search_id)
+}
+
+/// Stops a running search; it still ends with `search-finished`.
+#[tauri::command]
+pub fn cancel_search(search_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let searches = state.searches.lock().map_err(|e| e.to_string())?;
+    let cancelled = searches.get(&search_id).ok_or("Unknown or finished search")?;
+    cancelled.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Every edit a project-wide replace would make, with before/after lines.
+/// Nothing is written until `apply_replace`.
+#[tauri::command]
+pub async fn preview_replace(
+    project_path: String,
+    options: SearchOptions,
+    replacement: String,
+    window: Window,
+) -> Result<ReplacePreview, String> {
+    let root = sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?;
+    tauri::async_runtime::spawn_blocking(move || replace::preview(&root, &options, &replacement))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Applies the edits picked from a preview. Files changed on disk since
+/// the preview are rejected, the rest are written all together or not at
+/// all.
+#[tauri::command]
+pub fn apply_replace(
+    project_path: String,
+    files: Vec<FileReplacement>,
+    window: Window,
+) -> Result<ReplaceResult, String> {
+    let state = window.state::<AppState>();
+    let root = sandbox(&window, &state)?.resolve(&project_path)?;
+    let result = replace::apply(&root, &files)?;
+    for path in &result.files_changed {
+        if let Ok(data) = std::fs::read(path) {
+            record_own_write(&state, Path::new(path), &data);
+        }
+    }
+    if !result.files_changed.is_empty() {
+        let _ = window.emit("refresh-project-files", project_path);
+    }
+    Ok(result)
+}
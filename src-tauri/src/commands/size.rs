@@ -0,0 +1,29 @@
+use tauri::{command, Manager, Window};
+
+use crate::commands::workspace::sandbox;
+use crate::models::size::{SizeDiff, SizeReport};
+use crate::services::size_analysis;
+use crate::services::artifacts::build_artifacts_dir;
+use crate::state::app_state::AppState;
+
+/// Memory usage of a collected build, by component, archive, object and
+/// symbol. `build_number` picks `artifacts/build-<n>`; `None` is the latest.
+#[command]
+pub fn analyze_build_size(project_path: String, build_number: Option<u32>, window: Window) -> Result<SizeReport, String> {
+    let dir = build_artifacts_dir(&sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?, build_number)?;
+    size_analysis::report(&dir, build_number)
+}
+
+/// What grew or shrank between `base_build` and `build_number` (latest if `None`).
+#[command]
+pub fn compare_build_sizes(
+    project_path: String,
+    base_build: u32,
+    build_number: Option<u32>,
+    window: Window,
+) -> Result<SizeDiff, String> {
+    let project = sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?;
+    let base_dir = build_artifacts_dir(&project, Some(base_build))?;
+    let dir = build_artifacts_dir(&project, build_number)?;
+    size_analysis::compare(&base_dir, base_build, &dir, build_number)
+}
@@ -0,0 +1,153 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tauri::{Manager, State, Window};
+use uuid::Uuid;
+
+use crate::commands::workspace::sandbox;
+use crate::models::terminal::{TerminalExit, TerminalOutput};
+use crate::services::process_runner::{self, RunOptions};
+use crate::services::{idf_env, toolchain};
+use crate::state::app_state::{AppState, TerminalSession};
+use crate::utils::process::kill_process_group;
+
+/// Starts an interactive bash in `project_path` with the project's ESP-IDF
+/// environment already set, and returns the session id. Output arrives as
+/// `terminal-output` events, the end of the shell as `terminal-exit`.
+#[tauri::command]
+pub fn terminal_spawn(
+    project_path: String,
+    rows: Option<u16>,
+    cols: Option<u16>,
+    window: Window,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let project = sandbox(&window, &state)?.resolve(&project_path)?;
+    let session_id = Uuid::new_v4().to_string();
+
+    // the shell still reads ~/.bashrc, the IDF variables come from the cache
+    let (idf_vars, notice) = match toolchain::for_project(&project)
+        .and_then(|t| idf_env::environment(&t))
+    {
+        Ok(vars) => (vars, None),
+        Err(e) => (Vec::new(), Some(format!("{}\r\n", e))),
+    };
+
+    let opts = RunOptions::new("bash")
+        .arg("-i")
+        .cwd(&project)
+        .envs(idf_vars)
+        .env("TERM", "xterm-256color")
+        .pty(true)
+        .pty_size(rows.unwrap_or(24), cols.unwrap_or(80));
+
+    let mut process = process_runner::spawn(opts)?;
+    let writer = process.take_writer().ok_or("Terminal has no input")?;
+    let master = process.take_master().ok_or("Terminal has no pty")?;
+
+    state.terminals.lock().map_err(|e| e.to_string())?.insert(
+        session_id.clone(),
+        TerminalSession {
+            window_label: window.label().to_string(),
+            pid: process.pid(),
+            writer: Arc::new(Mutex::new(writer)),
+            master,
+        },
+    );
+
+    if let Some(text) = notice {
+        let _ = window.emit(
+            "terminal-output",
+            TerminalOutput {
+                session_id: session_id.clone(),
+                data: text,
+            },
+        );
+    }
+
+    let id = session_id.clone();
+    thread::spawn(move || {
+        let exit = process.wait(|chunk| {
+            let _ = window.emit(
+                "terminal-output",
+                TerminalOutput {
+                    session_id: id.clone(),
+                    data: chunk.data,
+                },
+            );
+        });
+
+        let state = window.state::<AppState>();
+        state.terminals.lock().unwrap().remove(&id);
+
+        let _ = window.emit(
+            "terminal-exit",
+            TerminalExit {
+                session_id: id,
+                code: exit.ok().and_then(|e| e.code),
+            },
+        );
+    });
+
+    Ok(session_id)
+}
+
+#[tauri::command]
+pub fn terminal_write(
+    session_id: String,
+    data: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let writer = {
+        let terminals = state.terminals.lock().map_err(|e| e.to_string())?;
+        let session = terminals.get(&session_id).ok_or("Unknown terminal session")?;
+        session.writer.clone()
+    };
+
+    let mut writer = writer.lock().map_err(|e| e.to_string())?;
+    writer.write_all(data.as_bytes()).map_err(|e| e.to_string())?;
+    writer.flush().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn terminal_resize(
+    session_id: String,
+    rows: u16,
+    cols: u16,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let terminals = state.terminals.lock().map_err(|e| e.to_string())?;
+    let session = terminals.get(&session_id).ok_or("Unknown terminal session")?;
+
+    session
+        .master
+        .resize(portable_pty::PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn terminal_kill(session_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let terminals = state.terminals.lock().map_err(|e| e.to_string())?;
+    let session = terminals.get(&session_id).ok_or("Unknown terminal session")?;
+
+    match session.pid {
+        Some(pid) => kill_process_group(pid),
+        None => Ok(()),
+    }
+}
+
+/// Kills every session a window opened; called when the window goes away.
+pub fn kill_window_sessions(window: &Window) {
+    let state = window.state::<AppState>();
+    let terminals = state.terminals.lock().unwrap();
+
+    for session in terminals.values().filter(|s| s.window_label == window.label()) {
+        if let Some(pid) = session.pid {
+            let _ = kill_process_group(pid);
+        }
+    }
+}
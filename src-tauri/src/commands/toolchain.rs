@@ -0,0 +1,42 @@
+use tauri::{command, Manager, Window};
+
+use crate::commands::workspace::sandbox;
+use crate::models::toolchain::Toolchain;
+use crate::services::{idf_env, project_settings, toolchain};
+use crate::state::app_state::AppState;
+
+#[command]
+pub fn list_toolchains() -> Vec<Toolchain> {
+    toolchain::discover()
+}
+
+/// Pins `project_path` to a toolchain id from `list_toolchains`, or unpins it
+/// (falling back to the default install) when `toolchain_id` is `None`.
+#[command]
+pub fn set_project_toolchain(
+    project_path: String,
+    toolchain_id: Option<String>,
+    window: Window,
+) -> Result<(), String> {
+    let project = sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?;
+
+    if let Some(id) = &toolchain_id {
+        toolchain::find(id)?;
+    }
+
+    let mut settings = project_settings::load(&project);
+    settings.toolchain_id = toolchain_id;
+    project_settings::save(&project, &settings)
+}
+
+#[command]
+pub fn get_project_toolchain(project_path: String, window: Window) -> Result<Toolchain, String> {
+    toolchain::for_project(&sandbox(&window, &window.state::<AppState>())?.resolve(&project_path)?)
+}
+
+/// Forgets the cached export.sh environment of a toolchain, e.g. after
+/// re-running its `install.sh`.
+#[command]
+pub fn refresh_toolchain_env(toolchain_id: String) -> Result<(), String> {
+    idf_env::invalidate(&toolchain::find(&toolchain_id)?)
+}
@@ -1,12 +1,19 @@
 use std::path::Path;
-use crate::services::s3;
+use crate::services::{firmware_image, s3};
 
+/// Uploads a firmware image for the lab controllers. The image is checked
+/// first, and refused if it is corrupt or built for another chip than
+/// `expected_chip`.
 #[tauri::command]
 pub async fn upload_bin(
     bucket: String,
     key: String,
     bin_path: String,
+    expected_chip: Option<String>,
 ) -> Result<String, String> {
-    s3::upload(&bucket, &key, Path::new(&bin_path)).await?;
+    let path = Path::new(&bin_path);
+    firmware_image::validate_for_flash(path, expected_chip.as_deref())?;
+
+    s3::upload(&bucket, &key, path).await?;
     Ok("Upload successful".into())
 }
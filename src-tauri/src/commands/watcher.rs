@@ -0,0 +1,117 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tauri::{Manager, State, Window};
+
+use crate::commands::workspace::sandbox;
+use crate::models::watcher::{FsChange, FsChangeBatch, FsChangeKind};
+use crate::services::fs_watcher;
+use crate::state::app_state::{AppState, OwnChange, ProjectWatcher};
+use crate::utils::fs::content_hash;
+
+/// How long an own change waits for the watcher to report it.
+const OWN_CHANGE_TTL: Duration = Duration::from_secs(30);
+
+/// Watches the project open in this window, replacing the window's
+/// previous watch. Changes arrive in batches as `fs-changes`; files that
+/// something other than the editor modified or removed are also sent as
+/// `file-changed-externally` so open editors can reload.
+#[tauri::command]
+pub fn watch_project(project_path: String, window: Window, state: State<'_, AppState>) -> Result<(), String> {
+    let mut watchers = state.watchers.lock().map_err(|e| e.to_string())?;
+    if watchers.get(window.label()).is_some_and(|w| w.project_path == project_path) {
+        return Ok(());
+    }
+
+    let root = sandbox(&window, &state)?.resolve(&project_path)?;
+    let emitter = window.clone();
+    let project = project_path.clone();
+    let watcher = fs_watcher::watch(&root, move |changes| {
+        for change in changes.iter().filter(|c| is_external(&emitter, c)) {
+            let _ = emitter.emit("file-changed-externally", change.clone());
+        }
+        let _ = emitter.emit(
+            "fs-changes",
+            FsChangeBatch {
+                project_path: project.clone(),
+                changes,
+            },
+        );
+    })?;
+
+    watchers.insert(
+        window.label().to_string(),
+        ProjectWatcher {
+            project_path,
+            _watcher: watcher,
+        },
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unwatch_project(window: Window, state: State<'_, AppState>) -> Result<(), String> {
+    state.watchers.lock().map_err(|e| e.to_string())?.remove(window.label());
+    Ok(())
+}
+
+/// Stops the window's watch; called when the window goes away.
+pub fn stop_window_watcher(window: &Window) {
+    let state = window.state::<AppState>();
+    state.watchers.lock().unwrap().remove(window.label());
+}
+
+/// Remembers what the editor wrote to `path` so the watcher doesn't report
+/// it back as an external change.
+pub fn record_own_write(state: &AppState, path: &Path, data: &[u8]) {
+    record_own_change(state, path, Some(content_hash(data)));
+}
+
+/// Remembers that the editor deleted or moved away `path` (a file or a
+/// folder); call it before the path is gone.
+pub fn record_own_removal(state: &AppState, path: &Path) {
+    record_own_change(state, path, None);
+}
+
+fn record_own_change(state: &AppState, path: &Path, hash: Option<String>) {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut own_changes = state.own_changes.lock().unwrap();
+    // changes made while nothing watches are never seen
+    own_changes.retain(|_, c| c.at.elapsed() < OWN_CHANGE_TTL);
+    own_changes.insert(path, OwnChange { hash, at: Instant::now() });
+}
+
+/// A modified or removed file that the editor didn't write or remove
+/// itself.
+fn is_external(window: &Window, change: &FsChange) -> bool {
+    let state = window.state::<AppState>();
+    let mut own_changes = state.own_changes.lock().unwrap();
+    let path = Path::new(&change.path);
+
+    match change.kind {
+        FsChangeKind::Removed if !change.is_dir => {
+            // the file itself or one of its folders
+            let removed = path.ancestors().find(|p| matches!(own_changes.get(*p), Some(OwnChange { hash: None, .. })));
+            match removed {
+                Some(p) => {
+                    if p == path {
+                        own_changes.remove(path);
+                    }
+                    false
+                }
+                None => true,
+            }
+        }
+        FsChangeKind::Modified if !change.is_dir => match own_changes.get(path) {
+            Some(OwnChange { hash: Some(hash), .. }) => {
+                let own = fs::read(path).is_ok_and(|data| &content_hash(&data) == hash);
+                if own {
+                    own_changes.remove(path);
+                }
+                !own
+            }
+            _ => true,
+        },
+        _ => false,
+    }
+}
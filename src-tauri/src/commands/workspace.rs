@@ -0,0 +1,74 @@
+use std::path::{Path, PathBuf};
+use tauri::{Manager, State, Window};
+
+use crate::models::workspace::{FsError, FsErrorKind};
+use crate::services::workspace::{self, Sandbox};
+use crate::state::app_state::AppState;
+
+/// The sandbox for paths coming from `window`.
+pub fn sandbox(window: &Window, state: &AppState) -> Result<Sandbox, FsError> {
+    let workspaces = state.workspaces.lock().unwrap();
+    match workspaces.get(window.label()) {
+        Some(roots) if !roots.is_empty() => Ok(Sandbox::new(roots.clone())),
+        _ => Err(FsError {
+            kind: FsErrorKind::NoProjectOpen,
+            path: None,
+            message: "No project is open in this window".into(),
+        }),
+    }
+}
+
+/// Adds `root` (canonical) to the window's open projects and to the
+/// webview's fs scope.
+pub fn register_root(window: &Window, state: &AppState, root: PathBuf) {
+    let _ = window.fs_scope().allow_directory(&root, true);
+    let mut workspaces = state.workspaces.lock().unwrap();
+    let roots = workspaces.entry(window.label().to_string()).or_default();
+    if !roots.contains(&root) {
+        roots.push(root);
+    }
+}
+
+/// Opens `project_path` in this window so the filesystem commands accept
+/// paths inside it. Only projects in ~/esp-projects, ones created or picked
+/// in the folder dialog, and ones already open are allowed. Returns the
+/// canonical project path.
+#[tauri::command]
+pub fn open_project(project_path: String, window: Window, state: State<'_, AppState>) -> Result<String, FsError> {
+    let root = workspace::project_root(&project_path)?;
+    let already_open = state
+        .workspaces
+        .lock()
+        .unwrap()
+        .values()
+        .any(|roots| roots.contains(&root));
+    if !already_open && !workspace::is_trusted(&root) {
+        return Err(FsError::new(
+            FsErrorKind::OutsideWorkspace,
+            &root,
+            format!("{} hasn't been opened through the folder dialog", project_path),
+        ));
+    }
+
+    register_root(&window, &state, root.clone());
+    Ok(root.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub fn close_project(project_path: String, window: Window, state: State<'_, AppState>) -> Result<(), FsError> {
+    let root = Path::new(&project_path)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(&project_path));
+    if let Some(roots) = state.workspaces.lock().unwrap().get_mut(window.label()) {
+        roots.retain(|r| r != &root);
+    }
+    Ok(())
+}
+
+/// Drops the window's open projects and explorer history; called when
+/// the window goes away.
+pub fn forget_window(window: &Window) {
+    let state = window.state::<AppState>();
+    state.workspaces.lock().unwrap().remove(window.label());
+    state.explorer_history.lock().unwrap().remove(window.label());
+}
@@ -51,6 +51,9 @@ fn main() {
             commands::project::get_recent_file_path,
             commands::project::read_recent_projects,
             commands::build::build_project,
+            commands::build::cancel_build,
+            commands::build::get_build_status,
+            commands::build::get_last_build_diagnostics,
             commands::artifacts::get_build_artifacts,
             commands::upload::upload_bin,
            
@@ -71,7 +74,65 @@ fn main() {
             commands::refresh::refresh_token,
             commands::controllers::get_student_controllers,
             commands::select_controller::select_controller,
-            commands::release_controller::release_controller
+            commands::release_controller::release_controller,
+            commands::toolchain::list_toolchains,
+            commands::toolchain::set_project_toolchain,
+            commands::toolchain::get_project_toolchain,
+            commands::toolchain::refresh_toolchain_env,
+            commands::profiles::list_build_profiles,
+            commands::profiles::create_build_profile,
+            commands::profiles::activate_build_profile,
+            commands::terminal::terminal_spawn,
+            commands::terminal::terminal_write,
+            commands::terminal::terminal_resize,
+            commands::terminal::terminal_kill,
+            commands::partitions::read_partition_table,
+            commands::partitions::validate_partition_table,
+            commands::partitions::save_partition_table,
+            commands::partitions::convert_partition_table,
+            commands::kconfig::kconfig_load,
+            commands::kconfig::kconfig_search,
+            commands::kconfig::kconfig_set_value,
+            commands::kconfig::kconfig_save,
+            commands::size::analyze_build_size,
+            commands::size::compare_build_sizes,
+            commands::project::detect_project_framework,
+            commands::project::clean_project,
+            commands::artifacts::get_flash_layout,
+            commands::arduino::get_project_fqbn,
+            commands::arduino::set_project_fqbn,
+            commands::arduino::preprocess_sketch,
+            commands::arduino::get_arduino_settings,
+            commands::arduino::set_arduino_settings,
+            commands::arduino::search_arduino_libraries,
+            commands::arduino::list_arduino_libraries,
+            commands::arduino::install_arduino_library,
+            commands::arduino::search_arduino_platforms,
+            commands::arduino::list_arduino_platforms,
+            commands::arduino::install_arduino_platform,
+            commands::search::search_project,
+            commands::search::cancel_search,
+            commands::search::preview_replace,
+            commands::search::apply_replace,
+            commands::watcher::watch_project,
+            commands::watcher::unwatch_project,
+            commands::explorer::list_directory,
+            commands::explorer::set_explorer_excludes,
+            commands::workspace::open_project,
+            commands::workspace::close_project,
+            commands::explorer::list_trash,
+            commands::explorer::restore_from_trash,
+            commands::explorer::empty_trash,
+            commands::explorer::undo_explorer_operation,
+            commands::explorer::move_path,
+            commands::explorer::copy_path,
+            commands::explorer::duplicate_path,
+            commands::editor_state::open_file,
+            commands::history::list_file_history,
+            commands::history::read_file_version,
+            commands::history::diff_file_version,
+            commands::history::restore_file_version,
+            commands::explorer::read_file_bytes
         ])
        .on_menu_event(|event| {
     let window = event.window();
@@ -104,6 +165,13 @@ fn main() {
     }
 })
 
+        .on_window_event(|event| {
+            if let tauri::WindowEvent::Destroyed = event.event() {
+                commands::terminal::kill_window_sessions(event.window());
+                commands::watcher::stop_window_watcher(event.window());
+                commands::workspace::forget_window(event.window());
+            }
+        })
         .run(tauri::generate_context!())
         .expect("error running tauri app");
 }
@@ -0,0 +1,76 @@
+use serde::{Serialize , Deserialize};
+
+/// Where the Arduino library/board manager reads indexes from and installs
+/// to; kept in `~/.esp-projects/arduino.json`.
+#[derive(Debug , Clone , Default , Serialize , Deserialize)]
+#[serde(default)]
+
+pub struct ArduinoSettings {
+    /// Local copy of the Arduino download server: `library_index.json`,
+    /// `package_*index.json` and the archives they list.
+    pub mirror_dir : Option<String>,
+    /// Shared libraries folder, `~/Arduino/libraries` when unset.
+    pub libraries_dir : Option<String>,
+    /// Board cores and tools, the arduino-cli data folder when unset.
+    pub data_dir : Option<String>,
+}
+
+#[derive(Debug , Clone , Copy , PartialEq , Serialize , Deserialize)]
+
+pub enum LibraryLocation {
+    /// `<project>/libraries`, passed to arduino-cli with `--libraries`.
+    Project,
+    Shared
+}
+
+/// A library in the mirror's index, at its latest version.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct LibraryRelease {
+    pub name : String,
+    pub version : String,
+    pub author : Option<String>,
+    pub sentence : Option<String>,
+    pub category : Option<String>,
+    pub architectures : Vec<String>,
+    pub dependencies : Vec<String>,
+    /// Every version in the index, newest first.
+    pub versions : Vec<String>,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct InstalledLibrary {
+    pub name : String,
+    pub version : String,
+    pub location : LibraryLocation,
+    pub path : String,
+    /// Newest version in the mirror, when the library is in it.
+    pub latest_version : Option<String>,
+    pub updatable : bool,
+}
+
+/// A board core in the mirror's package indexes, at its latest version.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct PlatformRelease {
+    /// `packager:architecture`, e.g. `esp32:esp32`.
+    pub id : String,
+    pub name : String,
+    pub maintainer : Option<String>,
+    pub version : String,
+    pub boards : Vec<String>,
+    /// Every version in the indexes, newest first.
+    pub versions : Vec<String>,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct InstalledPlatform {
+    pub id : String,
+    pub name : String,
+    pub version : String,
+    pub path : String,
+    pub latest_version : Option<String>,
+    pub updatable : bool,
+}
@@ -7,4 +7,63 @@ pub struct Artifact {
     pub name : String ,
     pub path : String,
     pub size_bytes : u64 ,
+    /// Parsed image header, for files that are ESP firmware images.
+    pub image : Option<FirmwareImage>,
+}
+
+/// `manifest.json` written next to the files of each `artifacts/build-<n>/`.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct ArtifactManifest {
+    pub build_number : u32,
+    pub created_at : u64,
+    pub project_name : Option<String>,
+    pub files : Vec<String>,
+}
+
+#[derive(Debug , Clone , PartialEq , Serialize , Deserialize)]
+
+pub enum ImageKind {
+    App,
+    Bootloader,
+    /// bootloader + partition table + app, as written by `idf.py merge-bin`.
+    Merged,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct ImageSegment {
+    pub load_address : u32,
+    pub offset : u32,
+    pub length : u32,
+}
+
+/// `esp_app_desc_t`, embedded at the start of an app's first segment.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct AppDescription {
+    pub project_name : String,
+    pub version : String,
+    pub idf_version : String,
+    pub compile_date : String,
+    pub compile_time : String,
+    pub secure_version : u32,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct FirmwareImage {
+    pub kind : ImageKind,
+    pub chip : Option<String>,
+    pub chip_id : u16,
+    pub entry_address : u32,
+    /// Where the app image starts inside the file (non-zero for merged binaries).
+    pub app_offset : u32,
+    pub segments : Vec<ImageSegment>,
+    pub checksum_valid : bool,
+    /// `None` when the image has no appended SHA-256.
+    pub sha256_valid : Option<bool>,
+    pub app_description : Option<AppDescription>,
+    /// Everything that makes the image unsafe to flash; empty when valid.
+    pub errors : Vec<String>,
 }
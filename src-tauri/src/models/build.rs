@@ -1,16 +1,59 @@
 use serde::{Serialize , Deserialize};
 
-#[derive(Debug , Serialize , Deserialize)]
+#[derive(Debug , Clone , PartialEq , Serialize , Deserialize)]
 
 pub enum BuildStatus {
     Idle,
     Building,
     Success,
-    Failed
+    Failed,
+    Cancelled
 }
 
 #[derive(Debug, Serialize , Deserialize)]
 
 pub struct BuildRequest {
     pub project_path : String
-}
\ No newline at end of file
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct BuildJobInfo {
+    pub job_id : String,
+    pub project_path : String,
+    pub status : BuildStatus,
+    pub exit_code : Option<i32>,
+}
+
+#[derive(Debug , Clone , Copy , PartialEq , Default , Serialize , Deserialize)]
+
+pub enum OptimizationLevel {
+    #[default]
+    Debug,
+    Size,
+    Performance,
+    None
+}
+
+/// A named set of build settings stored in the project's settings file.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct BuildProfile {
+    pub name : String,
+    pub target : String,
+    #[serde(default)]
+    pub optimization : OptimizationLevel,
+    /// Extra `-D NAME=VALUE` cmake defines, given as `NAME=VALUE`.
+    #[serde(default)]
+    pub defines : Vec<String>,
+    /// sdkconfig defaults files, relative to the project root.
+    #[serde(default)]
+    pub sdkconfig_defaults : Vec<String>,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct BuildProfileList {
+    pub profiles : Vec<BuildProfile>,
+    pub active : Option<String>,
+}
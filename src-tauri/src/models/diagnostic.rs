@@ -0,0 +1,29 @@
+use serde::{Serialize , Deserialize};
+
+#[derive(Debug , Clone , PartialEq , Serialize , Deserialize)]
+
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Note,
+}
+
+#[derive(Debug , Clone , PartialEq , Serialize , Deserialize)]
+
+pub struct DiagnosticNote {
+    pub file : Option<String>,
+    pub line : Option<u32>,
+    pub column : Option<u32>,
+    pub message : String,
+}
+
+#[derive(Debug , Clone , PartialEq , Serialize , Deserialize)]
+
+pub struct Diagnostic {
+    pub file : Option<String>,
+    pub line : Option<u32>,
+    pub column : Option<u32>,
+    pub severity : DiagnosticSeverity,
+    pub message : String,
+    pub notes : Vec<DiagnosticNote>,
+}
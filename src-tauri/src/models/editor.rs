@@ -0,0 +1,47 @@
+use serde::{Serialize , Deserialize};
+
+/// Which version of a file the editor has; sent back with a save so
+/// changes made on disk in the meantime aren't overwritten.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct FileVersion {
+    /// SHA-256 of the file's bytes, hex.
+    pub hash : String,
+    /// Milliseconds since the epoch.
+    pub modified : Option<u64>,
+}
+
+/// A text file opened for editing.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct OpenedFile {
+    pub content : String,
+    pub encoding : TextEncoding,
+    pub version : FileVersion,
+}
+
+/// How a text file is stored on disk; files are saved back the same way.
+#[derive(Debug , Clone , Copy , PartialEq , Default , Serialize , Deserialize)]
+
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    /// UTF-8 starting with a byte order mark.
+    Utf8Bom,
+    /// UTF-16 with a byte order mark, little endian.
+    Utf16Le,
+    /// UTF-16 with a byte order mark, big endian.
+    Utf16Be,
+    /// ISO-8859-1, for text that isn't valid UTF-8.
+    Latin1
+}
+
+/// A slice of a file, for the hex viewer.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct FileChunk {
+    pub offset : u64,
+    pub bytes : Vec<u8>,
+    /// Size of the whole file.
+    pub file_size : u64,
+}
@@ -0,0 +1,80 @@
+use serde::{Serialize , Deserialize};
+
+/// A file or folder in the project tree. Folders come with `children: None`
+/// until they are listed with `list_directory`; a folder that can't be
+/// expanded (a symlink loop or one leading out of the project) has no
+/// children.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct ExplorerNode {
+    pub id : String,
+    pub name : String,
+    pub path : String,
+    #[serde(rename = "type")]
+    pub node_type : String,
+    pub children : Option<Vec<ExplorerNode>>,
+    /// Size in bytes, files only.
+    pub size : Option<u64>,
+    /// Last modification, in milliseconds since the epoch.
+    pub modified : Option<u64>,
+    /// Matched by a .gitignore (or .git/info/exclude).
+    pub ignored : bool,
+    /// Build output or downloaded dependencies, like build/ or
+    /// managed_components/.
+    pub generated : bool,
+    pub is_symlink : bool,
+}
+
+/// An explorer operation that `undo_explorer_operation` can revert.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+#[serde(tag = "op")]
+
+pub enum ExplorerOperation {
+    /// `path` is the outermost file or folder that didn't exist before;
+    /// `replaced` is the trash id of the entry a copy overwrote.
+    Create {
+        path: String,
+        #[serde(default)]
+        replaced: Option<String>,
+    },
+    Delete { project_path: String, trash_id: String, path: String },
+    Rename { from: String, to: String },
+    Move { moves: Vec<MovedPath> },
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct MovedPath {
+    pub from : String,
+    pub to : String,
+    /// Trash id of the entry the move overwrote at `to`.
+    #[serde(default)]
+    pub replaced : Option<String>,
+}
+
+/// What `move_path` and `copy_path` do when the target folder already has
+/// an entry with the same name.
+#[derive(Debug , Clone , Copy , PartialEq , Default , Serialize , Deserialize)]
+
+pub enum ConflictPolicy {
+    /// The existing entry goes to the trash.
+    Overwrite,
+    Skip,
+    /// The new entry gets a free name (`main copy.c`, `main copy 2.c`).
+    #[default]
+    Rename
+}
+
+/// Sent as `transfer-progress` while a move or copy runs, and once more
+/// with `done` set.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct TransferProgress {
+    pub source : String,
+    pub destination : String,
+    pub files_done : u64,
+    pub files_total : u64,
+    pub bytes_done : u64,
+    pub bytes_total : u64,
+    pub done : bool,
+}
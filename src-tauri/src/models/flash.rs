@@ -7,3 +7,23 @@ pub struct FlashRequest {
     pub firmware_url : String,
     pub timeout_minutes : u32 
 }
+
+/// One file of a multi-part flash, written at `offset`.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct FlashImage {
+    pub offset : u32,
+    pub path : String,
+}
+
+/// Where each output file of a build goes in flash, plus the settings the
+/// images were built for.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct FlashLayout {
+    pub chip : Option<String>,
+    pub flash_mode : Option<String>,
+    pub flash_freq : Option<String>,
+    pub flash_size : Option<String>,
+    pub images : Vec<FlashImage>,
+}
@@ -0,0 +1,48 @@
+use serde::{Serialize , Deserialize};
+
+/// One saved version of a file, kept in `<project>/.veditor/history`.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct HistoryEntry {
+    pub id : String,
+    /// The file, relative to the project folder.
+    pub path : String,
+    /// SHA-256 of the content, hex.
+    pub hash : String,
+    pub size : u64,
+    /// Size of the compressed snapshot, counted against the history limit.
+    pub stored_size : u64,
+    /// Milliseconds since the epoch.
+    pub saved_at : u64,
+}
+
+#[derive(Debug , Clone , Copy , PartialEq , Serialize , Deserialize)]
+
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct DiffLine {
+    pub kind : DiffLineKind,
+    /// 1-based line in the old version, none for added lines.
+    pub old_line : Option<usize>,
+    /// 1-based line in the new version, none for removed lines.
+    pub new_line : Option<usize>,
+    pub text : String,
+}
+
+/// A run of changes with a few lines of context, like a hunk of a unified
+/// diff.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct DiffHunk {
+    pub old_start : usize,
+    pub old_lines : usize,
+    pub new_start : usize,
+    pub new_lines : usize,
+    pub lines : Vec<DiffLine>,
+}
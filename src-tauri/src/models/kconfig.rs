@@ -0,0 +1,61 @@
+use serde::{Serialize , Deserialize};
+
+#[derive(Debug , Clone , Copy , PartialEq , Serialize , Deserialize)]
+
+pub enum KconfigType {
+    Bool,
+    Tristate,
+    Int,
+    Hex,
+    String,
+    Unknown
+}
+
+#[derive(Debug , Clone , PartialEq , Serialize , Deserialize)]
+
+pub enum KconfigNodeKind {
+    Menu,
+    Symbol,
+    Choice,
+    Comment
+}
+
+/// One entry of the configuration menu, as shown by menuconfig.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct KconfigNode {
+    pub id : usize,
+    pub kind : KconfigNodeKind,
+    pub title : String,
+    pub symbol : Option<String>,
+    pub symbol_type : Option<KconfigType>,
+    pub value : Option<String>,
+    /// Whether the user changed the value (it is not the default).
+    pub user_set : bool,
+    pub visible : bool,
+    pub help : Option<String>,
+    pub depends_on : Option<String>,
+    pub selects : Vec<String>,
+    pub selected_by : Vec<String>,
+    pub defaults : Vec<String>,
+    pub range : Option<(String, String)>,
+    pub children : Vec<KconfigNode>,
+}
+
+/// A symbol's state after a change, so the UI can update in place.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct KconfigSymbolState {
+    pub name : String,
+    pub value : String,
+    pub visible : bool,
+}
+
+/// Result of loading a project's configuration: the menu tree plus any
+/// Kconfig lines that couldn't be parsed.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct KconfigMenu {
+    pub root : KconfigNode,
+    pub warnings : Vec<String>,
+}
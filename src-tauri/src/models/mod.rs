@@ -1,7 +1,22 @@
 pub mod payloads;
+pub mod arduino;
 pub mod artifact;
 pub mod build;
 pub mod controller;
+pub mod diagnostic;
+pub mod editor;
+pub mod explorer;
 pub mod flash;
+pub mod history;
+pub mod kconfig;
 pub mod nats;
+pub mod partition;
 pub mod project;
+pub mod search;
+pub mod size;
+pub mod sketch;
+pub mod terminal;
+pub mod toolchain;
+pub mod trash;
+pub mod watcher;
+pub mod workspace;
@@ -0,0 +1,43 @@
+use serde::{Serialize , Deserialize};
+
+#[derive(Debug , Clone , PartialEq , Serialize , Deserialize)]
+
+pub struct PartitionEntry {
+    pub name : String,
+    /// "app", "data", or a number for custom types.
+    #[serde(rename = "type")]
+    pub partition_type : String,
+    /// "factory", "ota_0", "nvs", ... or a number.
+    pub subtype : String,
+    pub offset : u32,
+    pub size : u32,
+    #[serde(default)]
+    pub encrypted : bool,
+    #[serde(default)]
+    pub readonly : bool,
+}
+
+#[derive(Debug , Clone , PartialEq , Serialize , Deserialize)]
+
+pub enum IssueSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct PartitionIssue {
+    pub severity : IssueSeverity,
+    pub partition : Option<String>,
+    pub message : String,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct PartitionTable {
+    pub entries : Vec<PartitionEntry>,
+    /// File the table was read from (CSV or binary).
+    pub source : String,
+    pub flash_size : Option<u32>,
+    pub issues : Vec<PartitionIssue>,
+}
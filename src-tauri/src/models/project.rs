@@ -1,5 +1,7 @@
 use serde::{Serialize , Deserialize};
 
+use crate::models::build::BuildProfile;
+
 #[derive(Debug , Serialize , Deserialize)]
 
 pub struct Project {
@@ -7,8 +9,26 @@ pub struct Project {
     pub path : String,
 }
 
-#[derive(Debug , Serialize , Deserialize)]
+#[derive(Debug , Clone , Copy , PartialEq , Serialize , Deserialize)]
 
 pub enum ProjectFramework {
-    EspIdf
-}
\ No newline at end of file
+    EspIdf,
+    Arduino,
+    PlatformIo,
+    /// Cargo project on the esp-rs crates (esp-idf-svc, esp-hal, ...).
+    RustEsp
+}
+
+/// Per-project settings, kept in `<project>/.veditor/project.json`.
+#[derive(Debug , Clone , Default , Serialize , Deserialize)]
+#[serde(default)]
+
+pub struct ProjectSettings {
+    pub toolchain_id : Option<String>,
+    pub profiles : Vec<BuildProfile>,
+    pub active_profile : Option<String>,
+    /// Board Arduino sketches are compiled for.
+    pub fqbn : Option<String>,
+    /// Globs hidden from the project tree, in .gitignore syntax.
+    pub explorer_excludes : Vec<String>,
+}
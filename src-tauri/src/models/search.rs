@@ -0,0 +1,98 @@
+use serde::{Serialize , Deserialize};
+
+#[derive(Debug , Clone , Default , Serialize , Deserialize)]
+#[serde(default)]
+
+pub struct SearchOptions {
+    pub query : String,
+    /// `query` is a regular expression instead of literal text.
+    pub regex : bool,
+    pub case_sensitive : bool,
+    pub whole_word : bool,
+    /// Globs relative to the project root; only matching files are searched
+    /// when any are given. A trailing `/` means the whole folder.
+    pub include : Vec<String>,
+    pub exclude : Vec<String>,
+    /// Lines of context around each match, 0 by default.
+    pub context_lines : usize,
+    /// Stop after this many matches, 10000 by default.
+    pub max_results : Option<usize>,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct SearchMatch {
+    /// 1-based line and column; columns count characters.
+    pub line : usize,
+    pub column : usize,
+    /// Length of the match in characters.
+    pub length : usize,
+    pub text : String,
+    pub before : Vec<String>,
+    pub after : Vec<String>,
+}
+
+/// Matches in one file, sent as a `search-result` event.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct SearchFileResult {
+    pub search_id : String,
+    pub path : String,
+    pub matches : Vec<SearchMatch>,
+}
+
+/// Sent as `search-finished` once the walk ends or is cancelled.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct SearchSummary {
+    pub search_id : String,
+    pub files_searched : usize,
+    pub files_matched : usize,
+    pub matches : usize,
+    /// The match limit was hit.
+    pub truncated : bool,
+    pub cancelled : bool,
+}
+
+/// One replacement in a file. `start`/`end` are byte offsets of the match
+/// in the file as it was previewed.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct ReplaceEdit {
+    pub line : usize,
+    pub column : usize,
+    pub start : usize,
+    pub end : usize,
+    pub replacement : String,
+    /// The line as it is and as it would be with only this edit applied.
+    pub before : String,
+    pub after : String,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct FileReplacement {
+    pub path : String,
+    /// Hash of the file the edits were computed from; applying is refused
+    /// when the file no longer matches it.
+    pub hash : String,
+    pub edits : Vec<ReplaceEdit>,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct ReplacePreview {
+    pub files : Vec<FileReplacement>,
+    pub edits : usize,
+    /// The match limit was hit; not every match has an edit.
+    pub truncated : bool,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct ReplaceResult {
+    pub files_changed : Vec<String>,
+    pub edits_applied : usize,
+    /// Files left alone because they changed since the preview.
+    pub rejected : Vec<String>,
+}
@@ -0,0 +1,97 @@
+use serde::{Serialize , Deserialize};
+
+/// Memory a section ends up in, following the output section names of the
+/// ESP-IDF linker scripts.
+#[derive(Debug , Clone , Copy , PartialEq , Eq , Hash , Serialize , Deserialize)]
+
+pub enum MemoryKind {
+    Iram,
+    Dram,
+    FlashCode,
+    FlashData,
+    Rtc,
+    ExtRam,
+    Other
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct MemoryUsage {
+    pub kind : MemoryKind,
+    pub used : u64,
+    /// Size of the linker memory regions holding this kind, when the map has them.
+    pub total : Option<u64>,
+    pub regions : Vec<String>,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct SectionSize {
+    pub name : String,
+    pub address : u32,
+    pub size : u64,
+    pub kind : MemoryKind,
+}
+
+/// Bytes one component, archive or object file contributes to each memory.
+#[derive(Debug , Clone , Default , Serialize , Deserialize)]
+
+pub struct SizeEntry {
+    pub name : String,
+    pub iram : u64,
+    pub dram : u64,
+    pub flash_code : u64,
+    pub flash_data : u64,
+    pub rtc : u64,
+    pub ext_ram : u64,
+    pub other : u64,
+    pub total : u64,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct SymbolSize {
+    pub name : String,
+    pub size : u64,
+    pub address : u32,
+    pub section : String,
+    pub kind : MemoryKind,
+    pub archive : Option<String>,
+    pub object : Option<String>,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct SizeReport {
+    pub build_number : Option<u32>,
+    pub elf : String,
+    pub memory : Vec<MemoryUsage>,
+    pub sections : Vec<SectionSize>,
+    pub components : Vec<SizeEntry>,
+    pub archives : Vec<SizeEntry>,
+    pub objects : Vec<SizeEntry>,
+    pub largest_symbols : Vec<SymbolSize>,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct SizeDelta {
+    pub name : String,
+    pub before : u64,
+    pub after : u64,
+    pub delta : i64,
+}
+
+/// What changed between two builds; only entries whose size changed, the
+/// biggest changes first.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct SizeDiff {
+    pub base_build : u32,
+    pub build_number : Option<u32>,
+    pub memory : Vec<SizeDelta>,
+    pub components : Vec<SizeDelta>,
+    pub archives : Vec<SizeDelta>,
+    pub objects : Vec<SizeDelta>,
+    pub symbols : Vec<SizeDelta>,
+}
@@ -0,0 +1,12 @@
+use serde::{Serialize , Deserialize};
+
+/// An Arduino sketch merged into a single C++ translation unit.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct PreprocessedSketch {
+    pub source : String,
+    /// Prototypes that were generated, in the order they were inserted.
+    pub prototypes : Vec<String>,
+    /// Tabs in the order they were merged, main tab first.
+    pub tabs : Vec<String>,
+}
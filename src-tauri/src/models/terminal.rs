@@ -0,0 +1,15 @@
+use serde::{Serialize , Deserialize};
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct TerminalOutput {
+    pub session_id : String,
+    pub data : String,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct TerminalExit {
+    pub session_id : String,
+    pub code : Option<i32>,
+}
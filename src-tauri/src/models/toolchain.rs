@@ -0,0 +1,19 @@
+use serde::{Serialize , Deserialize};
+
+#[derive(Debug , Clone , PartialEq , Serialize , Deserialize)]
+
+pub enum ToolchainSource {
+    IdfPathEnv,
+    EspDir,
+    IdfEnvJson,
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct Toolchain {
+    pub id : String,
+    pub version : Option<String>,
+    pub idf_path : String,
+    pub python : Option<String>,
+    pub source : ToolchainSource,
+}
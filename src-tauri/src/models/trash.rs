@@ -0,0 +1,14 @@
+use serde::{Serialize , Deserialize};
+
+/// Something deleted from a project, kept in `<project>/.veditor/trash`.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct TrashEntry {
+    pub id : String,
+    pub name : String,
+    /// Where it was, relative to the project folder.
+    pub original_path : String,
+    /// Milliseconds since the epoch.
+    pub deleted_at : u64,
+    pub is_dir : bool,
+}
@@ -0,0 +1,28 @@
+use serde::{Serialize , Deserialize};
+
+#[derive(Debug , Clone , Copy , PartialEq , Serialize , Deserialize)]
+
+pub enum FsChangeKind {
+    Created,
+    Modified,
+    Removed,
+    Renamed
+}
+
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct FsChange {
+    pub kind : FsChangeKind,
+    pub path : String,
+    /// Previous path of a renamed file or folder.
+    pub from : Option<String>,
+    pub is_dir : bool,
+}
+
+/// Debounced changes under a watched project, sent as `fs-changes`.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct FsChangeBatch {
+    pub project_path : String,
+    pub changes : Vec<FsChange>,
+}
@@ -0,0 +1,76 @@
+use serde::{Serialize , Deserialize};
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug , Clone , Copy , PartialEq , Serialize , Deserialize)]
+
+pub enum FsErrorKind {
+    /// The window has no project open, so no path is allowed.
+    NoProjectOpen,
+    /// The path (or where a symlink in it leads) is outside every open
+    /// project.
+    OutsideWorkspace,
+    /// Relative paths, `..` after a missing folder, or an operation on a
+    /// project root itself.
+    InvalidPath,
+    NotFound,
+    AlreadyExists,
+    /// The file changed on disk since the editor read it.
+    Conflict,
+    /// The file isn't text; `read_file_bytes` can still show it.
+    Binary,
+    Io
+}
+
+/// Error of the filesystem commands, so the UI can tell a refused path
+/// from a failed operation.
+#[derive(Debug , Clone , Serialize , Deserialize)]
+
+pub struct FsError {
+    pub kind : FsErrorKind,
+    pub path : Option<String>,
+    pub message : String,
+}
+
+impl FsError {
+    pub fn new(kind: FsErrorKind, path: &Path, message: impl Into<String>) -> Self {
+        FsError {
+            kind,
+            path: Some(path.to_string_lossy().to_string()),
+            message: message.into(),
+        }
+    }
+
+    pub fn io(path: &Path, err: std::io::Error) -> Self {
+        let kind = match err.kind() {
+            std::io::ErrorKind::NotFound => FsErrorKind::NotFound,
+            std::io::ErrorKind::AlreadyExists => FsErrorKind::AlreadyExists,
+            _ => FsErrorKind::Io,
+        };
+        FsError::new(kind, path, format!("{}: {}", path.display(), err))
+    }
+}
+
+impl fmt::Display for FsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Errors of the `Result<_, String>` helpers.
+impl From<String> for FsError {
+    fn from(message: String) -> Self {
+        FsError {
+            kind: FsErrorKind::Io,
+            path: None,
+            message,
+        }
+    }
+}
+
+/// For commands that still report plain strings.
+impl From<FsError> for String {
+    fn from(err: FsError) -> Self {
+        err.message
+    }
+}
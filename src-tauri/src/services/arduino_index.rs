@@ -0,0 +1,607 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use md5::Md5;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::models::arduino::{ArduinoSettings, LibraryRelease, PlatformRelease};
+
+fn settings_file() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Failed to find home directory")?;
+    Ok(home.join(".esp-projects").join("arduino.json"))
+}
+
+pub fn load_settings() -> ArduinoSettings {
+    settings_file()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(settings: &ArduinoSettings) -> Result<(), String> {
+    let path = settings_file()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    fs::write(path, data).map_err(|e| e.to_string())
+}
+
+pub fn mirror_dir(settings: &ArduinoSettings) -> Result<PathBuf, String> {
+    let dir = settings
+        .mirror_dir
+        .as_ref()
+        .map(PathBuf::from)
+        .ok_or("No Arduino mirror folder configured")?;
+    if !dir.is_dir() {
+        return Err(format!("Arduino mirror folder {} not found", dir.display()));
+    }
+    Ok(dir)
+}
+
+/// A downloadable file as the indexes describe it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Archive {
+    pub url: String,
+    pub archive_file_name: String,
+    /// `SHA-256:<hex>` (or `MD5:`/`SHA-1:` in older indexes).
+    #[serde(default)]
+    pub checksum: String,
+    #[serde(default, deserialize_with = "lenient_size")]
+    pub size: u64,
+}
+
+/// Some indexes write sizes as strings.
+fn lenient_size<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(match value {
+        serde_json::Value::Number(n) => n.as_u64().unwrap_or(0),
+        serde_json::Value::String(s) => s.trim().parse().unwrap_or(0),
+        _ => 0,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct LibraryIndex {
+    libraries: Vec<IndexLibrary>,
+}
+
+/// One release of a library in `library_index.json`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexLibrary {
+    pub name: String,
+    pub version: String,
+    pub author: Option<String>,
+    pub sentence: Option<String>,
+    pub category: Option<String>,
+    #[serde(default)]
+    pub architectures: Vec<String>,
+    #[serde(default)]
+    pub dependencies: Vec<LibraryDependency>,
+    #[serde(flatten)]
+    pub archive: Archive,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LibraryDependency {
+    pub name: String,
+    /// Version constraint, any version when missing.
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageIndex {
+    packages: Vec<IndexPackage>,
+}
+
+/// A packager in `package_*index.json` with its cores and tools. Written
+/// back as the `installed.json` arduino-cli expects next to a core.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexPackage {
+    pub name: String,
+    pub maintainer: Option<String>,
+    #[serde(default)]
+    pub platforms: Vec<IndexPlatform>,
+    #[serde(default)]
+    pub tools: Vec<IndexTool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexPlatform {
+    pub name: String,
+    pub architecture: String,
+    pub version: String,
+    #[serde(default)]
+    pub boards: Vec<IndexBoard>,
+    #[serde(default)]
+    pub tools_dependencies: Vec<ToolDependency>,
+    #[serde(flatten)]
+    pub archive: Archive,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexBoard {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolDependency {
+    pub packager: String,
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexTool {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub systems: Vec<ToolSystem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolSystem {
+    pub host: String,
+    #[serde(flatten)]
+    pub archive: Archive,
+}
+
+pub fn load_libraries(mirror: &Path) -> Result<Vec<IndexLibrary>, String> {
+    let path = mirror.join("library_index.json");
+    let data = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let index: LibraryIndex = serde_json::from_str(&data).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(index.libraries)
+}
+
+/// `package_index.json` and every `package_*_index.json` in the mirror.
+pub fn package_index_files(mirror: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut files: Vec<PathBuf> = fs::read_dir(mirror)
+        .map_err(|e| format!("{}: {}", mirror.display(), e))?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            let name = p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            name.starts_with("package_") && name.ends_with("index.json")
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// All packages of all package indexes; a packager listed by several
+/// indexes gets their platforms and tools combined.
+pub fn load_packages(mirror: &Path) -> Result<Vec<IndexPackage>, String> {
+    let mut packages: Vec<IndexPackage> = Vec::new();
+    for path in package_index_files(mirror)? {
+        let data = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let index: PackageIndex = serde_json::from_str(&data).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        for package in index.packages {
+            match packages.iter_mut().find(|p| p.name == package.name) {
+                Some(existing) => {
+                    existing.platforms.extend(package.platforms);
+                    existing.tools.extend(package.tools);
+                }
+                None => packages.push(package),
+            }
+        }
+    }
+    Ok(packages)
+}
+
+/// Arduino versions aren't always semver: `1.2`, `v2.0.1` and `1.0.0.4`
+/// all show up. Missing parts count as 0, extra parts are dropped.
+pub fn parse_version(version: &str) -> Option<Version> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    if let Ok(v) = Version::parse(version) {
+        return Some(v);
+    }
+
+    let (core, pre) = match version.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (version, None),
+    };
+    let mut parts = core.split('.').map(|p| p.parse::<u64>());
+    let mut next = || parts.next().unwrap_or(Ok(0)).ok();
+    let mut v = Version::new(next()?, next()?, next()?);
+    if let Some(pre) = pre {
+        v.pre = semver::Prerelease::new(pre).ok()?;
+    }
+    Some(v)
+}
+
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (parse_version(a), parse_version(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => a.cmp(b),
+    }
+}
+
+/// Whether `version` satisfies a dependency constraint (`>=1.2`, `=2.0.0`,
+/// `^1`, or a bare version meaning exactly that one).
+pub fn satisfies(version: &str, constraint: Option<&str>) -> bool {
+    let constraint = match constraint.map(str::trim) {
+        Some(c) if !c.is_empty() => c,
+        _ => return true,
+    };
+    let version = match parse_version(version) {
+        Some(v) => v,
+        None => return false,
+    };
+
+    let bare = constraint.starts_with(|c: char| c.is_ascii_digit() || c == 'v');
+    if bare {
+        return parse_version(constraint).is_some_and(|c| c == version);
+    }
+    VersionReq::parse(constraint).is_ok_and(|req| req.matches(&version))
+}
+
+/// Newest item in `items`; `version` picks a specific one instead.
+pub fn pick_release<'a, T>(
+    items: impl Iterator<Item = &'a T>,
+    version_of: impl Fn(&T) -> &str,
+    version: Option<&str>,
+) -> Option<&'a T> {
+    match version {
+        Some(wanted) => items.into_iter().find(|i| {
+            let v = version_of(i);
+            v == wanted || matches!((parse_version(v), parse_version(wanted)), (Some(a), Some(b)) if a == b)
+        }),
+        None => items.into_iter().max_by(|a, b| compare_versions(version_of(a), version_of(b))),
+    }
+}
+
+/// The releases to install for `name` (at `version`, or the newest) and
+/// everything it depends on, the requested library first. Dependencies
+/// `installed` already satisfies (name -> version) are left out.
+pub fn resolve_libraries<'a>(
+    index: &'a [IndexLibrary],
+    name: &str,
+    version: Option<&str>,
+    installed: &HashMap<String, String>,
+) -> Result<Vec<&'a IndexLibrary>, String> {
+    let root = pick_release(index.iter().filter(|l| l.name == name), |l| &l.version, version).ok_or_else(|| {
+        match version {
+            Some(v) => format!("Library {} {} not found in the mirror", name, v),
+            None => format!("Library {} not found in the mirror", name),
+        }
+    })?;
+
+    let mut selected: Vec<&IndexLibrary> = vec![root];
+    let mut queue: VecDeque<&IndexLibrary> = VecDeque::from([root]);
+
+    while let Some(library) = queue.pop_front() {
+        for dep in &library.dependencies {
+            let constraint = dep.version.as_deref();
+
+            if let Some(chosen) = selected.iter().find(|l| l.name == dep.name) {
+                if !satisfies(&chosen.version, constraint) {
+                    return Err(format!(
+                        "{} needs {} {} but {} {} is required elsewhere",
+                        library.name,
+                        dep.name,
+                        constraint.unwrap_or_default(),
+                        chosen.name,
+                        chosen.version
+                    ));
+                }
+                continue;
+            }
+            if installed.get(&dep.name).is_some_and(|v| satisfies(v, constraint)) {
+                continue;
+            }
+
+            let release = pick_release(
+                index
+                    .iter()
+                    .filter(|l| l.name == dep.name && satisfies(&l.version, constraint)),
+                |l| &l.version,
+                None,
+            )
+            .ok_or_else(|| {
+                format!(
+                    "{} depends on {} {}, which isn't in the mirror",
+                    library.name,
+                    dep.name,
+                    constraint.unwrap_or_default()
+                )
+                .trim_end()
+                .to_string()
+            })?;
+            selected.push(release);
+            queue.push_back(release);
+        }
+    }
+
+    Ok(selected)
+}
+
+/// Libraries whose name, sentence, author or category contains `query`
+/// (all of them for an empty query), each at its newest version.
+pub fn search_libraries(mirror: &Path, query: &str) -> Result<Vec<LibraryRelease>, String> {
+    let query = query.trim().to_lowercase();
+    let index = load_libraries(mirror)?;
+
+    let mut by_name: HashMap<&str, Vec<&IndexLibrary>> = HashMap::new();
+    for library in &index {
+        by_name.entry(library.name.as_str()).or_default().push(library);
+    }
+
+    let mut releases: Vec<LibraryRelease> = by_name
+        .into_values()
+        .filter_map(|mut versions| {
+            versions.sort_by(|a, b| compare_versions(&b.version, &a.version));
+            let latest = versions[0];
+            let haystack = [Some(&latest.name), latest.sentence.as_ref(), latest.author.as_ref(), latest.category.as_ref()];
+            let matched = query.is_empty() || haystack.iter().flatten().any(|s| s.to_lowercase().contains(&query));
+            matched.then(|| LibraryRelease {
+                name: latest.name.clone(),
+                version: latest.version.clone(),
+                author: latest.author.clone(),
+                sentence: latest.sentence.clone(),
+                category: latest.category.clone(),
+                architectures: latest.architectures.clone(),
+                dependencies: latest.dependencies.iter().map(|d| d.name.clone()).collect(),
+                versions: versions.iter().map(|l| l.version.clone()).collect(),
+            })
+        })
+        .collect();
+    releases.sort_by_key(|r| r.name.to_lowercase());
+    Ok(releases)
+}
+
+/// Board cores whose id, name or boards contain `query`, each at its
+/// newest version.
+pub fn search_platforms(mirror: &Path, query: &str) -> Result<Vec<PlatformRelease>, String> {
+    let query = query.trim().to_lowercase();
+    let mut releases = Vec::new();
+
+    for package in load_packages(mirror)? {
+        let mut by_arch: HashMap<&str, Vec<&IndexPlatform>> = HashMap::new();
+        for platform in &package.platforms {
+            by_arch.entry(platform.architecture.as_str()).or_default().push(platform);
+        }
+
+        for (arch, mut versions) in by_arch {
+            versions.sort_by(|a, b| compare_versions(&b.version, &a.version));
+            let latest = versions[0];
+            let id = format!("{}:{}", package.name, arch);
+
+            let matched = query.is_empty()
+                || id.to_lowercase().contains(&query)
+                || latest.name.to_lowercase().contains(&query)
+                || latest.boards.iter().any(|b| b.name.to_lowercase().contains(&query));
+            if matched {
+                releases.push(PlatformRelease {
+                    id,
+                    name: latest.name.clone(),
+                    maintainer: package.maintainer.clone(),
+                    version: latest.version.clone(),
+                    boards: latest.boards.iter().map(|b| b.name.clone()).collect(),
+                    versions: versions.iter().map(|p| p.version.clone()).collect(),
+                });
+            }
+        }
+    }
+    releases.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(releases)
+}
+
+/// The mirrored copy of `archive`: looked up by file name at the top of
+/// the mirror or in the `libraries/` and `packages/` folders (the
+/// arduino-cli staging layout), then by the path part of its URL.
+pub fn find_archive(mirror: &Path, archive: &Archive) -> Result<PathBuf, String> {
+    let url_path = archive
+        .url
+        .split_once("://")
+        .and_then(|(_, rest)| rest.split_once('/'))
+        .map(|(_, path)| path.to_string());
+
+    let candidates = [
+        Some(mirror.join(&archive.archive_file_name)),
+        Some(mirror.join("libraries").join(&archive.archive_file_name)),
+        Some(mirror.join("packages").join(&archive.archive_file_name)),
+        url_path.map(|p| mirror.join(p)),
+    ];
+    let path = candidates
+        .into_iter()
+        .flatten()
+        .find(|p| p.is_file())
+        .ok_or_else(|| format!("{} not found in the mirror", archive.archive_file_name))?;
+
+    verify(&path, archive)?;
+    Ok(path)
+}
+
+/// Checks size and checksum; algorithms we can't compute are skipped. Cores
+/// and toolchains run to hundreds of MB, so the file is hashed as it's read.
+fn verify(path: &Path, archive: &Archive) -> Result<(), String> {
+    let size = fs::metadata(path).map_err(|e| format!("{}: {}", path.display(), e))?.len();
+    if archive.size > 0 && size != archive.size {
+        return Err(format!(
+            "{} is {} bytes, the index says {}",
+            archive.archive_file_name,
+            size,
+            archive.size
+        ));
+    }
+
+    let (algorithm, expected) = archive.checksum.split_once(':').unwrap_or(("", ""));
+    let actual = match algorithm {
+        "SHA-256" => file_digest::<Sha256>(path)?,
+        "MD5" => file_digest::<Md5>(path)?,
+        _ => return Ok(()),
+    };
+    let actual: String = actual.iter().map(|b| format!("{:02x}", b)).collect();
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(format!("{} has the wrong checksum", archive.archive_file_name));
+    }
+    Ok(())
+}
+
+fn file_digest<D: Digest + io::Write>(path: &Path) -> Result<Vec<u8>, String> {
+    let mut file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut hasher = D::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(hasher.finalize().to_vec())
+}
+
+/// How well a tool's `host` fits this machine, lower is better; `None`
+/// when it can't run here.
+pub fn host_rank(host: &str) -> Option<u8> {
+    let arch = std::env::consts::ARCH;
+    match std::env::consts::OS {
+        "linux" => {
+            if !host.contains("linux") {
+                return None;
+            }
+            let ok = match arch {
+                "x86_64" => host.starts_with("x86_64"),
+                "x86" => host.starts_with("i686") || host.starts_with("i386"),
+                "aarch64" => host.starts_with("aarch64"),
+                "arm" => host.starts_with("arm"),
+                _ => false,
+            };
+            ok.then_some(0)
+        }
+        "macos" => {
+            if !host.contains("apple-darwin") {
+                return None;
+            }
+            // x86_64 builds still run on Apple silicon
+            match (arch, host.starts_with("x86_64") || host.starts_with("i386")) {
+                ("aarch64", false) if host.starts_with("arm64") || host.starts_with("aarch64") => Some(0),
+                ("aarch64", true) => Some(1),
+                ("x86_64", true) => Some(0),
+                _ => None,
+            }
+        }
+        "windows" => {
+            if !host.contains("mingw32") && !host.contains("windows") {
+                return None;
+            }
+            match (arch, host.starts_with("x86_64")) {
+                ("x86_64", true) => Some(0),
+                (_, false) if host.starts_with("i686") || host.starts_with("i386") => Some(1),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mirror() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/arduino-mirror")
+    }
+
+    fn resolve(name: &str, version: Option<&str>, installed: &[(&str, &str)]) -> Result<Vec<String>, String> {
+        let index = load_libraries(&mirror()).unwrap();
+        let installed = installed.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect();
+        Ok(resolve_libraries(&index, name, version, &installed)?
+            .iter()
+            .map(|l| format!("{} {}", l.name, l.version))
+            .collect())
+    }
+
+    #[test]
+    fn parses_loose_versions() {
+        let v = |s: &str| parse_version(s).map(|v| v.to_string());
+
+        assert_eq!(v("1.2").as_deref(), Some("1.2.0"));
+        assert_eq!(v("v2.0.1").as_deref(), Some("2.0.1"));
+        assert_eq!(v("1.0.0.4").as_deref(), Some("1.0.0"));
+        assert_eq!(v("3.0.0-rc1").as_deref(), Some("3.0.0-rc1"));
+        assert_eq!(v("1.0-beta").as_deref(), Some("1.0.0-beta"));
+        assert_eq!(v("latest"), None);
+        assert!(compare_versions("1.10.0", "1.9.9").is_gt());
+    }
+
+    #[test]
+    fn checks_dependency_constraints() {
+        assert!(satisfies("0.1.0", None));
+        assert!(satisfies("0.1.0", Some(" ")));
+        assert!(satisfies("1.5.2", Some(">=1.5.0")));
+        assert!(!satisfies("1.4", Some(">=1.5.0")));
+        assert!(satisfies("v1.6.0", Some("^1")));
+        // a bare version means exactly that one
+        assert!(satisfies("1.4.0", Some("1.4")));
+        assert!(!satisfies("1.4.1", Some("1.4")));
+        assert!(!satisfies("latest", Some(">=1")));
+        assert!(!satisfies("1.0.0", Some(">>1")));
+    }
+
+    #[test]
+    fn picks_newest_or_requested_release() {
+        let index = load_libraries(&mirror()).unwrap();
+        let bus_io = || index.iter().filter(|l| l.name == "BusIO");
+        let pick = |version: Option<&str>| pick_release(bus_io(), |l| &l.version, version).map(|l| l.version.as_str());
+
+        assert_eq!(pick(None), Some("v1.6.0"));
+        assert_eq!(pick(Some("1.5.2")), Some("1.5.2"));
+        assert_eq!(pick(Some("1.6.0")), Some("v1.6.0"));
+        assert_eq!(pick(Some("1.4.0")), Some("1.4"));
+        assert_eq!(pick(Some("2.0.0")), None);
+    }
+
+    #[test]
+    fn resolves_dependencies_breadth_first() {
+        assert_eq!(
+            resolve("Sensors", None, &[]).unwrap(),
+            vec!["Sensors 1.2.0", "BusIO v1.6.0", "Unified Sensor 1.1.14"]
+        );
+        assert_eq!(resolve("Sensors", Some("1.0.0"), &[]).unwrap(), vec!["Sensors 1.0.0", "BusIO v1.6.0"]);
+    }
+
+    #[test]
+    fn skips_dependencies_that_are_installed_in_range() {
+        assert_eq!(
+            resolve("Sensors", None, &[("BusIO", "1.5.2")]).unwrap(),
+            vec!["Sensors 1.2.0", "Unified Sensor 1.1.14"]
+        );
+        assert_eq!(
+            resolve("Sensors", None, &[("BusIO", "1.4")]).unwrap(),
+            vec!["Sensors 1.2.0", "BusIO v1.6.0", "Unified Sensor 1.1.14"]
+        );
+    }
+
+    #[test]
+    fn reports_conflicts_and_missing_dependencies() {
+        let err = resolve("Dashboard", None, &[]).unwrap_err();
+        assert_eq!(err, "Display needs BusIO =1.4 but BusIO v1.6.0 is required elsewhere");
+
+        let err = resolve("Orphan", None, &[]).unwrap_err();
+        assert_eq!(err, "Orphan depends on Nowhere ^2, which isn't in the mirror");
+
+        let err = resolve("Sensors", Some("9.9.9"), &[]).unwrap_err();
+        assert_eq!(err, "Library Sensors 9.9.9 not found in the mirror");
+    }
+
+    #[test]
+    fn verifies_mirrored_archives() {
+        let index = load_libraries(&mirror()).unwrap();
+        let archive = |version: &str| &index.iter().find(|l| l.name == "BusIO" && l.version == version).unwrap().archive;
+
+        let path = find_archive(&mirror(), archive("v1.6.0")).unwrap();
+        assert_eq!(path, mirror().join("libraries/BusIO-1.6.0.zip"));
+
+        let err = find_archive(&mirror(), archive("1.5.2")).unwrap_err();
+        assert!(err.contains("wrong checksum"), "{}", err);
+        let err = find_archive(&mirror(), archive("1.4")).unwrap_err();
+        assert!(err.contains("not found in the mirror"), "{}", err);
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::models::arduino::{ArduinoSettings, InstalledLibrary, InstalledPlatform, LibraryLocation};
+use crate::services::arduino_index::{self, IndexPackage};
+use crate::services::process_runner::{self, RunOptions};
+
+/// `<project>/libraries`.
+pub fn project_libraries_dir(project: &Path) -> PathBuf {
+    project.join("libraries")
+}
+
+/// The sketchbook's libraries folder arduino-cli searches by default.
+pub fn shared_libraries_dir(settings: &ArduinoSettings) -> Result<PathBuf, String> {
+    if let Some(dir) = &settings.libraries_dir {
+        return Ok(PathBuf::from(dir));
+    }
+    let home = dirs::home_dir().ok_or("Failed to find home directory")?;
+    Ok(home.join("Arduino").join("libraries"))
+}
+
+/// Where arduino-cli keeps board cores and tools.
+pub fn data_dir(settings: &ArduinoSettings) -> Result<PathBuf, String> {
+    if let Some(dir) = &settings.data_dir {
+        return Ok(PathBuf::from(dir));
+    }
+    if cfg!(windows) {
+        let local = dirs::data_local_dir().ok_or("Failed to find local app data folder")?;
+        return Ok(local.join("Arduino15"));
+    }
+    let home = dirs::home_dir().ok_or("Failed to find home directory")?;
+    if cfg!(target_os = "macos") {
+        Ok(home.join("Library").join("Arduino15"))
+    } else {
+        Ok(home.join(".arduino15"))
+    }
+}
+
+pub fn libraries_dir(settings: &ArduinoSettings, location: LibraryLocation, project: Option<&Path>) -> Result<PathBuf, String> {
+    match location {
+        LibraryLocation::Shared => shared_libraries_dir(settings),
+        LibraryLocation::Project => project
+            .map(project_libraries_dir)
+            .ok_or_else(|| "A project is needed to install libraries into it".to_string()),
+    }
+}
+
+/// Libraries in `dir`, read from their `library.properties`.
+pub fn installed_libraries(dir: &Path, location: LibraryLocation) -> Vec<InstalledLibrary> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut libraries: Vec<InstalledLibrary> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter_map(|path| {
+            let props = read_properties(&path.join("library.properties"))?;
+            let name = props.get("name")?.clone();
+            Some(InstalledLibrary {
+                name,
+                version: props.get("version").cloned().unwrap_or_default(),
+                location,
+                path: path.to_string_lossy().to_string(),
+                latest_version: None,
+                updatable: false,
+            })
+        })
+        .collect();
+    libraries.sort_by_key(|l| l.name.to_lowercase());
+    libraries
+}
+
+/// Project libraries (when `project` is given) then shared ones, with the
+/// newest version the mirror has when one is configured.
+pub fn list_libraries(settings: &ArduinoSettings, project: Option<&Path>) -> Result<Vec<InstalledLibrary>, String> {
+    let mut libraries = Vec::new();
+    if let Some(project) = project {
+        libraries.extend(installed_libraries(&project_libraries_dir(project), LibraryLocation::Project));
+    }
+    libraries.extend(installed_libraries(&shared_libraries_dir(settings)?, LibraryLocation::Shared));
+
+    let index = match arduino_index::mirror_dir(settings) {
+        Ok(mirror) => arduino_index::load_libraries(&mirror)?,
+        Err(_) => return Ok(libraries),
+    };
+    for library in &mut libraries {
+        let latest = arduino_index::pick_release(index.iter().filter(|l| l.name == library.name), |l| &l.version, None);
+        if let Some(latest) = latest {
+            library.updatable = arduino_index::compare_versions(&latest.version, &library.version).is_gt();
+            library.latest_version = Some(latest.version.clone());
+        }
+    }
+    Ok(libraries)
+}
+
+/// Installs `name` and its missing dependencies from the mirror into the
+/// libraries folder for `location`. Libraries already there are replaced.
+pub fn install_library(
+    settings: &ArduinoSettings,
+    name: &str,
+    version: Option<&str>,
+    location: LibraryLocation,
+    project: Option<&Path>,
+) -> Result<Vec<InstalledLibrary>, String> {
+    let mirror = arduino_index::mirror_dir(settings)?;
+    let index = arduino_index::load_libraries(&mirror)?;
+    let target = libraries_dir(settings, location, project)?;
+
+    // anything arduino-cli would find for this project satisfies a dependency
+    let mut visible = installed_libraries(&shared_libraries_dir(settings)?, LibraryLocation::Shared);
+    if let Some(project) = project {
+        visible.extend(installed_libraries(&project_libraries_dir(project), LibraryLocation::Project));
+    }
+    let installed: HashMap<String, String> = visible.into_iter().map(|l| (l.name, l.version)).collect();
+
+    let releases = arduino_index::resolve_libraries(&index, name, version, &installed)?;
+
+    fs::create_dir_all(&target).map_err(|e| e.to_string())?;
+    releases
+        .into_iter()
+        .map(|release| {
+            let archive = arduino_index::find_archive(&mirror, &release.archive)?;
+            let dest = target.join(library_folder_name(&release.name));
+            extract(&archive, &dest)?;
+
+            Ok(InstalledLibrary {
+                name: release.name.clone(),
+                version: release.version.clone(),
+                location,
+                path: dest.to_string_lossy().to_string(),
+                latest_version: None,
+                updatable: false,
+            })
+        })
+        .collect()
+}
+
+/// Installs the core `packager:arch` (at `version`, or the newest) with
+/// the tools it needs into the arduino-cli data folder.
+pub fn install_platform(settings: &ArduinoSettings, id: &str, version: Option<&str>) -> Result<InstalledPlatform, String> {
+    let (packager, arch) = id
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid platform '{}', expected packager:architecture", id))?;
+
+    let mirror = arduino_index::mirror_dir(settings)?;
+    let packages = arduino_index::load_packages(&mirror)?;
+    let data = data_dir(settings)?;
+
+    let package = packages
+        .iter()
+        .find(|p| p.name == packager)
+        .ok_or_else(|| format!("Package {} not found in the mirror", packager))?;
+    let platform = arduino_index::pick_release(
+        package.platforms.iter().filter(|p| p.architecture == arch),
+        |p| &p.version,
+        version,
+    )
+    .ok_or_else(|| format!("Platform {} {} not found in the mirror", id, version.unwrap_or_default()).trim_end().to_string())?;
+
+    // tools first, a core without its compiler is useless
+    let mut tools: Vec<(&str, &arduino_index::IndexTool)> = Vec::new();
+    for dep in &platform.tools_dependencies {
+        let tool = packages
+            .iter()
+            .find(|p| p.name == dep.packager)
+            .and_then(|p| p.tools.iter().find(|t| t.name == dep.name && t.version == dep.version))
+            .ok_or_else(|| format!("Tool {}:{}@{} not found in the mirror", dep.packager, dep.name, dep.version))?;
+        tools.push((&dep.packager, tool));
+
+        let dest = data.join("packages").join(&dep.packager).join("tools").join(&tool.name).join(&tool.version);
+        if dest.is_dir() {
+            continue;
+        }
+        let system = tool
+            .systems
+            .iter()
+            .filter_map(|s| arduino_index::host_rank(&s.host).map(|rank| (rank, s)))
+            .min_by_key(|(rank, _)| *rank)
+            .map(|(_, s)| s)
+            .ok_or_else(|| format!("Tool {}@{} has no build for this computer", tool.name, tool.version))?;
+
+        let archive = arduino_index::find_archive(&mirror, &system.archive)?;
+        extract(&archive, &dest)?;
+    }
+
+    let dest = data.join("packages").join(packager).join("hardware").join(arch).join(&platform.version);
+    let archive = arduino_index::find_archive(&mirror, &platform.archive)?;
+    extract(&archive, &dest)?;
+
+    // arduino-cli reads the core's tool dependencies from installed.json
+    let installed = IndexPackage {
+        name: package.name.clone(),
+        maintainer: package.maintainer.clone(),
+        platforms: vec![platform.clone()],
+        tools: tools.iter().filter(|(p, _)| *p == packager).map(|(_, t)| (*t).clone()).collect(),
+    };
+    let installed = serde_json::json!({ "packages": [installed] });
+    let json = serde_json::to_string_pretty(&installed).map_err(|e| e.to_string())?;
+    fs::write(dest.join("installed.json"), json).map_err(|e| e.to_string())?;
+
+    // so `arduino-cli core list` and friends see the same packages offline
+    for file in arduino_index::package_index_files(&mirror)? {
+        if let Some(name) = file.file_name() {
+            fs::copy(&file, data.join(name)).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(InstalledPlatform {
+        id: id.to_string(),
+        name: platform.name.clone(),
+        version: platform.version.clone(),
+        path: dest.to_string_lossy().to_string(),
+        latest_version: None,
+        updatable: false,
+    })
+}
+
+/// Installed cores with the newest version the mirror has when one is
+/// configured.
+pub fn list_platforms(settings: &ArduinoSettings) -> Result<Vec<InstalledPlatform>, String> {
+    let mut platforms = installed_platforms(settings)?;
+
+    let packages = match arduino_index::mirror_dir(settings) {
+        Ok(mirror) => arduino_index::load_packages(&mirror)?,
+        Err(_) => return Ok(platforms),
+    };
+    for platform in &mut platforms {
+        let (packager, arch) = platform.id.split_once(':').unwrap_or_default();
+        let latest = packages
+            .iter()
+            .filter(|p| p.name == packager)
+            .flat_map(|p| p.platforms.iter().filter(|r| r.architecture == arch))
+            .max_by(|a, b| arduino_index::compare_versions(&a.version, &b.version));
+        if let Some(latest) = latest {
+            platform.updatable = arduino_index::compare_versions(&latest.version, &platform.version).is_gt();
+            platform.latest_version = Some(latest.version.clone());
+        }
+    }
+    Ok(platforms)
+}
+
+/// Cores under `<data>/packages/<packager>/hardware/<arch>/<version>`.
+fn installed_platforms(settings: &ArduinoSettings) -> Result<Vec<InstalledPlatform>, String> {
+    let packages = data_dir(settings)?.join("packages");
+    let mut platforms = Vec::new();
+
+    for packager in subdirs(&packages) {
+        for arch in subdirs(&packager.join("hardware")) {
+            for release in subdirs(&arch) {
+                let id = format!("{}:{}", file_name(&packager), file_name(&arch));
+                let name = read_properties(&release.join("platform.txt"))
+                    .and_then(|p| p.get("name").cloned())
+                    .unwrap_or_else(|| id.clone());
+                platforms.push(InstalledPlatform {
+                    id,
+                    name,
+                    version: file_name(&release),
+                    path: release.to_string_lossy().to_string(),
+                    latest_version: None,
+                    updatable: false,
+                });
+            }
+        }
+    }
+
+    platforms.sort_by(|a, b| a.id.cmp(&b.id).then_with(|| arduino_index::compare_versions(&b.version, &a.version)));
+    Ok(platforms)
+}
+
+/// Arduino's folder name for a library: its name with anything but
+/// letters, digits, `_`, `-` and `.` replaced by `_`.
+fn library_folder_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "_-.".contains(c) { c } else { '_' })
+        .collect()
+}
+
+/// Unpacks `archive` into `dest`, replacing it. Index archives hold a
+/// single top-level folder whose name varies, so its contents become `dest`.
+fn extract(archive: &Path, dest: &Path) -> Result<(), String> {
+    let parent = dest.parent().ok_or("Invalid install folder")?;
+    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+
+    let staging = parent.join(format!(".{}.partial", file_name(dest)));
+    if staging.exists() {
+        fs::remove_dir_all(&staging).map_err(|e| e.to_string())?;
+    }
+    fs::create_dir_all(&staging).map_err(|e| e.to_string())?;
+
+    let is_zip = archive.extension().is_some_and(|e| e.eq_ignore_ascii_case("zip"));
+    // bsdtar (the tar on Windows and macOS) reads zips as well
+    let opts = if is_zip && !cfg!(windows) {
+        RunOptions::new("unzip")
+            .arg("-q")
+            .arg(archive.to_string_lossy())
+            .arg("-d")
+            .arg(staging.to_string_lossy())
+    } else {
+        RunOptions::new("tar")
+            .arg("-xf")
+            .arg(archive.to_string_lossy())
+            .arg("-C")
+            .arg(staging.to_string_lossy())
+    }
+    .timeout(Duration::from_secs(600));
+
+    let result = process_runner::run_collect(opts).and_then(|(exit, output)| {
+        if exit.success {
+            Ok(())
+        } else {
+            Err(format!("Failed to unpack {}: {}", archive.display(), output.trim()))
+        }
+    });
+    if let Err(e) = result {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    let entries = subdirs(&staging);
+    let files = fs::read_dir(&staging).map(|e| e.count()).unwrap_or(0);
+    let root = match entries.as_slice() {
+        [single] if files == 1 => single.clone(),
+        _ => staging.clone(),
+    };
+
+    if dest.exists() {
+        fs::remove_dir_all(dest).map_err(|e| e.to_string())?;
+    }
+    fs::rename(&root, dest).map_err(|e| e.to_string())?;
+    if staging.exists() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    Ok(())
+}
+
+/// `key=value` lines, as in `library.properties` and `platform.txt`.
+fn read_properties(path: &Path) -> Option<HashMap<String, String>> {
+    let data = fs::read_to_string(path).ok()?;
+    Some(
+        data.lines()
+            .filter(|l| !l.trim_start().starts_with('#'))
+            .filter_map(|l| l.split_once('='))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .collect(),
+    )
+}
+
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect())
+        .unwrap_or_default();
+    found.sort();
+    found
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
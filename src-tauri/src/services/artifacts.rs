@@ -0,0 +1,128 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::models::artifact::{Artifact, ArtifactManifest};
+use crate::services::firmware_image;
+
+/// How many `artifacts/build-<n>` folders are kept per project.
+const KEEP_BUILDS: usize = 10;
+
+pub fn artifacts_root(project_path: &Path) -> PathBuf {
+    project_path.join("artifacts")
+}
+
+/// Copies the given outputs of a finished build (missing ones are skipped)
+/// into a new `artifacts/build-<n>/` folder with a manifest, so the build
+/// folder can stay incremental. Returns the new folder.
+pub fn store(project_path: &Path, sources: &[PathBuf], project_name: Option<String>) -> Result<PathBuf, String> {
+    let root = artifacts_root(project_path);
+    let build_number = latest_build_number(&root).map(|n| n + 1).unwrap_or(1);
+    let out = root.join(format!("build-{}", build_number));
+    fs::create_dir_all(&out).map_err(|e| e.to_string())?;
+
+    let mut files = Vec::new();
+    for src in sources.iter().filter(|p| p.is_file()) {
+        let name = src.file_name().unwrap().to_string_lossy().to_string();
+        fs::copy(src, out.join(&name)).map_err(|e| format!("Failed to copy {}: {}", name, e))?;
+        files.push(name);
+    }
+
+    let manifest = ArtifactManifest {
+        build_number,
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        project_name,
+        files,
+    };
+    let data = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    fs::write(out.join("manifest.json"), data).map_err(|e| e.to_string())?;
+
+    prune(&root);
+
+    Ok(out)
+}
+
+/// `artifacts/build-<n>`, whether or not it (still) exists.
+pub fn build_dir(project_path: &Path, build_number: u32) -> PathBuf {
+    artifacts_root(project_path).join(format!("build-{}", build_number))
+}
+
+/// `artifacts/build-<n>` with the highest `n`, if any build was collected.
+pub fn latest_dir(project_path: &Path) -> Option<PathBuf> {
+    let root = artifacts_root(project_path);
+    latest_build_number(&root).map(|n| root.join(format!("build-{}", n)))
+}
+
+pub fn find_bins(build_dir: &Path) -> Vec<Artifact> {
+    let mut artifacts = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(build_dir) {
+        for e in entries.flatten() {
+            let path = e.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("bin") {
+                let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                artifacts.push(Artifact {
+                    name: path.file_name().unwrap().to_string_lossy().to_string(),
+                    path: path.to_string_lossy().to_string(),
+                    size_bytes: size,
+                    image: firmware_image::read(&path).ok().flatten(),
+                });
+            }
+        }
+    }
+
+    artifacts.sort_by(|a, b| a.name.cmp(&b.name));
+    artifacts
+}
+
+/// Where a project's flashable output lives: the newest `artifacts/build-<n>`
+/// folder, or `build/` for projects built before artifacts were collected.
+pub fn artifacts_dir(project_path: &Path) -> PathBuf {
+    latest_dir(project_path).unwrap_or_else(|| project_path.join("build"))
+}
+
+/// `artifacts/build-<n>` for `Some(n)`, `artifacts_dir` for `None`.
+pub fn build_artifacts_dir(project_path: &Path, build_number: Option<u32>) -> Result<PathBuf, String> {
+    match build_number {
+        Some(n) => {
+            let dir = build_dir(project_path, n);
+            if dir.is_dir() {
+                Ok(dir)
+            } else {
+                Err(format!("Build {} not found", n))
+            }
+        }
+        None => Ok(artifacts_dir(project_path)),
+    }
+}
+
+fn build_numbers(root: &Path) -> Vec<u32> {
+    let mut numbers: Vec<u32> = fs::read_dir(root)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| e.path().is_dir())
+                .filter_map(|e| e.file_name().to_string_lossy().strip_prefix("build-")?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    numbers.sort_unstable();
+    numbers
+}
+
+fn latest_build_number(root: &Path) -> Option<u32> {
+    build_numbers(root).last().copied()
+}
+
+fn prune(root: &Path) {
+    let numbers = build_numbers(root);
+    if numbers.len() <= KEEP_BUILDS {
+        return;
+    }
+    for n in &numbers[..numbers.len() - KEEP_BUILDS] {
+        let _ = fs::remove_dir_all(root.join(format!("build-{}", n)));
+    }
+}
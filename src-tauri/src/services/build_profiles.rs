@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::build::{BuildProfile, OptimizationLevel};
+use crate::services::{project_settings, sdkconfig};
+use crate::utils::fs::content_hash;
+
+pub const TARGETS: [&str; 10] = [
+    "esp32", "esp32s2", "esp32s3", "esp32c2", "esp32c3", "esp32c5", "esp32c6", "esp32c61", "esp32h2", "esp32p4",
+];
+
+const OPTIMIZATION_KEYS: [(OptimizationLevel, &str); 4] = [
+    (OptimizationLevel::Debug, "CONFIG_COMPILER_OPTIMIZATION_DEBUG"),
+    (OptimizationLevel::Size, "CONFIG_COMPILER_OPTIMIZATION_SIZE"),
+    (OptimizationLevel::Performance, "CONFIG_COMPILER_OPTIMIZATION_PERF"),
+    (OptimizationLevel::None, "CONFIG_COMPILER_OPTIMIZATION_NONE"),
+];
+
+/// What `build_project` has to do for the active profile.
+pub struct ProfileBuildArgs {
+    /// Set when the configured target differs from the profile's.
+    pub set_target: Option<String>,
+    /// Arguments placed before the `idf.py` action (`-D ...`).
+    pub idf_args: Vec<String>,
+}
+
+pub fn active(project_path: &Path) -> Option<BuildProfile> {
+    let settings = project_settings::load(project_path);
+    let name = settings.active_profile?;
+    settings.profiles.into_iter().find(|p| p.name == name)
+}
+
+pub fn validate(profile: &BuildProfile) -> Result<(), String> {
+    if profile.name.trim().is_empty() {
+        return Err("Profile name cannot be empty".into());
+    }
+    if !TARGETS.contains(&profile.target.as_str()) {
+        return Err(format!("Unknown target chip: {}", profile.target));
+    }
+    for define in &profile.defines {
+        match define.split_once('=') {
+            Some((name, _)) if !name.is_empty() && !name.contains(char::is_whitespace) => {}
+            _ => return Err(format!("Invalid define (expected NAME=VALUE): {}", define)),
+        }
+    }
+    Ok(())
+}
+
+/// Prepares the project for a build with `profile` and returns the extra
+/// `idf.py` arguments.
+///
+/// The optimisation level lives in sdkconfig, so it is written to a generated
+/// defaults file (used whenever sdkconfig is regenerated, e.g. by
+/// `set-target`) and patched straight into an existing sdkconfig, since
+/// defaults never override values already there.
+pub fn prepare(project_path: &Path, profile: &BuildProfile) -> Result<ProfileBuildArgs, String> {
+    let generated = write_generated_defaults(project_path, profile)?;
+
+    let mut defaults: Vec<String> = Vec::new();
+    if project_path.join("sdkconfig.defaults").is_file() {
+        defaults.push("sdkconfig.defaults".into());
+    }
+    defaults.extend(profile.sdkconfig_defaults.iter().cloned());
+    defaults.push(generated.to_string_lossy().to_string());
+
+    let mut idf_args = vec!["-D".to_string(), format!("SDKCONFIG_DEFAULTS={}", defaults.join(";"))];
+    for define in &profile.defines {
+        idf_args.push("-D".into());
+        idf_args.push(define.clone());
+    }
+
+    let sdkconfig_file = project_path.join("sdkconfig");
+    let set_target = match sdkconfig::read_value(&sdkconfig_file, "IDF_TARGET") {
+        Some(t) if t == profile.target => {
+            patch_optimization(&sdkconfig_file, profile.optimization)?;
+            None
+        }
+        _ => Some(profile.target.clone()),
+    };
+
+    Ok(ProfileBuildArgs { set_target, idf_args })
+}
+
+fn write_generated_defaults(project_path: &Path, profile: &BuildProfile) -> Result<PathBuf, String> {
+    let dir = project_settings::editor_dir(project_path).join("profiles");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let file = dir.join(format!("{}.sdkconfig.defaults", file_stem(&profile.name)));
+    fs::write(&file, optimization_lines(profile.optimization).join("\n") + "\n")
+        .map_err(|e| e.to_string())?;
+
+    Ok(file)
+}
+
+fn patch_optimization(sdkconfig_file: &Path, level: OptimizationLevel) -> Result<(), String> {
+    let values: Vec<(&str, Option<&str>)> = OPTIMIZATION_KEYS
+        .iter()
+        .map(|(l, key)| (key.trim_start_matches("CONFIG_"), (*l == level).then_some("y")))
+        .collect();
+    sdkconfig::set_values(sdkconfig_file, &values)
+}
+
+fn optimization_lines(level: OptimizationLevel) -> Vec<String> {
+    OPTIMIZATION_KEYS
+        .iter()
+        .map(|(l, key)| {
+            if *l == level {
+                format!("{}=y", key)
+            } else {
+                format!("# {} is not set", key)
+            }
+        })
+        .collect()
+}
+
+/// The profile name made safe for a file name, plus a short hash of the
+/// name so that e.g. "a b" and "a_b" don't share a defaults file.
+fn file_stem(name: &str) -> String {
+    let safe: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("{}-{}", safe, &content_hash(name.as_bytes())[..8])
+}
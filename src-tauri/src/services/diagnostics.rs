@@ -0,0 +1,255 @@
+use std::path::{Path, PathBuf};
+
+use crate::models::diagnostic::{Diagnostic, DiagnosticNote, DiagnosticSeverity};
+use crate::services::process_runner::strip_ansi;
+
+/// Turns build output into `Diagnostic`s, one line at a time.
+///
+/// Understands GCC/Clang (`file:line:col: error: msg` followed by `note:`
+/// lines and source excerpts) and CMake (`CMake Error at file:line (cmd):`
+/// followed by an indented message and call stack). A diagnostic is only
+/// returned once the lines that belong to it are over, so notes are attached.
+pub struct DiagnosticParser {
+    project_root: PathBuf,
+    current: Option<Pending>,
+}
+
+enum Pending {
+    Compiler(Diagnostic),
+    CMake { diag: Diagnostic, in_call_stack: bool },
+}
+
+impl DiagnosticParser {
+    pub fn new(project_root: &Path) -> Self {
+        Self {
+            project_root: project_root.to_path_buf(),
+            current: None,
+        }
+    }
+
+    /// Feeds one output line. Returns the diagnostic this line completed, if any.
+    pub fn push_line(&mut self, raw: &str) -> Option<Diagnostic> {
+        let line = strip_ansi(raw);
+        let line = line.trim_end();
+
+        if let Some(Pending::CMake { diag, in_call_stack }) = &mut self.current {
+            if line.is_empty() {
+                return None;
+            }
+            if line.starts_with("Call Stack") {
+                *in_call_stack = true;
+                return None;
+            }
+            if line.starts_with(' ') {
+                let text = line.trim();
+                if *in_call_stack {
+                    diag.notes.push(cmake_stack_note(text, &self.project_root));
+                } else if diag.message.is_empty() {
+                    diag.message = text.to_string();
+                } else {
+                    diag.message.push(' ');
+                    diag.message.push_str(text);
+                }
+                return None;
+            }
+        }
+
+        if let Some(diag) = self.parse_cmake_header(line) {
+            let done = self.finish();
+            self.current = Some(Pending::CMake { diag, in_call_stack: false });
+            return done;
+        }
+
+        if let Some((loc, severity, message)) = parse_compiler_line(line) {
+            let (file, line_no, column) = self.resolve(loc);
+
+            if severity == DiagnosticSeverity::Note {
+                if let Some(Pending::Compiler(diag)) = &mut self.current {
+                    diag.notes.push(DiagnosticNote { file, line: line_no, column, message });
+                    return None;
+                }
+            }
+
+            let done = self.finish();
+            self.current = Some(Pending::Compiler(Diagnostic {
+                file,
+                line: line_no,
+                column,
+                severity,
+                message,
+                notes: Vec::new(),
+            }));
+            return done;
+        }
+
+        if matches!(self.current, Some(Pending::Compiler(_))) && is_compiler_continuation(line) {
+            return None;
+        }
+
+        self.finish()
+    }
+
+    /// Flushes whatever diagnostic is still being collected.
+    pub fn finish(&mut self) -> Option<Diagnostic> {
+        match self.current.take()? {
+            Pending::Compiler(d) => Some(d),
+            Pending::CMake { diag, .. } => Some(diag),
+        }
+    }
+
+    fn parse_cmake_header(&self, line: &str) -> Option<Diagnostic> {
+        let rest = line.strip_prefix("CMake ")?;
+
+        let (severity, rest) = if let Some(r) = rest.strip_prefix("Error") {
+            (DiagnosticSeverity::Error, r)
+        } else if let Some(r) = rest.strip_prefix("Deprecation Warning") {
+            (DiagnosticSeverity::Warning, r)
+        } else if let Some(r) = rest.strip_prefix("Warning") {
+            (DiagnosticSeverity::Warning, r)
+        } else {
+            return None;
+        };
+
+        // "CMake Warning (dev) at ..." / "CMake Error: message"
+        let rest = rest.trim_start();
+        let rest = rest.strip_prefix("(dev)").unwrap_or(rest).trim_start();
+
+        if let Some(msg) = rest.strip_prefix(':') {
+            return Some(Diagnostic {
+                file: None,
+                line: None,
+                column: None,
+                severity,
+                message: msg.trim().to_string(),
+                notes: Vec::new(),
+            });
+        }
+
+        let at = rest.strip_prefix("at ")?;
+        let at = at.strip_suffix(':').unwrap_or(at);
+        let loc = at.rsplit_once(" (").map(|(l, _)| l).unwrap_or(at);
+        let (file, line_no) = split_file_line(loc)?;
+
+        Some(Diagnostic {
+            file: Some(self.resolve_path(file)),
+            line: Some(line_no),
+            column: None,
+            severity,
+            message: String::new(),
+            notes: Vec::new(),
+        })
+    }
+
+    fn resolve(&self, loc: Location) -> (Option<String>, Option<u32>, Option<u32>) {
+        (Some(self.resolve_path(loc.file)), loc.line, loc.column)
+    }
+
+    /// Compiler paths are relative to `build/` (ninja's cwd), CMake paths to
+    /// the project root. Whichever exists wins; otherwise the path is kept as is.
+    fn resolve_path(&self, file: &str) -> String {
+        let p = Path::new(file);
+        if p.is_absolute() {
+            return file.to_string();
+        }
+
+        for base in [self.project_root.join("build"), self.project_root.clone()] {
+            if let Ok(full) = base.join(p).canonicalize() {
+                return full.to_string_lossy().to_string();
+            }
+        }
+
+        file.to_string()
+    }
+}
+
+struct Location<'a> {
+    file: &'a str,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+/// `file:line[:col]: severity: message`
+fn parse_compiler_line(line: &str) -> Option<(Location<'_>, DiagnosticSeverity, String)> {
+    const MARKERS: [(&str, DiagnosticSeverity); 4] = [
+        (": fatal error: ", DiagnosticSeverity::Error),
+        (": error: ", DiagnosticSeverity::Error),
+        (": warning: ", DiagnosticSeverity::Warning),
+        (": note: ", DiagnosticSeverity::Note),
+    ];
+
+    let (idx, marker, severity) = MARKERS
+        .iter()
+        .filter_map(|(m, s)| line.find(m).map(|i| (i, *m, s.clone())))
+        .min_by_key(|(i, _, _)| *i)?;
+
+    let loc = parse_location(&line[..idx])?;
+    let message = line[idx + marker.len()..].trim().to_string();
+
+    Some((loc, severity, message))
+}
+
+fn parse_location(s: &str) -> Option<Location<'_>> {
+    let mut parts: Vec<&str> = Vec::new();
+    let mut rest = s;
+
+    // peel at most two numeric components off the end: file:line:col
+    while parts.len() < 2 {
+        match rest.rsplit_once(':') {
+            Some((head, tail)) if !tail.is_empty() && tail.bytes().all(|b| b.is_ascii_digit()) => {
+                parts.push(tail);
+                rest = head;
+            }
+            _ => break,
+        }
+    }
+
+    if parts.is_empty() || rest.is_empty() || (rest.contains(' ') && !looks_like_path(rest)) {
+        return None;
+    }
+
+    let nums: Vec<u32> = parts.iter().rev().filter_map(|p| p.parse().ok()).collect();
+
+    Some(Location {
+        file: rest,
+        line: nums.first().copied(),
+        column: nums.get(1).copied(),
+    })
+}
+
+fn looks_like_path(s: &str) -> bool {
+    s.contains('/') || s.contains('\\')
+}
+
+fn split_file_line(loc: &str) -> Option<(&str, u32)> {
+    let (file, line) = loc.rsplit_once(':')?;
+    Some((file, line.trim().parse().ok()?))
+}
+
+fn cmake_stack_note(text: &str, root: &Path) -> DiagnosticNote {
+    let loc = text.rsplit_once(" (").map(|(l, _)| l).unwrap_or(text);
+
+    match split_file_line(loc) {
+        Some((file, line)) => {
+            let full = root.join(file);
+            DiagnosticNote {
+                file: Some(
+                    if full.exists() { full.to_string_lossy().to_string() } else { file.to_string() },
+                ),
+                line: Some(line),
+                column: None,
+                message: text.to_string(),
+            }
+        }
+        None => DiagnosticNote { file: None, line: None, column: None, message: text.to_string() },
+    }
+}
+
+/// Lines GCC prints between a diagnostic and the next one: source excerpts,
+/// caret markers, include chains and "In function" headers.
+fn is_compiler_continuation(line: &str) -> bool {
+    line.starts_with(' ')
+        || line.starts_with("In file included from")
+        || line.contains(": In function ")
+        || line.contains(": In member function ")
+        || line.contains(": At top level:")
+}
@@ -0,0 +1,143 @@
+use std::path::Path;
+
+/// Just enough of a 32-bit little-endian ELF reader for size analysis: the
+/// section headers and the symbol table. Every ESP chip (Xtensa and RISC-V)
+/// produces this format.
+pub struct ElfFile {
+    pub sections: Vec<ElfSection>,
+    pub symbols: Vec<ElfSymbol>,
+}
+
+pub struct ElfSection {
+    pub name: String,
+    pub address: u32,
+    pub size: u32,
+    pub flags: u32,
+    pub nobits: bool,
+}
+
+pub struct ElfSymbol {
+    pub name: String,
+    pub value: u32,
+    pub size: u32,
+    pub kind: u8,
+    pub section: Option<usize>,
+}
+
+pub const SHF_ALLOC: u32 = 0x2;
+pub const STT_OBJECT: u8 = 1;
+pub const STT_FUNC: u8 = 2;
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_NOBITS: u32 = 8;
+const SECTION_HEADER_LEN: usize = 40;
+const SYMBOL_LEN: usize = 16;
+
+pub fn read(path: &Path) -> Result<ElfFile, String> {
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse(&data).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn parse(data: &[u8]) -> Result<ElfFile, String> {
+    if data.len() < 52 || &data[..4] != b"\x7fELF" {
+        return Err("not an ELF file".into());
+    }
+    if data[4] != 1 || data[5] != 1 {
+        return Err("only 32-bit little-endian ELF files are supported".into());
+    }
+
+    let shoff = u32_at(data, 0x20)? as usize;
+    let shentsize = u16_at(data, 0x2E)? as usize;
+    let shnum = u16_at(data, 0x30)? as usize;
+    let shstrndx = u16_at(data, 0x32)? as usize;
+    if shentsize < SECTION_HEADER_LEN {
+        return Err("bad section header size".into());
+    }
+
+    struct RawSection {
+        name: u32,
+        kind: u32,
+        flags: u32,
+        address: u32,
+        offset: u32,
+        size: u32,
+        link: u32,
+    }
+
+    let mut raw = Vec::with_capacity(shnum);
+    for i in 0..shnum {
+        let h = shoff + i * shentsize;
+        raw.push(RawSection {
+            name: u32_at(data, h)?,
+            kind: u32_at(data, h + 4)?,
+            flags: u32_at(data, h + 8)?,
+            address: u32_at(data, h + 12)?,
+            offset: u32_at(data, h + 16)?,
+            size: u32_at(data, h + 20)?,
+            link: u32_at(data, h + 24)?,
+        });
+    }
+
+    let shstrtab = raw
+        .get(shstrndx)
+        .map(|s| bytes(data, s.offset, s.size))
+        .transpose()?
+        .unwrap_or(&[]);
+
+    let sections = raw
+        .iter()
+        .map(|s| ElfSection {
+            name: c_str(shstrtab, s.name as usize),
+            address: s.address,
+            size: s.size,
+            flags: s.flags,
+            nobits: s.kind == SHT_NOBITS,
+        })
+        .collect();
+
+    let mut symbols = Vec::new();
+    if let Some(symtab) = raw.iter().find(|s| s.kind == SHT_SYMTAB) {
+        let table = bytes(data, symtab.offset, symtab.size)?;
+        let strtab = match raw.get(symtab.link as usize) {
+            Some(s) => bytes(data, s.offset, s.size)?,
+            None => &[],
+        };
+
+        for entry in table.chunks_exact(SYMBOL_LEN) {
+            let shndx = u16_at(entry, 14)? as usize;
+            symbols.push(ElfSymbol {
+                name: c_str(strtab, u32_at(entry, 0)? as usize),
+                value: u32_at(entry, 4)?,
+                size: u32_at(entry, 8)?,
+                kind: entry[12] & 0xF,
+                // 0 is SHN_UNDEF, 0xff00.. are special (ABS, COMMON)
+                section: Some(shndx).filter(|&i| i != 0 && i < 0xFF00),
+            });
+        }
+    }
+
+    Ok(ElfFile { sections, symbols })
+}
+
+fn bytes(data: &[u8], offset: u32, size: u32) -> Result<&[u8], String> {
+    let (start, end) = (offset as usize, offset as usize + size as usize);
+    data.get(start..end).ok_or_else(|| "section extends past the end of the file".into())
+}
+
+fn c_str(table: &[u8], offset: usize) -> String {
+    let rest = table.get(offset..).unwrap_or(&[]);
+    let end = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+    String::from_utf8_lossy(&rest[..end]).to_string()
+}
+
+fn u16_at(data: &[u8], pos: usize) -> Result<u16, String> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| "truncated ELF file".into())
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Result<u32, String> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| "truncated ELF file".into())
+}
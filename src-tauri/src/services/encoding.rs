@@ -0,0 +1,86 @@
+use crate::models::editor::TextEncoding;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// How `data` is encoded, `None` if it doesn't look like text. UTF-16 is
+/// only recognised by its byte order mark; anything that isn't valid UTF-8
+/// and has no NUL or unusual control bytes is taken as Latin-1.
+pub fn detect(data: &[u8]) -> Option<TextEncoding> {
+    if data.starts_with(UTF8_BOM) {
+        return std::str::from_utf8(&data[UTF8_BOM.len()..]).ok().map(|_| TextEncoding::Utf8Bom);
+    }
+    if data.starts_with(UTF16_LE_BOM) || data.starts_with(UTF16_BE_BOM) {
+        let encoding = if data.starts_with(UTF16_LE_BOM) { TextEncoding::Utf16Le } else { TextEncoding::Utf16Be };
+        return decode_utf16(&data[2..], encoding == TextEncoding::Utf16Le).map(|_| encoding);
+    }
+
+    let binary = data
+        .iter()
+        .any(|&b| b == 0 || (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0C | 0x1B)));
+    if binary {
+        return None;
+    }
+    if std::str::from_utf8(data).is_ok() {
+        Some(TextEncoding::Utf8)
+    } else {
+        Some(TextEncoding::Latin1)
+    }
+}
+
+/// `data` as text, when it is valid in `encoding`.
+pub fn decode(data: &[u8], encoding: TextEncoding) -> Option<String> {
+    match encoding {
+        TextEncoding::Utf8 => String::from_utf8(data.to_vec()).ok(),
+        TextEncoding::Utf8Bom => String::from_utf8(data.strip_prefix(UTF8_BOM).unwrap_or(data).to_vec()).ok(),
+        TextEncoding::Utf16Le => decode_utf16(data.strip_prefix(UTF16_LE_BOM).unwrap_or(data), true),
+        TextEncoding::Utf16Be => decode_utf16(data.strip_prefix(UTF16_BE_BOM).unwrap_or(data), false),
+        TextEncoding::Latin1 => Some(data.iter().map(|&b| b as char).collect()),
+    }
+}
+
+/// `data` as text in whatever encoding it seems to use, with replacement
+/// characters if it doesn't look like text at all.
+pub fn decode_any(data: &[u8]) -> String {
+    detect(data)
+        .and_then(|encoding| decode(data, encoding))
+        .unwrap_or_else(|| String::from_utf8_lossy(data).into_owned())
+}
+
+fn decode_utf16(data: &[u8], little_endian: bool) -> Option<String> {
+    if data.len() % 2 != 0 {
+        return None;
+    }
+    let units = data.chunks_exact(2).map(|pair| {
+        if little_endian {
+            u16::from_le_bytes([pair[0], pair[1]])
+        } else {
+            u16::from_be_bytes([pair[0], pair[1]])
+        }
+    });
+    char::decode_utf16(units).collect::<Result<String, _>>().ok()
+}
+
+/// `text` in `encoding`, with its byte order mark if it has one. Fails for
+/// Latin-1 when the text has characters Latin-1 doesn't.
+pub fn encode(text: &str, encoding: TextEncoding) -> Result<Vec<u8>, String> {
+    match encoding {
+        TextEncoding::Utf8 => Ok(text.as_bytes().to_vec()),
+        TextEncoding::Utf8Bom => Ok([UTF8_BOM, text.as_bytes()].concat()),
+        TextEncoding::Utf16Le => Ok(UTF16_LE_BOM
+            .iter()
+            .copied()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect()),
+        TextEncoding::Utf16Be => Ok(UTF16_BE_BOM
+            .iter()
+            .copied()
+            .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect()),
+        TextEncoding::Latin1 => text
+            .chars()
+            .map(|c| u8::try_from(c as u32).map_err(|_| format!("'{}' can't be saved in Latin-1", c)))
+            .collect(),
+    }
+}
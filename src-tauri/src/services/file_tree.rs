@@ -0,0 +1,137 @@
+use std::fs::{self, Metadata};
+use std::path::Path;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+use crate::models::explorer::ExplorerNode;
+use crate::models::workspace::FsError;
+use crate::services::project_settings::{self, EDITOR_DIR};
+use crate::services::search;
+use crate::utils::fs::modified_ms;
+
+/// Never shown: VCS data and the editor's own files.
+const HIDDEN_NAMES: [&str; 2] = [".git", EDITOR_DIR];
+
+/// Top-level entries written by tools rather than people; everything
+/// inside them is generated too.
+const GENERATED_NAMES: [&str; 6] = ["build", "managed_components", "target", ".pio", "artifacts", "sdkconfig.old"];
+
+/// The entries of `dir` (canonical, inside the project `root`), folders
+/// first. Nodes get paths under `shown_dir`, the path the UI knows `dir`
+/// by, which differs from `dir` when it was reached through a symlink.
+pub fn list(root: &Path, dir: &Path, shown_dir: &Path) -> Result<Vec<ExplorerNode>, FsError> {
+    let settings = project_settings::load(root);
+    let excludes = search::glob_set(&settings.explorer_excludes)?;
+    let ignores = Ignores::new(root, dir);
+    let relative_dir = dir.strip_prefix(root).unwrap_or(Path::new(""));
+    let dir_generated = relative_dir
+        .components()
+        .next()
+        .is_some_and(|c| is_generated_name(&c.as_os_str().to_string_lossy()));
+
+    let mut nodes = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| FsError::io(dir, e))? {
+        let entry = entry.map_err(|e| FsError::io(dir, e))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if HIDDEN_NAMES.contains(&name.as_str()) || excludes.is_match(relative_dir.join(&name)) {
+            continue;
+        }
+
+        let path = dir.join(&name);
+        let link_meta = match fs::symlink_metadata(&path) {
+            Ok(meta) => meta,
+            // removed while listing
+            Err(_) => continue,
+        };
+        let is_symlink = link_meta.file_type().is_symlink();
+        // a dangling link shows as a file
+        let meta = if is_symlink { fs::metadata(&path).unwrap_or(link_meta) } else { link_meta };
+
+        let mut node = node(&shown_dir.join(&name), &meta);
+        node.ignored = ignores.is_ignored(&path, meta.is_dir());
+        node.generated = dir_generated || (relative_dir.as_os_str().is_empty() && is_generated_name(&name));
+        node.is_symlink = is_symlink;
+        if is_symlink && meta.is_dir() && !can_expand(root, dir, &path) {
+            node.children = Some(Vec::new());
+        }
+        nodes.push(node);
+    }
+
+    nodes.sort_by(|a, b| {
+        (b.node_type == "folder")
+            .cmp(&(a.node_type == "folder"))
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(nodes)
+}
+
+/// A node for `path` with its metadata; folders aren't listed yet.
+pub fn node(path: &Path, meta: &Metadata) -> ExplorerNode {
+    let path_str = path.to_string_lossy().to_string();
+    ExplorerNode {
+        id: path_str.clone(),
+        name: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| path_str.clone()),
+        path: path_str,
+        node_type: if meta.is_dir() { "folder".into() } else { "file".into() },
+        children: None,
+        size: if meta.is_dir() { None } else { Some(meta.len()) },
+        modified: modified_ms(meta),
+        ignored: false,
+        generated: false,
+        is_symlink: false,
+    }
+}
+
+fn is_generated_name(name: &str) -> bool {
+    GENERATED_NAMES.contains(&name)
+}
+
+/// Whether the symlinked folder `link` in `dir` may be listed: its target
+/// has to be in the project and not one of the folders it sits in.
+fn can_expand(root: &Path, dir: &Path, link: &Path) -> bool {
+    match link.canonicalize() {
+        Ok(target) => target.starts_with(root) && !dir.starts_with(&target),
+        Err(_) => false,
+    }
+}
+
+/// The .gitignore files that apply inside one folder, the closest last,
+/// after .git/info/exclude.
+struct Ignores(Vec<Gitignore>);
+
+impl Ignores {
+    fn new(root: &Path, dir: &Path) -> Self {
+        let mut files = vec![(root.to_path_buf(), root.join(".git").join("info").join("exclude"))];
+        let mut current = root.to_path_buf();
+        files.push((current.clone(), current.join(".gitignore")));
+        for component in dir.strip_prefix(root).unwrap_or(Path::new("")).components() {
+            current.push(component);
+            files.push((current.clone(), current.join(".gitignore")));
+        }
+
+        let matchers = files
+            .into_iter()
+            .filter(|(_, file)| file.is_file())
+            .filter_map(|(base, file)| {
+                let mut builder = GitignoreBuilder::new(base);
+                // bad lines are skipped, the rest still applies
+                let _ = builder.add(file);
+                builder.build().ok()
+            })
+            .collect();
+        Ignores(matchers)
+    }
+
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for matcher in self.0.iter().rev() {
+            match matcher.matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,236 @@
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+use crate::models::artifact::{AppDescription, FirmwareImage, ImageKind, ImageSegment};
+use crate::services::partition_table;
+
+const IMAGE_MAGIC: u8 = 0xE9;
+const HEADER_LEN: usize = 24;
+const SEGMENT_HEADER_LEN: usize = 8;
+const CHECKSUM_SEED: u8 = 0xEF;
+const APP_DESC_MAGIC: u32 = 0xABCD_5432;
+/// More segments than this means we're not looking at a real header.
+const MAX_SEGMENTS: u8 = 16;
+
+/// `esp_chip_id_t` values from the extended image header.
+const CHIPS: [(u16, &str); 10] = [
+    (0, "esp32"),
+    (2, "esp32s2"),
+    (5, "esp32c3"),
+    (9, "esp32s3"),
+    (12, "esp32c2"),
+    (13, "esp32c6"),
+    (16, "esp32h2"),
+    (18, "esp32p4"),
+    (20, "esp32c61"),
+    (23, "esp32c5"),
+];
+
+pub fn chip_name(chip_id: u16) -> Option<&'static str> {
+    CHIPS.iter().find(|(id, _)| *id == chip_id).map(|(_, name)| *name)
+}
+
+/// Where the ROM loads the second-stage bootloader from, per chip.
+pub fn bootloader_offset(chip: &str) -> u32 {
+    match chip {
+        "esp32" | "esp32s2" => 0x1000,
+        "esp32p4" | "esp32c5" => 0x2000,
+        _ => 0x0,
+    }
+}
+
+/// "ESP32-S3", "esp32s3" and "esp32_s3" all name the same chip.
+pub fn normalize_chip(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// Parses `path` if it is an ESP firmware image. Returns `Ok(None)` for files
+/// that are something else (partition tables, NVS images, ...).
+pub fn read(path: &Path) -> Result<Option<FirmwareImage>, String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    Ok(parse(&data))
+}
+
+pub fn parse(data: &[u8]) -> Option<FirmwareImage> {
+    if let Some(app_offset) = merged_app_offset(data) {
+        let mut image = parse_image(&data[app_offset..], ImageKind::Merged);
+        image.app_offset = app_offset as u32;
+        return Some(image);
+    }
+
+    if data.first() != Some(&IMAGE_MAGIC) {
+        return None;
+    }
+
+    let mut image = parse_image(data, ImageKind::App);
+    if image.app_description.is_none() && image.errors.is_empty() {
+        image.kind = ImageKind::Bootloader;
+    }
+    Some(image)
+}
+
+/// Checks that `path` is a complete, intact image for `expected_chip`
+/// before it is sent anywhere.
+pub fn validate_for_flash(path: &Path, expected_chip: Option<&str>) -> Result<FirmwareImage, String> {
+    let image = read(path)?.ok_or("Not an ESP firmware image (bad magic byte)")?;
+
+    if !image.errors.is_empty() {
+        return Err(format!("Corrupt firmware image: {}", image.errors.join("; ")));
+    }
+
+    if let Some(expected) = expected_chip {
+        let actual = image.chip.as_deref().unwrap_or("unknown chip");
+        if normalize_chip(actual) != normalize_chip(expected) {
+            return Err(format!("Firmware was built for {}, but the target is {}", actual, expected));
+        }
+    }
+
+    Ok(image)
+}
+
+fn parse_image(data: &[u8], kind: ImageKind) -> FirmwareImage {
+    let mut image = FirmwareImage {
+        kind,
+        chip: None,
+        chip_id: 0,
+        entry_address: 0,
+        app_offset: 0,
+        segments: Vec::new(),
+        checksum_valid: false,
+        sha256_valid: None,
+        app_description: None,
+        errors: Vec::new(),
+    };
+
+    if data.len() < HEADER_LEN || data[0] != IMAGE_MAGIC {
+        image.errors.push("Missing 0xE9 image header".into());
+        return image;
+    }
+
+    let segment_count = data[1];
+    image.entry_address = u32_at(data, 4);
+    image.chip_id = u16::from_le_bytes([data[12], data[13]]);
+    image.chip = chip_name(image.chip_id).map(String::from);
+    let hash_appended = data[23] == 1;
+
+    if image.chip.is_none() {
+        image.errors.push(format!("Unknown chip id {}", image.chip_id));
+    }
+    if segment_count == 0 || segment_count > MAX_SEGMENTS {
+        image.errors.push(format!("Implausible segment count {}", segment_count));
+        return image;
+    }
+
+    let mut pos = HEADER_LEN;
+    let mut checksum = CHECKSUM_SEED;
+    for i in 0..segment_count {
+        if pos + SEGMENT_HEADER_LEN > data.len() {
+            image.errors.push(format!("Image truncated in segment {} header", i));
+            return image;
+        }
+        let load_address = u32_at(data, pos);
+        let length = u32_at(data, pos + 4);
+        let start = pos + SEGMENT_HEADER_LEN;
+        let end = start + length as usize;
+        if end > data.len() {
+            image.errors.push(format!("Segment {} runs past the end of the file", i));
+            return image;
+        }
+
+        checksum = data[start..end].iter().fold(checksum, |acc, b| acc ^ b);
+        image.segments.push(ImageSegment {
+            load_address,
+            offset: start as u32,
+            length,
+        });
+        pos = end;
+    }
+
+    // the checksum byte sits in the last byte of the next 16-byte block
+    let checksum_pos = pos | 0xF;
+    match data.get(checksum_pos) {
+        Some(&stored) => {
+            image.checksum_valid = stored == checksum;
+            if !image.checksum_valid {
+                image.errors.push(format!("Checksum mismatch (stored {:#04x}, computed {:#04x})", stored, checksum));
+            }
+        }
+        None => image.errors.push("Image truncated before checksum".into()),
+    }
+
+    if hash_appended {
+        let hashed_end = checksum_pos + 1;
+        match data.get(hashed_end..hashed_end + 32) {
+            Some(stored) => {
+                let valid = Sha256::digest(&data[..hashed_end]).as_slice() == stored;
+                image.sha256_valid = Some(valid);
+                if !valid {
+                    image.errors.push("SHA-256 digest mismatch".into());
+                }
+            }
+            None => {
+                image.sha256_valid = Some(false);
+                image.errors.push("Image truncated before SHA-256 digest".into());
+            }
+        }
+    }
+
+    image.app_description = image
+        .segments
+        .first()
+        .and_then(|s| read_app_description(&data[s.offset as usize..(s.offset + s.length) as usize]));
+
+    image
+}
+
+/// `esp_app_desc_t` layout: magic, secure_version, 2 reserved words, then
+/// version[32], project_name[32], time[16], date[16], idf_ver[32].
+fn read_app_description(segment: &[u8]) -> Option<AppDescription> {
+    if segment.len() < 144 || u32_at(segment, 0) != APP_DESC_MAGIC {
+        return None;
+    }
+
+    Some(AppDescription {
+        secure_version: u32_at(segment, 4),
+        version: c_string(&segment[16..48]),
+        project_name: c_string(&segment[48..80]),
+        compile_time: c_string(&segment[80..96]),
+        compile_date: c_string(&segment[96..112]),
+        idf_version: c_string(&segment[112..144]),
+    })
+}
+
+/// A merged binary starts with the bootloader (at 0x0, 0x1000 or 0x2000
+/// depending on the chip) and has a partition table at 0x8000; the app lives
+/// in the first app partition.
+/// Whether `data` starts like a merged flash image: a bootloader at one of
+/// the offsets the chips boot from, with 0xFF padding in front of it.
+pub fn is_merged_image(data: &[u8]) -> bool {
+    [0x0, 0x1000, 0x2000].iter().any(|off| data.get(*off) == Some(&IMAGE_MAGIC))
+}
+
+fn merged_app_offset(data: &[u8]) -> Option<usize> {
+    if !is_merged_image(data) {
+        return None;
+    }
+
+    let table = data.get(partition_table::DEFAULT_TABLE_OFFSET as usize..)?;
+    partition_table::parse_binary(table)
+        .ok()?
+        .into_iter()
+        .find(|p| p.partition_type == "app")
+        .map(|p| p.offset as usize)
+        .filter(|off| data.get(*off) == Some(&IMAGE_MAGIC))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).to_string()
+}
@@ -0,0 +1,226 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{validate_project_name, CreateOptions, FrameworkBackend};
+use crate::models::flash::{FlashImage, FlashLayout};
+use crate::services::{arduino_index, arduino_packages, artifacts, firmware_image, ino_preprocessor, partition_table, project_settings, sdkconfig};
+use crate::services::process_runner::RunOptions;
+
+/// Arduino sketch folders (`<name>/<name>.ino`) built with `arduino-cli`.
+pub struct Arduino;
+
+const SKETCH_TEMPLATE: &str = "void setup() {
+  // put your setup code here, to run once:
+
+}
+
+void loop() {
+  // put your main code here, to run repeatedly:
+
+}
+";
+
+impl FrameworkBackend for Arduino {
+    fn create(&self, parent: &Path, name: &str, options: &CreateOptions) -> Result<PathBuf, String> {
+        validate_project_name(name)?;
+        if let Some(fqbn) = &options.fqbn {
+            validate_fqbn(fqbn)?;
+        }
+
+        let project_path = parent.join(name);
+        if project_path.exists() {
+            return Err(format!("{} already exists", project_path.display()));
+        }
+        fs::create_dir_all(&project_path).map_err(|e| e.to_string())?;
+        fs::write(project_path.join(format!("{}.ino", name)), SKETCH_TEMPLATE).map_err(|e| e.to_string())?;
+
+        if options.fqbn.is_some() {
+            let mut settings = project_settings::load(&project_path);
+            settings.fqbn = options.fqbn.clone();
+            project_settings::save(&project_path, &settings)?;
+        }
+
+        Ok(project_path)
+    }
+
+    fn build_command(&self, project: &Path, log: &mut dyn FnMut(String)) -> Result<RunOptions, String> {
+        let fqbn = project_settings::load(project)
+            .fqbn
+            .ok_or("No board selected for this sketch, set its FQBN first")?;
+        let cli = find_arduino_cli().ok_or("arduino-cli not found (install it or set ARDUINO_CLI)")?;
+
+        // arduino-cli gets our merged tabs, so prototypes and #line
+        // directives are the ones the editor shows in its preview
+        let sketch = ino_preprocessor::write_build_sketch(project, &project_settings::editor_dir(project).join("sketch"))?;
+        project_settings::ensure_gitignore(project);
+
+        log(format!(" Compiling sketch for {}...", fqbn));
+
+        // build/ is reused between runs, arduino-cli only recompiles what changed
+        let mut opts = RunOptions::new(cli.to_string_lossy())
+            .arg("compile")
+            .arg("--fqbn")
+            .arg(fqbn)
+            .arg("--build-path")
+            .arg(project.join("build").to_string_lossy());
+
+        // cores and shared libraries from where the board and library
+        // manager installed them; arduino-cli reads `<user dir>/libraries`
+        let settings = arduino_index::load_settings();
+        opts = opts.env("ARDUINO_DIRECTORIES_DATA", arduino_packages::data_dir(&settings)?.to_string_lossy());
+        let shared = arduino_packages::shared_libraries_dir(&settings)?;
+        match shared.parent().filter(|_| shared.file_name().is_some_and(|n| n == "libraries")) {
+            Some(user_dir) => opts = opts.env("ARDUINO_DIRECTORIES_USER", user_dir.to_string_lossy()),
+            None => opts = opts.arg("--libraries").arg(shared.to_string_lossy()),
+        }
+
+        // libraries installed for this project only
+        let libraries = arduino_packages::project_libraries_dir(project);
+        if libraries.is_dir() {
+            opts = opts.arg("--libraries").arg(libraries.to_string_lossy());
+        }
+
+        Ok(opts.arg(sketch.to_string_lossy()).cwd(project))
+    }
+
+    /// Removes build/ if arduino-cli made it (or it is empty).
+    fn clean(&self, project: &Path) -> Result<(), String> {
+        let build_dir = project.join("build");
+        if !build_dir.exists() {
+            return Ok(());
+        }
+
+        let is_empty = fs::read_dir(&build_dir)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(false);
+        if !is_empty && !build_dir.join("build.options.json").is_file() {
+            return Err(format!(
+                "{} doesn't look like an arduino-cli build folder, refusing to delete it",
+                build_dir.display()
+            ));
+        }
+        fs::remove_dir_all(&build_dir).map_err(|e| e.to_string())
+    }
+
+    /// `<sketch>.ino.bin`, `.bootloader.bin`, `.partitions.bin`,
+    /// `.merged.bin`, `.elf` and `.map` from build/, plus the sdkconfig the
+    /// core copies there (it has the flash offsets the images were built for).
+    fn collect_artifacts(&self, project: &Path) -> Result<PathBuf, String> {
+        let sketch = sketch_name(project)?;
+        let build_dir = project.join("build");
+        let prefix = format!("{}.ino.", sketch);
+
+        let mut sources: Vec<PathBuf> = fs::read_dir(&build_dir)
+            .map_err(|e| format!("{}: {}", build_dir.display(), e))?
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| {
+                let name = p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                name.starts_with(&prefix) && (name.ends_with(".bin") || name.ends_with(".elf") || name.ends_with(".map"))
+            })
+            .collect();
+        sources.sort();
+        sources.push(build_dir.join("sdkconfig"));
+
+        artifacts::store(project, &sources, Some(sketch))
+    }
+
+    /// The merged image at 0 when the core produced one; otherwise
+    /// bootloader, partition table and app. Bootloader and table offsets come
+    /// from the core's sdkconfig, falling back to the chip's defaults; the app
+    /// offset comes from the table.
+    fn flash_layout(&self, dir: &Path) -> Result<FlashLayout, String> {
+        let file = |suffix: &str| {
+            fs::read_dir(dir)
+                .ok()?
+                .flatten()
+                .map(|e| e.path())
+                .find(|p| p.to_string_lossy().ends_with(suffix))
+        };
+        let image = |offset: u32, path: PathBuf| FlashImage {
+            offset,
+            path: path.to_string_lossy().to_string(),
+        };
+
+        if let Some(merged) = file(".ino.merged.bin") {
+            let chip = firmware_image::read(&merged).ok().flatten().and_then(|i| i.chip);
+            return Ok(FlashLayout {
+                chip,
+                flash_mode: None,
+                flash_freq: None,
+                flash_size: None,
+                images: vec![image(0, merged)],
+            });
+        }
+
+        let bootloader = file(".ino.bootloader.bin").ok_or("Bootloader image not found")?;
+        let partitions = file(".ino.partitions.bin").ok_or("Partition table image not found")?;
+        let app = file(".ino.bin").ok_or("Application image not found")?;
+
+        let chip = firmware_image::read(&bootloader).ok().flatten().and_then(|i| i.chip);
+        let build_config = dir.join("sdkconfig");
+        let offset_setting = |name: &str| {
+            sdkconfig::read_value(&build_config, name)
+                .and_then(|v| u32::from_str_radix(v.trim_start_matches("0x"), 16).ok())
+        };
+        let bootloader_offset = offset_setting("BOOTLOADER_OFFSET_IN_FLASH")
+            .or_else(|| chip.as_deref().map(firmware_image::bootloader_offset))
+            .unwrap_or(0x0);
+        let table_offset = offset_setting("PARTITION_TABLE_OFFSET").unwrap_or(partition_table::DEFAULT_TABLE_OFFSET);
+
+        let table = fs::read(&partitions).map_err(|e| e.to_string())?;
+        let app_offset = partition_table::parse_binary(&table)?
+            .iter()
+            .find(|p| p.partition_type == "app")
+            .map(|p| p.offset)
+            .ok_or("Partition table has no app partition")?;
+
+        Ok(FlashLayout {
+            chip,
+            flash_mode: None,
+            flash_freq: None,
+            flash_size: None,
+            images: vec![
+                image(bootloader_offset, bootloader),
+                image(table_offset, partitions),
+                image(app_offset, app),
+            ],
+        })
+    }
+}
+
+/// `vendor:arch:board`, optionally followed by `:option=value,...`.
+pub fn validate_fqbn(fqbn: &str) -> Result<(), String> {
+    let parts: Vec<&str> = fqbn.split(':').collect();
+    if parts.len() < 3 || parts.len() > 4 || parts[..3].iter().any(|p| p.is_empty()) {
+        return Err(format!("Invalid FQBN '{}', expected vendor:arch:board", fqbn));
+    }
+    Ok(())
+}
+
+/// Arduino's rules: letters, digits, `_`, `-` and `.`, not starting with
+/// `-` or `.`, at most 63 characters.
+/// The sketch's name is the folder's; its main tab is `<name>.ino`.
+fn sketch_name(project: &Path) -> Result<String, String> {
+    project
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| "Invalid sketch folder".to_string())
+}
+
+/// `$ARDUINO_CLI`, then `arduino-cli` on PATH or in ~/bin / ~/.local/bin.
+fn find_arduino_cli() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("ARDUINO_CLI").map(PathBuf::from).filter(|p| p.is_file()) {
+        return Some(path);
+    }
+
+    let exe = if cfg!(windows) { "arduino-cli.exe" } else { "arduino-cli" };
+    let mut search: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|p| std::env::split_paths(&p).collect())
+        .unwrap_or_default();
+    if let Some(home) = dirs::home_dir() {
+        search.push(home.join("bin"));
+        search.push(home.join(".local/bin"));
+    }
+    search.into_iter().map(|d| d.join(exe)).find(|p| p.is_file())
+}
@@ -0,0 +1,201 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::{CreateOptions, FrameworkBackend};
+use crate::models::flash::{FlashImage, FlashLayout};
+use crate::services::{artifacts, build_profiles, idf_env, process_runner, project_settings, toolchain};
+use crate::services::process_runner::RunOptions;
+
+/// CMake projects built with `idf.py`.
+pub struct EspIdf;
+
+impl FrameworkBackend for EspIdf {
+    fn create(&self, parent: &Path, name: &str, options: &CreateOptions) -> Result<PathBuf, String> {
+        let toolchain = match &options.toolchain_id {
+            Some(id) => toolchain::find(id)?,
+            None => toolchain::default_toolchain()?,
+        };
+        let python = toolchain
+            .python
+            .clone()
+            .ok_or("ESP-IDF python environment not found")?;
+        let idf_py = toolchain::idf_py(&toolchain);
+
+        // Run create-project in the parent folder, idf.py will create the project folder
+        let opts = RunOptions::new(python)
+            .arg(idf_py.to_string_lossy())
+            .arg("create-project")
+            .arg(name)
+            .cwd(parent)
+            .env("IDF_PATH", &toolchain.idf_path)
+            .env("PYTHONPATH", "")
+            .timeout(Duration::from_secs(120));
+
+        let (exit, output) = process_runner::run_collect(opts)
+            .map_err(|e| format!("Failed to run idf.py: {}", e))?;
+
+        if !exit.success {
+            return Err(format!(
+                "idf.py create-project failed with exit code: {}\n{}",
+                exit.code.unwrap_or(-1),
+                output.trim()
+            ));
+        }
+
+        let project_path = parent.join(name);
+
+        if options.toolchain_id.is_some() {
+            let mut settings = project_settings::load(&project_path);
+            settings.toolchain_id = options.toolchain_id.clone();
+            project_settings::save(&project_path, &settings)?;
+        }
+
+        Ok(project_path)
+    }
+
+    fn build_command(&self, project: &Path, log: &mut dyn FnMut(String)) -> Result<RunOptions, String> {
+        let toolchain = toolchain::for_project(project)?;
+
+        if !toolchain::export_script(&toolchain).exists() {
+            return Err("ESP-IDF export.sh not found".into());
+        }
+        let idf_env = idf_env::environment(&toolchain)?;
+
+        log(format!(" Starting ESP-IDF build ({})...", toolchain.version.as_deref().unwrap_or(&toolchain.idf_path)));
+        let (set_target, idf_args) = match build_profiles::active(project) {
+            Some(profile) => {
+                log(format!(" Using build profile '{}' ({})", profile.name, profile.target));
+                let args = build_profiles::prepare(project, &profile)
+                    .map_err(|e| format!("Failed to apply build profile: {}", e))?;
+                (args.set_target, args.idf_args)
+            }
+            None => (None, Vec::new()),
+        };
+
+        let idf = std::iter::once("idf.py".to_string())
+            .chain(idf_args.iter().map(|a| shell_quote(a)))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let set_target = match set_target {
+            Some(target) => format!("{} set-target {}", idf, shell_quote(&target)),
+            None => String::new(),
+        };
+
+// build/ is left in place between runs so ninja only rebuilds what changed;
+// the export.sh environment is injected instead of sourced every time
+let command = format!(
+r#"
+set -e
+{}
+{} build
+idf.py merge-bin -o merged.bin
+"#,
+set_target,
+idf
+);
+
+        Ok(RunOptions::new("bash")
+            .arg("-c")
+            .arg(command)
+            .cwd(project)
+            .envs(idf_env))
+    }
+
+    /// Same as `idf.py fullclean`: build/ is only removed if it is a CMake
+    /// build folder (or empty).
+    fn clean(&self, project: &Path) -> Result<(), String> {
+        let build_dir = project.join("build");
+        if !build_dir.exists() {
+            return Ok(());
+        }
+
+        let is_empty = fs::read_dir(&build_dir)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(false);
+        if !is_empty && !build_dir.join("CMakeCache.txt").is_file() {
+            return Err(format!(
+                "{} doesn't look like a CMake build folder, refusing to delete it",
+                build_dir.display()
+            ));
+        }
+        fs::remove_dir_all(&build_dir).map_err(|e| e.to_string())
+    }
+
+    fn collect_artifacts(&self, project: &Path) -> Result<PathBuf, String> {
+        let build_dir = project.join("build");
+        let desc = read_project_description(&build_dir);
+
+        let mut sources: Vec<PathBuf> = vec![
+            build_dir.join("merged.bin"),
+            build_dir.join("bootloader/bootloader.bin"),
+            build_dir.join("bootloader/bootloader.elf"),
+            build_dir.join("partition_table/partition-table.bin"),
+            build_dir.join("flasher_args.json"),
+            build_dir.join("project_description.json"),
+        ];
+        for key in ["app_bin", "app_elf"] {
+            if let Some(name) = desc.as_ref().and_then(|d| d[key].as_str()) {
+                sources.push(build_dir.join(name));
+            }
+        }
+        if let Some(elf) = desc.as_ref().and_then(|d| d["app_elf"].as_str()) {
+            sources.push(build_dir.join(Path::new(elf).with_extension("map")));
+        }
+
+        let project_name = desc
+            .as_ref()
+            .and_then(|d| d["project_name"].as_str())
+            .map(String::from);
+        artifacts::store(project, &sources, project_name)
+    }
+
+    /// Read from flasher_args.json. Collected artifacts are stored flat, so
+    /// files are looked up by name when the relative path doesn't exist.
+    fn flash_layout(&self, dir: &Path) -> Result<FlashLayout, String> {
+        let data = fs::read_to_string(dir.join("flasher_args.json"))
+            .map_err(|e| format!("flasher_args.json: {}", e))?;
+        let args: serde_json::Value = serde_json::from_str(&data).map_err(|e| e.to_string())?;
+
+        let mut images = Vec::new();
+        if let Some(files) = args["flash_files"].as_object() {
+            for (offset, file) in files {
+                let offset = u32::from_str_radix(offset.trim_start_matches("0x"), 16)
+                    .map_err(|_| format!("Bad flash offset {}", offset))?;
+                let file = file.as_str().unwrap_or_default();
+
+                let mut path = dir.join(file);
+                if !path.is_file() {
+                    if let Some(name) = Path::new(file).file_name() {
+                        path = dir.join(name);
+                    }
+                }
+                images.push(FlashImage {
+                    offset,
+                    path: path.to_string_lossy().to_string(),
+                });
+            }
+        }
+        images.sort_by_key(|i| i.offset);
+
+        let setting = |key: &str| args["flash_settings"][key].as_str().map(String::from);
+        Ok(FlashLayout {
+            chip: args["extra_esptool_args"]["chip"].as_str().map(String::from),
+            flash_mode: setting("flash_mode"),
+            flash_freq: setting("flash_freq"),
+            flash_size: setting("flash_size"),
+            images,
+        })
+    }
+}
+
+fn read_project_description(build_dir: &Path) -> Option<serde_json::Value> {
+    let data = fs::read_to_string(build_dir.join("project_description.json")).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+/// Single-quotes `s` for `bash -c`.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
@@ -0,0 +1,99 @@
+pub mod arduino;
+pub mod esp_idf;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::flash::FlashLayout;
+use crate::models::project::ProjectFramework;
+use crate::services::process_runner::RunOptions;
+
+/// Options for `FrameworkBackend::create`; each backend uses what applies to it.
+#[derive(Default)]
+pub struct CreateOptions {
+    pub toolchain_id: Option<String>,
+    /// Board for Arduino sketches, e.g. `esp32:esp32:esp32s3`.
+    pub fqbn: Option<String>,
+}
+
+/// Everything the build/project commands need to know about one kind of
+/// project. Commands pick the backend with `for_project` and stay
+/// framework-agnostic.
+pub trait FrameworkBackend {
+    /// Creates `parent/name` and returns its path.
+    fn create(&self, parent: &Path, name: &str, options: &CreateOptions) -> Result<PathBuf, String>;
+
+    /// The command that builds the project, ready to spawn. Progress worth
+    /// showing before the build starts goes to `log`.
+    fn build_command(&self, project: &Path, log: &mut dyn FnMut(String)) -> Result<RunOptions, String>;
+
+    /// Removes build outputs so the next build starts from scratch.
+    fn clean(&self, project: &Path) -> Result<(), String>;
+
+    /// Copies the outputs of a successful build into a new
+    /// `artifacts/build-<n>` folder and returns it.
+    fn collect_artifacts(&self, project: &Path) -> Result<PathBuf, String>;
+
+    /// Offsets of the files in `dir` (an artifacts folder or the build
+    /// folder) for flashing.
+    fn flash_layout(&self, dir: &Path) -> Result<FlashLayout, String>;
+}
+
+/// A project name has to be a single folder name that every backend
+/// accepts; arduino-cli is the strictest about sketch names.
+pub fn validate_project_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 63
+        && !name.starts_with(['-', '.'])
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c));
+    if !valid {
+        return Err(format!("'{}' is not a valid project name", name));
+    }
+    Ok(())
+}
+
+/// Guesses the project type from the files in `project`.
+pub fn detect(project: &Path) -> Option<ProjectFramework> {
+    if project.join("platformio.ini").is_file() {
+        return Some(ProjectFramework::PlatformIo);
+    }
+
+    if let Ok(manifest) = fs::read_to_string(project.join("Cargo.toml")) {
+        let esp_crates = ["esp-idf-svc", "esp-idf-hal", "esp-idf-sys", "esp-hal"];
+        if esp_crates.iter().any(|c| manifest.contains(c)) {
+            return Some(ProjectFramework::RustEsp);
+        }
+    }
+
+    let cmake = fs::read_to_string(project.join("CMakeLists.txt")).unwrap_or_default();
+    if cmake.contains("project.cmake") || (!cmake.is_empty() && project.join("main").is_dir()) {
+        return Some(ProjectFramework::EspIdf);
+    }
+
+    let has_sketch = fs::read_dir(project)
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|e| e.path().extension().and_then(|x| x.to_str()) == Some("ino"))
+        })
+        .unwrap_or(false);
+    if has_sketch {
+        return Some(ProjectFramework::Arduino);
+    }
+
+    None
+}
+
+pub fn backend(framework: ProjectFramework) -> Result<Box<dyn FrameworkBackend>, String> {
+    match framework {
+        ProjectFramework::EspIdf => Ok(Box::new(esp_idf::EspIdf)),
+        ProjectFramework::Arduino => Ok(Box::new(arduino::Arduino)),
+        other => Err(format!("{:?} projects are not supported yet", other)),
+    }
+}
+
+pub fn for_project(project: &Path) -> Result<Box<dyn FrameworkBackend>, String> {
+    let framework = detect(project)
+        .ok_or_else(|| format!("{} is not a recognised project folder", project.display()))?;
+    backend(framework)
+}
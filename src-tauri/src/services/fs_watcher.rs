@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::models::watcher::{FsChange, FsChangeKind};
+use crate::services::project_settings::EDITOR_DIR;
+
+/// Quiet time before a batch goes out.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+/// A batch goes out after this long even if changes keep coming.
+const MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// Top-level folders that aren't watched: build output, VCS data and the
+/// editor's own files change constantly and nobody edits them.
+const SKIPPED_DIRS: [&str; 3] = ["build", ".git", EDITOR_DIR];
+
+/// Watches `root` and calls `on_batch` from a background thread with the
+/// changes of each quiet period, until the returned watcher is dropped.
+pub fn watch(
+    root: &Path,
+    mut on_batch: impl FnMut(Vec<FsChange>) + Send + 'static,
+) -> Result<Arc<Mutex<RecommendedWatcher>>, String> {
+    let root = root.canonicalize().map_err(|e| format!("{}: {}", root.display(), e))?;
+
+    let (tx, rx) = mpsc::channel();
+    let watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        let _ = tx.send(res);
+    })
+    .map_err(|e| e.to_string())?;
+    let watcher = Arc::new(Mutex::new(watcher));
+
+    // recursive watches per top-level folder so build/ never gets any
+    {
+        let mut w = watcher.lock().unwrap();
+        w.watch(&root, RecursiveMode::NonRecursive).map_err(|e| e.to_string())?;
+        for entry in fs::read_dir(&root).map_err(|e| e.to_string())?.flatten() {
+            let path = entry.path();
+            if path.is_dir() && !is_skipped(&root, &path) {
+                w.watch(&path, RecursiveMode::Recursive).map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    let weak = Arc::downgrade(&watcher);
+    thread::spawn(move || {
+        let mut pending = Pending::default();
+        loop {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(Ok(event)) => {
+                    for dir in pending.push(&root, event) {
+                        if let Some(watcher) = weak.upgrade() {
+                            let _ = watcher.lock().unwrap().watch(&dir, RecursiveMode::Recursive);
+                        }
+                    }
+                    if pending.since.is_some_and(|t| t.elapsed() >= MAX_DELAY) {
+                        pending.flush(&root, &mut on_batch);
+                    }
+                }
+                // backend hiccups (e.g. a queue overflow) aren't fatal
+                Ok(Err(_)) => {}
+                Err(RecvTimeoutError::Timeout) => {
+                    if pending.since.is_some() {
+                        pending.flush(&root, &mut on_batch);
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+
+    Ok(watcher)
+}
+
+fn is_skipped(root: &Path, path: &Path) -> bool {
+    let relative = match path.strip_prefix(root) {
+        Ok(r) => r,
+        Err(_) => return true,
+    };
+    let top = relative.components().next().map(|c| c.as_os_str().to_string_lossy().to_string());
+    if top.is_some_and(|t| SKIPPED_DIRS.contains(&t.as_str())) {
+        return true;
+    }
+    // temporary files of atomic saves (`.name.<id>.tmp`)
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    name.starts_with('.') && name.ends_with(".tmp")
+}
+
+/// Changes since the last batch, one per path, merged so a batch says
+/// what happened overall (created then modified is created, created then
+/// removed is nothing).
+#[derive(Default)]
+struct Pending {
+    changes: BTreeMap<PathBuf, (FsChangeKind, Option<PathBuf>)>,
+    /// Old name of a rename whose new name hasn't been reported yet.
+    rename_from: Option<PathBuf>,
+    since: Option<Instant>,
+}
+
+impl Pending {
+    /// Records `event`; returns new top-level folders that need a watch.
+    fn push(&mut self, root: &Path, event: Event) -> Vec<PathBuf> {
+        let paths = event.paths;
+        match event.kind {
+            EventKind::Create(_) => paths.iter().for_each(|p| self.record(root, p, FsChangeKind::Created)),
+            EventKind::Remove(_) => paths.iter().for_each(|p| self.record(root, p, FsChangeKind::Removed)),
+            EventKind::Modify(ModifyKind::Name(mode)) => match (mode, paths.as_slice()) {
+                (RenameMode::Both, [from, to]) => self.rename(root, from, to),
+                (RenameMode::From, [from]) => {
+                    // removed unless the new name follows
+                    self.since.get_or_insert_with(Instant::now);
+                    if let Some(previous) = self.rename_from.replace(from.clone()) {
+                        self.record(root, &previous, FsChangeKind::Removed);
+                    }
+                }
+                (RenameMode::To, [to]) => match self.rename_from.take() {
+                    Some(from) => self.rename(root, &from, to),
+                    None => self.record(root, to, FsChangeKind::Created),
+                },
+                // backends that can't tell which side of the rename a path is
+                _ => paths.iter().for_each(|p| {
+                    let kind = if p.exists() { FsChangeKind::Created } else { FsChangeKind::Removed };
+                    self.record(root, p, kind)
+                }),
+            },
+            // permission and timestamp changes don't change content
+            EventKind::Modify(ModifyKind::Metadata(_)) => {}
+            EventKind::Modify(_) => paths.iter().for_each(|p| self.record(root, p, FsChangeKind::Modified)),
+            _ => {}
+        }
+
+        paths
+            .into_iter()
+            .filter(|p| p.parent() == Some(root) && p.is_dir() && !is_skipped(root, p))
+            .filter(|p| matches!(self.changes.get(p), Some((FsChangeKind::Created, _)) | Some((FsChangeKind::Renamed, _))))
+            .collect()
+    }
+
+    fn record(&mut self, root: &Path, path: &Path, kind: FsChangeKind) {
+        use FsChangeKind::*;
+        if is_skipped(root, path) {
+            return;
+        }
+        self.since.get_or_insert_with(Instant::now);
+
+        let previous = self.changes.get(path).cloned();
+        let merged = match (previous.as_ref().map(|c| c.0), kind) {
+            (Some(Created), Modified) => Some((Created, None)),
+            (Some(Created), Removed) => None,
+            (Some(Removed), Created) => Some((Modified, None)),
+            (Some(Renamed), Modified) => previous,
+            (_, kind) => Some((kind, None)),
+        };
+        match merged {
+            Some(change) => self.changes.insert(path.to_path_buf(), change),
+            None => self.changes.remove(path),
+        };
+    }
+
+    fn rename(&mut self, root: &Path, from: &Path, to: &Path) {
+        match (is_skipped(root, from), is_skipped(root, to)) {
+            (true, true) => {}
+            // an atomic save renames a temporary file over the real one
+            (true, false) => self.record(root, to, FsChangeKind::Modified),
+            (false, true) => self.record(root, from, FsChangeKind::Removed),
+            (false, false) => {
+                self.since.get_or_insert_with(Instant::now);
+                let change = match self.changes.remove(from) {
+                    Some((FsChangeKind::Created, _)) => (FsChangeKind::Created, None),
+                    Some((FsChangeKind::Renamed, original)) => (FsChangeKind::Renamed, original),
+                    _ => (FsChangeKind::Renamed, Some(from.to_path_buf())),
+                };
+                self.changes.insert(to.to_path_buf(), change);
+            }
+        }
+    }
+
+    /// Hands the merged changes to `on_batch`, unless they cancelled out.
+    fn flush(&mut self, root: &Path, on_batch: &mut impl FnMut(Vec<FsChange>)) {
+        if let Some(from) = self.rename_from.take() {
+            self.record(root, &from, FsChangeKind::Removed);
+        }
+        self.since = None;
+        if self.changes.is_empty() {
+            return;
+        }
+
+        let changes = std::mem::take(&mut self.changes)
+            .into_iter()
+            .map(|(path, (kind, from))| FsChange {
+                kind,
+                is_dir: path.is_dir(),
+                path: path.to_string_lossy().to_string(),
+                from: from.map(|f| f.to_string_lossy().to_string()),
+            })
+            .collect();
+        on_batch(changes);
+    }
+}
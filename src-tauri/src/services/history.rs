@@ -0,0 +1,256 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use similar::{ChangeTag, TextDiff};
+use uuid::Uuid;
+
+use crate::models::history::{DiffHunk, DiffLine, DiffLineKind, HistoryEntry};
+use crate::models::workspace::{FsError, FsErrorKind};
+use crate::services::project_settings::{editor_dir, ensure_gitignore};
+use crate::utils::fs::{content_hash, write_atomic};
+
+/// Versions older than this are dropped.
+const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Compressed size of a project's history; the oldest versions go first.
+const MAX_TOTAL_SIZE: u64 = 50 * 1024 * 1024;
+/// Bigger files aren't kept, they are rarely hand-written.
+const MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+/// Unchanged lines shown around each diff hunk.
+const DIFF_CONTEXT: usize = 3;
+
+/// Serialises updates of the history indexes.
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+/// Snapshots live in `<project>/.veditor/history/objects/<hash>.gz`, shared
+/// by versions with the same content, and are listed in `index.json`
+/// oldest first.
+fn history_dir(project_path: &Path) -> PathBuf {
+    editor_dir(project_path).join("history")
+}
+
+fn object_file(project_path: &Path, hash: &str) -> PathBuf {
+    history_dir(project_path).join("objects").join(format!("{}.gz", hash))
+}
+
+fn load_index(project_path: &Path) -> Vec<HistoryEntry> {
+    fs::read_to_string(history_dir(project_path).join("index.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(project_path: &Path, entries: &[HistoryEntry]) -> Result<(), FsError> {
+    let data = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
+    ensure_gitignore(project_path);
+    Ok(write_atomic(&history_dir(project_path).join("index.json"), data.as_bytes())?)
+}
+
+/// `path` relative to the project, with `/` separators on every platform.
+fn relative_key(project_path: &Path, path: &Path) -> Result<String, FsError> {
+    let relative = path
+        .strip_prefix(project_path)
+        .map_err(|_| FsError::new(FsErrorKind::OutsideWorkspace, path, format!("{} is outside the project", path.display())))?;
+    Ok(relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Records a save of `path` (inside `project_path`, both canonical).
+/// `previous` is what the file held before; it's kept too when the file
+/// has no history yet, so the first save can be rolled back, stamped with
+/// `previous_modified` (the file's mtime before the save) when known.
+pub fn record_save(
+    project_path: &Path,
+    path: &Path,
+    previous: Option<&[u8]>,
+    previous_modified: Option<u64>,
+    data: &[u8],
+) -> Result<(), FsError> {
+    let _lock = INDEX_LOCK.lock().unwrap();
+    let key = relative_key(project_path, path)?;
+    let mut entries = load_index(project_path);
+    let now = now_ms();
+
+    if let Some(previous) = previous {
+        if !entries.iter().any(|e| e.path == key) {
+            add_version(project_path, &mut entries, &key, previous, previous_modified.unwrap_or(now))?;
+        }
+    }
+    add_version(project_path, &mut entries, &key, data, now)?;
+
+    prune(project_path, &mut entries);
+    save_index(project_path, &entries)
+}
+
+fn add_version(
+    project_path: &Path,
+    entries: &mut Vec<HistoryEntry>,
+    key: &str,
+    data: &[u8],
+    saved_at: u64,
+) -> Result<(), FsError> {
+    if data.len() as u64 > MAX_FILE_SIZE {
+        return Ok(());
+    }
+    let hash = content_hash(data);
+    if entries.iter().rev().find(|e| e.path == key).is_some_and(|e| e.hash == hash) {
+        return Ok(());
+    }
+
+    let object = object_file(project_path, &hash);
+    if !object.exists() {
+        let dir = object.parent().unwrap_or(project_path);
+        fs::create_dir_all(dir).map_err(|e| FsError::io(dir, e))?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let compressed = encoder
+            .write_all(data)
+            .and_then(|_| encoder.finish())
+            .map_err(|e| FsError::io(&object, e))?;
+        write_atomic(&object, &compressed)?;
+    }
+    let stored_size = fs::metadata(&object).map(|m| m.len()).unwrap_or(0);
+
+    entries.push(HistoryEntry {
+        id: Uuid::new_v4().simple().to_string(),
+        path: key.to_string(),
+        hash,
+        size: data.len() as u64,
+        stored_size,
+        saved_at,
+    });
+    Ok(())
+}
+
+/// Drops versions past the age limit, then the oldest ones until the
+/// history fits its size limit, and deletes snapshots nothing uses anymore.
+/// A version's age counts from when the next one replaced it, so the
+/// snapshot taken before a file's first save isn't dropped right away.
+fn prune(project_path: &Path, entries: &mut Vec<HistoryEntry>) {
+    let cutoff = now_ms().saturating_sub(MAX_AGE.as_millis() as u64);
+    let mut replaced_at: HashMap<String, u64> = HashMap::new();
+    let mut keep: Vec<bool> = entries
+        .iter()
+        .rev()
+        .map(|e| {
+            let current_until = replaced_at.insert(e.path.clone(), e.saved_at).unwrap_or(e.saved_at);
+            current_until >= cutoff
+        })
+        .collect();
+    keep.reverse();
+    let mut keep = keep.into_iter();
+    entries.retain(|_| keep.next().unwrap_or(true));
+
+    let mut total: u64 = unique_objects(entries).values().sum();
+    while total > MAX_TOTAL_SIZE && entries.len() > 1 {
+        let removed = entries.remove(0);
+        if !entries.iter().any(|e| e.hash == removed.hash) {
+            total -= removed.stored_size;
+        }
+    }
+
+    let used: HashSet<String> = entries.iter().map(|e| format!("{}.gz", e.hash)).collect();
+    let objects = history_dir(project_path).join("objects");
+    for entry in fs::read_dir(objects).into_iter().flatten().flatten() {
+        if !used.contains(&entry.file_name().to_string_lossy().to_string()) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+fn unique_objects(entries: &[HistoryEntry]) -> HashMap<&str, u64> {
+    entries.iter().map(|e| (e.hash.as_str(), e.stored_size)).collect()
+}
+
+/// Saved versions of `path`, newest first.
+pub fn list(project_path: &Path, path: &Path) -> Result<Vec<HistoryEntry>, FsError> {
+    let key = relative_key(project_path, path)?;
+    let mut entries: Vec<HistoryEntry> = load_index(project_path).into_iter().filter(|e| e.path == key).collect();
+    entries.reverse();
+    Ok(entries)
+}
+
+/// The content of version `id` of `path`.
+pub fn read(project_path: &Path, path: &Path, id: &str) -> Result<Vec<u8>, FsError> {
+    let key = relative_key(project_path, path)?;
+    let entry = load_index(project_path)
+        .into_iter()
+        .find(|e| e.id == id && e.path == key)
+        .ok_or_else(|| FsError::new(FsErrorKind::NotFound, path, format!("Version {} of {} not found", id, key)))?;
+
+    let object = object_file(project_path, &entry.hash);
+    let compressed = fs::read(&object).map_err(|e| FsError::io(&object, e))?;
+    let mut data = Vec::with_capacity(entry.size as usize);
+    GzDecoder::new(compressed.as_slice())
+        .read_to_end(&mut data)
+        .map_err(|e| FsError::io(&object, e))?;
+    Ok(data)
+}
+
+/// Line diff from `old` to `new`, as hunks with a few lines of context.
+pub fn diff(old: &str, new: &str) -> Vec<DiffHunk> {
+    let diff = TextDiff::from_lines(old, new);
+    diff.grouped_ops(DIFF_CONTEXT)
+        .iter()
+        .filter_map(|group| {
+            let (first, last) = (group.first()?, group.last()?);
+            let lines = group
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| DiffLine {
+                    kind: match change.tag() {
+                        ChangeTag::Equal => DiffLineKind::Context,
+                        ChangeTag::Insert => DiffLineKind::Added,
+                        ChangeTag::Delete => DiffLineKind::Removed,
+                    },
+                    old_line: change.old_index().map(|i| i + 1),
+                    new_line: change.new_index().map(|i| i + 1),
+                    text: change.value().trim_end_matches(['\r', '\n']).to_string(),
+                })
+                .collect();
+            Some(DiffHunk {
+                old_start: first.old_range().start + 1,
+                old_lines: last.old_range().end - first.old_range().start,
+                new_start: first.new_range().start + 1,
+                new_lines: last.new_range().end - first.new_range().start,
+                lines,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_save_keeps_the_old_content_with_its_own_time() {
+        let project = std::env::temp_dir().join(format!("history-{}", Uuid::new_v4()));
+        fs::create_dir_all(&project).unwrap();
+        let file = project.join("main.c");
+        let old_mtime = now_ms() - 90 * 24 * 60 * 60 * 1000;
+
+        record_save(&project, &file, Some(b"old"), Some(old_mtime), b"new").unwrap();
+        let versions = list(&project, &file).unwrap();
+        let _ = fs::remove_dir_all(&project);
+
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[1].saved_at, old_mtime);
+        assert!(versions[0].saved_at > old_mtime);
+    }
+}
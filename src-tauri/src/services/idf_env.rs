@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::models::toolchain::Toolchain;
+use crate::services::process_runner::{self, RunOptions};
+use crate::services::toolchain;
+
+const MARKER: &str = "__VEDITOR_IDF_ENV__";
+
+/// Variables the shell sets for itself; they must not leak into children.
+const SHELL_NOISE: [&str; 5] = ["_", "SHLVL", "PWD", "OLDPWD", "PS1"];
+
+/// What `export.sh` changed in the environment for one toolchain, stored in
+/// `~/.esp-projects/idf-env/<hash of idf_path>.json`.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedEnv {
+    idf_path: String,
+    key: String,
+    vars: BTreeMap<String, String>,
+}
+
+/// The variables `export.sh` sets for `toolchain`, ready to pass to
+/// `RunOptions::envs`. Sourcing export.sh takes seconds, so the result is
+/// captured once and reused until the IDF checkout changes.
+pub fn environment(toolchain: &Toolchain) -> Result<Vec<(String, String)>, String> {
+    let key = cache_key(toolchain);
+    let file = cache_file(toolchain)?;
+
+    if let Some(cached) = read_cache(&file) {
+        if cached.idf_path == toolchain.idf_path && cached.key == key {
+            return Ok(cached.vars.into_iter().collect());
+        }
+    }
+
+    let vars = capture(toolchain)?;
+    let cached = CachedEnv {
+        idf_path: toolchain.idf_path.clone(),
+        key,
+        vars,
+    };
+    if let Ok(data) = serde_json::to_string_pretty(&cached) {
+        let _ = fs::write(&file, data);
+    }
+
+    Ok(cached.vars.into_iter().collect())
+}
+
+/// Drops the cached environment so the next build sources export.sh again,
+/// e.g. after `install.sh` added tools without changing the IDF revision.
+pub fn invalidate(toolchain: &Toolchain) -> Result<(), String> {
+    let file = cache_file(toolchain)?;
+    if file.exists() {
+        fs::remove_file(file).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Sources export.sh in a clean bash and keeps every variable that ended up
+/// different from our own environment.
+fn capture(toolchain: &Toolchain) -> Result<BTreeMap<String, String>, String> {
+    let script = format!(
+        "source \"{}\" >/dev/null 2>&1 || exit 1\necho {}\nenv -0",
+        toolchain::export_script(toolchain).display(),
+        MARKER
+    );
+
+    let opts = RunOptions::new("bash")
+        .arg("-c")
+        .arg(script)
+        .env("IDF_PATH", &toolchain.idf_path)
+        .timeout(Duration::from_secs(300));
+
+    let (exit, output) = process_runner::run_collect(opts)?;
+    if !exit.success {
+        return Err(format!("Sourcing {}/export.sh failed", toolchain.idf_path));
+    }
+
+    let dump = output
+        .split_once(&format!("{}\n", MARKER))
+        .map(|(_, env)| env)
+        .ok_or("export.sh produced no environment")?;
+
+    Ok(dump
+        .split('\0')
+        .filter_map(|entry| entry.split_once('='))
+        .filter(|(k, _)| !SHELL_NOISE.contains(k))
+        .filter(|(k, v)| std::env::var(k).ok().as_deref() != Some(*v))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect())
+}
+
+/// Changes whenever the checkout moves to another commit, export.sh is
+/// edited or the python env is swapped.
+fn cache_key(toolchain: &Toolchain) -> String {
+    let idf = Path::new(&toolchain.idf_path);
+    let export_mtime = fs::metadata(toolchain::export_script(toolchain))
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    format!(
+        "{}|{}|{}",
+        git_revision(idf).unwrap_or_default(),
+        toolchain.python.as_deref().unwrap_or(""),
+        export_mtime
+    )
+}
+
+/// HEAD's commit id, read straight from `.git` to avoid spawning git.
+fn git_revision(repo: &Path) -> Option<String> {
+    let git_dir = repo.join(".git");
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+
+    let reference = match head.strip_prefix("ref: ") {
+        Some(r) => r,
+        None => return Some(head.to_string()),
+    };
+
+    if let Ok(id) = fs::read_to_string(git_dir.join(reference)) {
+        return Some(id.trim().to_string());
+    }
+
+    fs::read_to_string(git_dir.join("packed-refs"))
+        .ok()?
+        .lines()
+        .find_map(|l| {
+            let (id, name) = l.split_once(' ')?;
+            (name == reference).then(|| id.to_string())
+        })
+}
+
+fn cache_file(toolchain: &Toolchain) -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Failed to find home directory")?;
+    let dir = home.join(".esp-projects").join("idf-env");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let mut hasher = DefaultHasher::new();
+    toolchain.idf_path.hash(&mut hasher);
+    Ok(dir.join(format!("{:016x}.json", hasher.finish())))
+}
+
+fn read_cache(file: &Path) -> Option<CachedEnv> {
+    let data = fs::read_to_string(file).ok()?;
+    serde_json::from_str(&data).ok()
+}
@@ -0,0 +1,618 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::sketch::PreprocessedSketch;
+
+/// Words that can't start a function definition at file scope.
+const NOT_FUNCTIONS: [&str; 8] = ["struct", "class", "enum", "union", "namespace", "typedef", "using", "extern"];
+const CONTROL_KEYWORDS: [&str; 6] = ["if", "while", "for", "switch", "return", "sizeof"];
+const QUALIFIERS: [&str; 5] = ["const", "noexcept", "override", "final", "volatile"];
+
+/// Turns the `.ino` tabs of the sketch in `sketch_dir` into one C++ file,
+/// like the Arduino builder does.
+pub fn preprocess(sketch_dir: &Path) -> Result<PreprocessedSketch, String> {
+    let tabs = read_tabs(sketch_dir)?;
+    Ok(preprocess_tabs(&tabs))
+}
+
+/// Lays the sketch out for arduino-cli in `out_dir/<name>/`: the merged C++
+/// as `<name>.merged.cpp` behind an empty main tab, plus the sketch's other
+/// files and `src/`. arduino-cli then only has our output to preprocess.
+/// Files are rewritten only when they changed, so incremental builds still
+/// skip what's up to date.
+pub fn write_build_sketch(sketch_dir: &Path, out_dir: &Path) -> Result<PathBuf, String> {
+    let tabs = read_tabs(sketch_dir)?;
+    let name = sketch_dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let merged = preprocess_tabs(&tabs);
+
+    let mut files = vec![
+        (PathBuf::from(format!("{}.ino", name)), b"// the tabs are merged into the .merged.cpp next to this file\n".to_vec()),
+        (PathBuf::from(format!("{}.merged.cpp", name)), merged.source.into_bytes()),
+    ];
+    collect_sources(sketch_dir, Path::new(""), &mut files)?;
+
+    let dest = out_dir.join(name);
+    for (rel, data) in &files {
+        let path = dest.join(rel);
+        if fs::read(&path).ok().as_deref() == Some(data.as_slice()) {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(&path, data).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+
+    let keep: HashSet<&Path> = files.iter().map(|(rel, _)| rel.as_path()).collect();
+    remove_stale(&dest, Path::new(""), &keep);
+    Ok(dest)
+}
+
+/// Everything arduino-cli compiles or reads besides the tabs: top-level
+/// files (headers, `.cpp`, `sketch.yaml`) and the `src/` tree.
+fn collect_sources(dir: &Path, rel: &Path, files: &mut Vec<(PathBuf, Vec<u8>)>) -> Result<(), String> {
+    let top = rel.as_os_str().is_empty();
+
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())?.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            if !top || name == "src" {
+                collect_sources(&path, &rel.join(&name), files)?;
+            }
+        } else if !(top && matches!(path.extension().and_then(|e| e.to_str()), Some("ino") | Some("pde"))) {
+            let data = fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            files.push((rel.join(&name), data));
+        }
+    }
+    Ok(())
+}
+
+/// Deletes files under `dir` that the sketch no longer has.
+fn remove_stale(dir: &Path, rel: &Path, keep: &HashSet<&Path>) {
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let rel = rel.join(entry.file_name());
+        if path.is_dir() {
+            remove_stale(&path, &rel, keep);
+        } else if !keep.contains(rel.as_path()) {
+            let _ = fs::remove_file(&path);
+        }
+    }
+}
+
+/// The main tab (`<folder>.ino`) first, then the other `.ino`/`.pde` tabs by name.
+fn read_tabs(sketch_dir: &Path) -> Result<Vec<(PathBuf, String)>, String> {
+    let name = sketch_dir
+        .file_name()
+        .ok_or("Invalid sketch folder")?
+        .to_string_lossy()
+        .to_string();
+    let main = sketch_dir.join(format!("{}.ino", name));
+    if !main.is_file() {
+        return Err(format!("{}.ino not found in {}", name, sketch_dir.display()));
+    }
+
+    let mut others: Vec<PathBuf> = fs::read_dir(sketch_dir)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p != &main && p.is_file())
+        .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("ino") | Some("pde")))
+        .collect();
+    others.sort_by_key(|p| p.file_name().map(|n| n.to_string_lossy().to_lowercase()));
+
+    std::iter::once(main)
+        .chain(others)
+        .map(|path| {
+            let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            Ok((path, text.trim_start_matches('\u{feff}').to_string()))
+        })
+        .collect()
+}
+
+/// Merges `tabs` in order behind `#include <Arduino.h>`, adds prototypes for
+/// functions that aren't declared yet right before the first function
+/// definition, and keeps `#line` directives pointing at the original tab and
+/// line for every line, prototypes included.
+pub fn preprocess_tabs(tabs: &[(PathBuf, String)]) -> PreprocessedSketch {
+    let scans: Vec<Scan> = tabs.iter().map(|(_, text)| scan(text)).collect();
+
+    let declared: HashSet<&str> = scans
+        .iter()
+        .flat_map(|s| s.declarations.iter().map(String::as_str))
+        .collect();
+
+    // (tab, line, signature)
+    let mut prototypes: Vec<(usize, usize, String)> = Vec::new();
+    for (tab, s) in scans.iter().enumerate() {
+        for def in &s.definitions {
+            // a prototype repeating default arguments would clash with the definition
+            if def.has_default_args
+                || declared.contains(def.name.as_str())
+                || prototypes.iter().any(|(_, _, sig)| sig == &def.signature)
+            {
+                continue;
+            }
+            prototypes.push((tab, def.line, def.signature.clone()));
+        }
+    }
+
+    let insert_at = scans
+        .iter()
+        .enumerate()
+        .find_map(|(tab, s)| s.definitions.first().map(|d| (tab, d.line)));
+
+    let mut source = String::from("#include <Arduino.h>\n");
+    for (tab, (path, text)) in tabs.iter().enumerate() {
+        source.push_str(&line_directive(1, path));
+
+        for (i, line) in text.lines().enumerate() {
+            if insert_at == Some((tab, i + 1)) {
+                for (proto_tab, proto_line, signature) in &prototypes {
+                    source.push_str(&line_directive(*proto_line, &tabs[*proto_tab].0));
+                    source.push_str(signature);
+                    source.push_str(";\n");
+                }
+                source.push_str(&line_directive(i + 1, path));
+            }
+            source.push_str(line);
+            source.push('\n');
+        }
+    }
+
+    PreprocessedSketch {
+        source,
+        prototypes: prototypes.into_iter().map(|(_, _, sig)| sig + ";").collect(),
+        tabs: tabs.iter().map(|(p, _)| p.to_string_lossy().to_string()).collect(),
+    }
+}
+
+fn line_directive(line: usize, path: &Path) -> String {
+    let file = path.to_string_lossy().replace('\\', "\\\\").replace('"', "\\\"");
+    format!("#line {} \"{}\"\n", line, file)
+}
+
+struct FunctionDef {
+    name: String,
+    /// Whitespace-normalised signature, without the body.
+    signature: String,
+    /// 1-based line the signature starts on.
+    line: usize,
+    has_default_args: bool,
+}
+
+/// File-scope functions of one tab.
+struct Scan {
+    definitions: Vec<FunctionDef>,
+    /// Names of functions declared with a prototype.
+    declarations: Vec<String>,
+}
+
+fn scan(text: &str) -> Scan {
+    let (code, mask) = clean(text);
+    let mut definitions = Vec::new();
+    let mut declarations = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+
+    for (i, &c) in mask.iter().enumerate() {
+        match c {
+            '{' => {
+                if depth == 0 {
+                    if let Some(mut def) = parse_signature(&code, &mask, start, i) {
+                        def.line = line_of(&mask, start);
+                        definitions.push(def);
+                    }
+                }
+                depth += 1;
+            }
+            '}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    start = i + 1;
+                }
+            }
+            ';' if depth == 0 => {
+                if let Some(decl) = parse_signature(&code, &mask, start, i) {
+                    declarations.push(decl.name);
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    Scan { definitions, declarations }
+}
+
+/// Recognises `<return type> name(<params>) [qualifiers]` in
+/// `code[from..to]`. `mask` is the same text with string and char literals
+/// blanked, so their contents can't confuse the structure checks.
+fn parse_signature(code: &[char], mask: &[char], from: usize, to: usize) -> Option<FunctionDef> {
+    let text: String = mask[from..to].iter().collect();
+    let mut text = text.trim();
+
+    // trailing qualifiers: `int pin() const`
+    while let Some(q) = QUALIFIERS.iter().find(|q| {
+        text.strip_suffix(**q)
+            .is_some_and(|rest| !rest.ends_with(|c: char| c.is_alphanumeric() || c == '_'))
+    }) {
+        text = text[..text.len() - q.len()].trim_end();
+    }
+    if !text.ends_with(')') || text.contains("operator") {
+        return None;
+    }
+
+    let first_word = text.split(|c: char| !(c.is_alphanumeric() || c == '_')).next().unwrap_or("");
+    if NOT_FUNCTIONS.contains(&first_word) {
+        return None;
+    }
+
+    // the first top-level '(' opens the parameter list; '=' outside of it
+    // means an initializer, not a function
+    let mut open = None;
+    let mut paren = 0;
+    let mut has_default_args = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => {
+                if paren == 0 && open.is_none() {
+                    open = Some(i);
+                }
+                paren += 1;
+            }
+            ')' => paren -= 1,
+            '=' if paren == 0 => return None,
+            '=' => has_default_args = true,
+            _ => {}
+        }
+    }
+
+    let open = open?;
+    let before = text[..open].trim_end();
+    let name_start = before
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let name = &before[name_start..];
+    let return_type = before[..name_start].trim();
+
+    if name.is_empty()
+        || name.contains("::")
+        || name.starts_with(|c: char| c.is_ascii_digit())
+        || CONTROL_KEYWORDS.contains(&name)
+        || return_type.is_empty()
+    {
+        return None;
+    }
+
+    let signature = code[from..to]
+        .iter()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Some(FunctionDef {
+        name: name.to_string(),
+        signature,
+        line: 0,
+        has_default_args,
+    })
+}
+
+fn line_of(mask: &[char], from: usize) -> usize {
+    let first = mask[from..]
+        .iter()
+        .position(|c| !c.is_whitespace())
+        .map(|p| from + p)
+        .unwrap_or(from);
+    mask[..first].iter().filter(|&&c| c == '\n').count() + 1
+}
+
+/// Blanks comments and preprocessor lines. Returns the text with those
+/// removed, and the same with string/char literal contents blanked too.
+/// Newlines are kept so positions map to the same lines.
+fn clean(text: &str) -> (Vec<char>, Vec<char>) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut code = chars.clone();
+    let mut mask = chars.clone();
+    let blank = |v: &mut Vec<char>, i: usize| {
+        if v[i] != '\n' {
+            v[i] = ' ';
+        }
+    };
+
+    let mut i = 0;
+    let mut line_start = true;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if line_start && c == '#' {
+            // directive, including `\` continuation lines
+            while i < chars.len() {
+                if chars[i] == '\n' && !continues(&chars[..i]) {
+                    break;
+                }
+                blank(&mut code, i);
+                blank(&mut mask, i);
+                i += 1;
+            }
+            continue;
+        }
+
+        match (c, next) {
+            ('/', Some('/')) => {
+                while i < chars.len() && chars[i] != '\n' {
+                    blank(&mut code, i);
+                    blank(&mut mask, i);
+                    i += 1;
+                }
+                continue;
+            }
+            ('/', Some('*')) => {
+                let end = (i + 2..chars.len().saturating_sub(1))
+                    .find(|&j| chars[j] == '*' && chars[j + 1] == '/')
+                    .map(|j| j + 2)
+                    .unwrap_or(chars.len());
+                for j in i..end {
+                    blank(&mut code, j);
+                    blank(&mut mask, j);
+                }
+                i = end;
+                continue;
+            }
+            ('"', _) | ('\'', _) => {
+                if c == '\'' && is_digit_separator(&chars, i) {
+                    i += 1;
+                    continue;
+                }
+                let end = literal_end(&chars, i);
+                for j in i + 1..end.saturating_sub(1) {
+                    blank(&mut mask, j);
+                }
+                line_start = false;
+                i = end;
+                continue;
+            }
+            _ => {}
+        }
+
+        if c == '\n' {
+            line_start = true;
+        } else if !c.is_whitespace() {
+            line_start = false;
+        }
+        i += 1;
+    }
+
+    (code, mask)
+}
+
+/// Whether the `'` at `i` is inside a number (`1'000'000`, `0xFF'FF`) rather
+/// than opening a char literal, which may have a prefix (`L'{'`, `u8'{'`).
+fn is_digit_separator(chars: &[char], i: usize) -> bool {
+    let token_start = chars[..i]
+        .iter()
+        .rposition(|c| !(c.is_ascii_alphanumeric() || *c == '_' || *c == '.' || *c == '\''))
+        .map_or(0, |p| p + 1);
+    token_start < i && chars[token_start].is_ascii_digit()
+}
+
+/// Whether the line ending at `before` ends with a `\` continuation.
+fn continues(before: &[char]) -> bool {
+    let before = before.strip_suffix(&['\r']).unwrap_or(before);
+    before.last() == Some(&'\\')
+}
+
+/// Index just past the literal starting at `start` (a quote), handling
+/// escapes and raw strings (`R"delim(...)delim"`).
+fn literal_end(chars: &[char], start: usize) -> usize {
+    let quote = chars[start];
+
+    let raw = quote == '"' && start > 0 && chars[start - 1] == 'R';
+    if raw {
+        let open = match chars[start + 1..].iter().position(|&c| c == '(') {
+            Some(p) => start + 1 + p,
+            None => return chars.len(),
+        };
+        let delim: String = chars[start + 1..open].iter().collect();
+        let closing: Vec<char> = format!("){}\"", delim).chars().collect();
+        return (open..chars.len())
+            .find(|&j| chars[j..].starts_with(&closing))
+            .map(|j| j + closing.len())
+            .unwrap_or(chars.len());
+    }
+
+    let mut j = start + 1;
+    while j < chars.len() {
+        match chars[j] {
+            '\\' => j += 2,
+            c if c == quote => return j + 1,
+            '\n' => return j,
+            _ => j += 1,
+        }
+    }
+    chars.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/sketches")
+            .join(name)
+    }
+
+    /// Follows the `#line` directives through the output and checks that
+    /// every line that isn't a generated prototype is the original line it
+    /// claims to be, and that each prototype points at its definition.
+    fn assert_lines_map_back(sketch: &PreprocessedSketch) {
+        let mut lines = sketch.source.lines();
+        assert_eq!(lines.next(), Some("#include <Arduino.h>"));
+
+        let mut current: Option<(String, usize)> = None;
+        for line in lines {
+            if let Some(rest) = line.strip_prefix("#line ") {
+                let (number, file) = rest.split_once(' ').unwrap();
+                current = Some((file.trim_matches('"').to_string(), number.parse().unwrap()));
+                continue;
+            }
+
+            let (file, number) = current.as_mut().expect("code before the first #line");
+            let original = fs::read_to_string(&*file).unwrap();
+            let original_line = original.lines().nth(*number - 1).unwrap_or("");
+
+            if sketch.prototypes.iter().any(|p| p == line) {
+                let name = line.split('(').next().unwrap().split_whitespace().last().unwrap();
+                assert!(
+                    original_line.contains(name),
+                    "prototype {} points at {}:{} ({})",
+                    line,
+                    file,
+                    number,
+                    original_line
+                );
+            } else {
+                assert_eq!(line, original_line, "{}:{}", file, number);
+            }
+            *number += 1;
+        }
+    }
+
+    #[test]
+    fn blink_gets_setup_and_loop_prototypes() {
+        let sketch = preprocess(&fixture("Blink")).unwrap();
+
+        assert_eq!(sketch.prototypes, vec!["void setup();", "void loop();"]);
+        assert_lines_map_back(&sketch);
+
+        // prototypes go right before the first definition, after the header comment
+        let setup_def = sketch.source.find("void setup() {").unwrap();
+        let setup_proto = sketch.source.find("void setup();").unwrap();
+        assert!(setup_proto < setup_def);
+        assert!(sketch.source.find("This example code is in the public domain").unwrap() < setup_proto);
+    }
+
+    #[test]
+    fn tabs_are_merged_main_tab_first() {
+        let sketch = preprocess(&fixture("WeatherStation")).unwrap();
+
+        let names: Vec<String> = sketch
+            .tabs
+            .iter()
+            .map(|t| Path::new(t).file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["WeatherStation.ino", "network.ino", "sensor.ino"]);
+        assert!(!sketch.source.contains("#define WIFI_SSID"), "headers are not tabs");
+
+        assert_eq!(
+            sketch.prototypes,
+            vec![
+                "void setup();",
+                "void loop();",
+                "void printReading(const Reading &r);",
+                "void connectWifi(const char *name);",
+                "bool publish(const Reading &r);",
+                "static bool post(const char *path, const String &body);",
+                "void initSensor();",
+                "Reading readSensor();",
+            ]
+        );
+        assert_lines_map_back(&sketch);
+    }
+
+    #[test]
+    fn prototypes_follow_the_types_they_use() {
+        let sketch = preprocess(&fixture("WeatherStation")).unwrap();
+
+        let struct_end = sketch.source.find("Reading lastReading").unwrap();
+        let first_proto = sketch.source.find("void setup();").unwrap();
+        assert!(struct_end < first_proto);
+    }
+
+    #[test]
+    fn skips_declared_members_defaults_and_non_functions() {
+        let sketch = preprocess(&fixture("Tricky")).unwrap();
+
+        assert_eq!(
+            sketch.prototypes,
+            vec![
+                "void IRAM_ATTR onPulse();",
+                "void setup();",
+                "void loop();",
+                "int clampTo(int value, int limit);",
+                "template <typename T> T largest(T a, T b);",
+                "static inline unsigned long scaled(unsigned long ms);",
+                "void espOnly();",
+            ]
+        );
+        assert_lines_map_back(&sketch);
+
+        // the string that looks like a function stays untouched
+        assert!(sketch.source.contains("const char *banner = \"void notAFunction() {\";"));
+    }
+
+    #[test]
+    fn prefixed_char_literals_are_not_digit_separators() {
+        let sketch = preprocess(&fixture("Literals")).unwrap();
+
+        assert_eq!(sketch.prototypes, vec!["void setup();", "void loop();", "int sum(int a, int b);"]);
+        assert_lines_map_back(&sketch);
+    }
+
+    #[test]
+    fn prototype_line_directives_point_at_definitions() {
+        let sketch = preprocess(&fixture("WeatherStation")).unwrap();
+        let network = fixture("WeatherStation").join("network.ino");
+
+        let expected = format!("#line 10 \"{}\"\nbool publish(const Reading &r);", network.display());
+        assert!(sketch.source.contains(&expected), "{}", sketch.source);
+    }
+
+    #[test]
+    fn build_sketch_has_merged_source_and_other_files() {
+        let out = std::env::temp_dir().join(format!("sketch-{}", uuid::Uuid::new_v4()));
+        let dest = write_build_sketch(&fixture("WeatherStation"), &out).unwrap();
+        let read = |name: &str| fs::read_to_string(dest.join(name)).unwrap();
+
+        assert_eq!(dest, out.join("WeatherStation"));
+        assert_eq!(read("WeatherStation.merged.cpp"), preprocess(&fixture("WeatherStation")).unwrap().source);
+        assert!(read("WeatherStation.ino").starts_with("//"));
+        assert!(read("config.h").contains("#define WIFI_SSID"));
+        assert!(!dest.join("network.ino").exists());
+
+        fs::write(dest.join("removed.h"), "").unwrap();
+        write_build_sketch(&fixture("WeatherStation"), &out).unwrap();
+        assert!(!dest.join("removed.h").exists());
+
+        fs::remove_dir_all(&out).unwrap();
+    }
+
+    #[test]
+    fn sketch_without_main_tab_is_rejected() {
+        let err = preprocess(&fixture("Blink").join("missing")).unwrap_err();
+        assert!(err.contains("missing.ino"));
+    }
+
+    #[test]
+    fn crlf_and_bom_are_handled() {
+        let tabs = vec![(
+            PathBuf::from("/sketch/Crlf.ino"),
+            "int x = 1;\r\nvoid setup() {\r\n}\r\nvoid loop() {\r\n  helper();\r\n}\r\nvoid helper() {}\r\n".to_string(),
+        )];
+        let sketch = preprocess_tabs(&tabs);
+
+        assert_eq!(sketch.prototypes, vec!["void setup();", "void loop();", "void helper();"]);
+        assert!(!sketch.source.contains('\r'));
+        assert!(sketch.source.contains("#line 7 \"/sketch/Crlf.ino\"\nvoid helper();"));
+    }
+}
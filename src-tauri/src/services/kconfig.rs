@@ -0,0 +1,1566 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::kconfig::{KconfigNode, KconfigNodeKind, KconfigSymbolState, KconfigType};
+use crate::models::toolchain::Toolchain;
+use crate::services::{project_settings, sdkconfig};
+
+/// Parsed Kconfig tree of one project, with the values from its sdkconfig
+/// applied. Evaluation follows kconfig semantics closely enough for the
+/// ESP-IDF tree: tristate logic, `depends on` propagated into prompts and
+/// defaults, `select`/`imply` reverse dependencies and choices.
+pub struct KconfigTree {
+    symbols: HashMap<String, Symbol>,
+    order: Vec<String>,
+    choices: Vec<Choice>,
+    nodes: Vec<Node>,
+    values: HashMap<String, String>,
+    idf_version: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+struct Symbol {
+    ty: KconfigType,
+    prompts: Vec<(String, Expr)>,
+    defaults: Vec<(Expr, Expr)>,
+    /// `default` lines as written, for display
+    default_text: Vec<String>,
+    selects: Vec<(String, Expr)>,
+    implies: Vec<(String, Expr)>,
+    ranges: Vec<(Expr, Expr, Expr)>,
+    /// one entry per definition, the symbol is "enabled" if any holds
+    dir_dep: Vec<Expr>,
+    rev_dep: Vec<(String, Expr)>,
+    weak_rev_dep: Vec<(String, Expr)>,
+    choice: Option<usize>,
+    user: Option<String>,
+}
+
+struct Choice {
+    name: Option<String>,
+    prompts: Vec<Expr>,
+    defaults: Vec<(String, Expr)>,
+    dir_dep: Expr,
+    members: Vec<String>,
+    user: Option<String>,
+    selection: Option<String>,
+}
+
+enum Item {
+    Menu,
+    Symbol(String),
+    Choice(usize),
+    Comment,
+}
+
+struct Node {
+    item: Item,
+    title: Option<String>,
+    dep: Expr,
+    visible_if: Expr,
+    help: Option<String>,
+    children: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Sym(String),
+    Str(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Cmp(&'static str, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn yes() -> Expr {
+        Expr::Sym("y".into())
+    }
+
+    fn is_yes(&self) -> bool {
+        matches!(self, Expr::Sym(s) if s == "y")
+    }
+
+    /// `a && b`, dropping trivially true operands.
+    fn and(a: Expr, b: Expr) -> Expr {
+        if a.is_yes() {
+            b
+        } else if b.is_yes() {
+            a
+        } else {
+            Expr::And(Box::new(a), Box::new(b))
+        }
+    }
+
+    fn all(exprs: impl IntoIterator<Item = Expr>) -> Expr {
+        exprs.into_iter().fold(Expr::yes(), Expr::and)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Sym(s) => write!(f, "{}", s),
+            Expr::Str(s) => write!(f, "\"{}\"", s),
+            Expr::Not(e) => match **e {
+                Expr::Sym(_) | Expr::Str(_) => write!(f, "!{}", e),
+                _ => write!(f, "!({})", e),
+            },
+            Expr::And(a, b) => {
+                for (i, e) in [a, b].into_iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " && ")?;
+                    }
+                    match **e {
+                        Expr::Or(..) => write!(f, "({})", e)?,
+                        _ => write!(f, "{}", e)?,
+                    }
+                }
+                Ok(())
+            }
+            Expr::Or(a, b) => write!(f, "{} || {}", a, b),
+            Expr::Cmp(op, a, b) => write!(f, "{} {} {}", a, op, b),
+        }
+    }
+}
+
+impl KconfigTree {
+    /// Parses `$IDF_PATH/Kconfig` of `toolchain` together with the project's
+    /// component Kconfig files and applies `sdkconfig` (or the defaults files
+    /// when the project hasn't been configured yet).
+    pub fn load(project: &Path, toolchain: &Toolchain) -> Result<KconfigTree, String> {
+        let idf_path = Path::new(&toolchain.idf_path);
+        let sdkconfig_path = project.join("sdkconfig");
+        let target = sdkconfig::read_value(&sdkconfig_path, "IDF_TARGET")
+            .unwrap_or_else(|| "esp32".to_string());
+
+        let (kconfigs, projbuild) = component_sources(project, idf_path)?;
+
+        let mut env = HashMap::new();
+        env.insert("IDF_PATH".to_string(), toolchain.idf_path.clone());
+        env.insert("IDF_TARGET".to_string(), target.clone());
+        env.insert("IDF_INIT_VERSION".to_string(), toolchain.version.clone().unwrap_or_default());
+        env.insert("IDF_ENV_FPGA".to_string(), String::new());
+        env.insert("IDF_CI_BUILD".to_string(), String::new());
+        env.insert("COMPONENT_KCONFIGS_SOURCE_FILE".to_string(), kconfigs.to_string_lossy().to_string());
+        env.insert("COMPONENT_KCONFIGS_PROJBUILD_SOURCE_FILE".to_string(), projbuild.to_string_lossy().to_string());
+
+        let mut parser = Parser::new(env, idf_path.to_path_buf());
+        parser.parse_file(&idf_path.join("Kconfig"), false)?;
+        let mut tree = parser.finish();
+        tree.idf_version = toolchain.version.clone();
+
+        let user_values = if sdkconfig_path.exists() {
+            sdkconfig::read_all(&sdkconfig_path)?
+        } else {
+            let mut values = Vec::new();
+            for file in ["sdkconfig.defaults".to_string(), format!("sdkconfig.defaults.{}", target)] {
+                if let Ok(v) = sdkconfig::read_all(&project.join(file)) {
+                    values.extend(v);
+                }
+            }
+            values
+        };
+        tree.apply_user_values(user_values);
+        tree.evaluate();
+        Ok(tree)
+    }
+
+    fn apply_user_values(&mut self, values: Vec<(String, String)>) {
+        for (name, value) in values {
+            let sym = match self.symbols.get_mut(&name) {
+                Some(s) => s,
+                None => continue,
+            };
+            match sym.choice {
+                Some(ci) => {
+                    if value == "y" {
+                        self.choices[ci].user = Some(name);
+                    }
+                }
+                None => sym.user = Some(value),
+            }
+        }
+    }
+
+    // ---- evaluation ----
+
+    fn evaluate(&mut self) {
+        // values only feed forward through dependencies, so a few passes settle
+        for _ in 0..32 {
+            let mut changed = false;
+
+            for ci in 0..self.choices.len() {
+                let selection = self.choice_selection(ci);
+                if selection != self.choices[ci].selection {
+                    self.choices[ci].selection = selection;
+                    changed = true;
+                }
+            }
+
+            for i in 0..self.order.len() {
+                let value = self.compute(&self.order[i]);
+                if self.values.get(&self.order[i]) != Some(&value) {
+                    self.values.insert(self.order[i].clone(), value);
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    fn compute(&self, name: &str) -> String {
+        let sym = &self.symbols[name];
+        let vis = self.visibility(name);
+
+        match sym.ty {
+            KconfigType::Bool | KconfigType::Tristate => {
+                if let Some(ci) = sym.choice {
+                    let choice = &self.choices[ci];
+                    let on = self.tri(&choice.dir_dep) > 0 && choice.selection.as_deref() == Some(name);
+                    return tri_name(if on { 2 } else { 0 }).to_string();
+                }
+
+                let user = sym.user.as_deref().filter(|_| vis > 0).and_then(parse_tri);
+                let mut val = match user {
+                    Some(u) => u.min(vis),
+                    None => {
+                        let default = sym
+                            .defaults
+                            .iter()
+                            .find(|(_, cond)| self.tri(cond) > 0)
+                            .map(|(v, cond)| self.tri(v).min(self.tri(cond)))
+                            .unwrap_or(0);
+                        let weak = sym
+                            .weak_rev_dep
+                            .iter()
+                            .map(|(s, cond)| self.sym_tri(s).min(self.tri(cond)))
+                            .max()
+                            .unwrap_or(0)
+                            .min(self.dir_dep(sym));
+                        default.max(weak)
+                    }
+                };
+
+                let rev = sym
+                    .rev_dep
+                    .iter()
+                    .map(|(s, cond)| self.sym_tri(s).min(self.tri(cond)))
+                    .max()
+                    .unwrap_or(0);
+                val = val.max(rev);
+
+                if sym.ty == KconfigType::Bool && val == 1 {
+                    val = 2;
+                }
+                tri_name(val).to_string()
+            }
+            KconfigType::Int | KconfigType::Hex | KconfigType::String => {
+                let user = sym.user.clone().filter(|u| vis > 0 && self.check_value(sym, u).is_ok());
+                let value = user.unwrap_or_else(|| {
+                    sym.defaults
+                        .iter()
+                        .find(|(_, cond)| self.tri(cond) > 0)
+                        .map(|(v, _)| self.str_val(v))
+                        .unwrap_or_default()
+                });
+
+                match (sym.ty, self.active_range(sym)) {
+                    (KconfigType::String, _) | (_, None) => value,
+                    (_, Some((low, high))) => {
+                        let hex = sym.ty == KconfigType::Hex;
+                        match parse_num(&value) {
+                            Some(v) if v < low => format_num(low, hex),
+                            Some(v) if v > high => format_num(high, hex),
+                            Some(_) => value,
+                            None => format_num(low, hex),
+                        }
+                    }
+                }
+            }
+            KconfigType::Unknown => String::new(),
+        }
+    }
+
+    fn choice_selection(&self, ci: usize) -> Option<String> {
+        let choice = &self.choices[ci];
+        if self.tri(&choice.dir_dep) == 0 {
+            return None;
+        }
+
+        let visible: Vec<&String> = choice
+            .members
+            .iter()
+            .filter(|m| self.visibility(m) > 0)
+            .collect();
+
+        if let Some(user) = choice.user.as_ref().filter(|u| visible.contains(u)) {
+            return Some(user.clone());
+        }
+        choice
+            .defaults
+            .iter()
+            .find(|(d, cond)| self.tri(cond) > 0 && visible.contains(&d))
+            .map(|(d, _)| d.clone())
+            .or_else(|| visible.first().map(|m| m.to_string()))
+    }
+
+    fn choice_visibility(&self, ci: usize) -> u8 {
+        self.choices[ci].prompts.iter().map(|p| self.tri(p)).max().unwrap_or(0)
+    }
+
+    fn visibility(&self, name: &str) -> u8 {
+        let sym = &self.symbols[name];
+        let mut vis = sym.prompts.iter().map(|(_, c)| self.tri(c)).max().unwrap_or(0);
+
+        if let Some(ci) = sym.choice {
+            vis = vis.min(self.choice_visibility(ci));
+        }
+        if sym.ty == KconfigType::Bool && vis == 1 {
+            vis = 2;
+        }
+        vis
+    }
+
+    fn dir_dep(&self, sym: &Symbol) -> u8 {
+        sym.dir_dep.iter().map(|d| self.tri(d)).max().unwrap_or(0)
+    }
+
+    fn active_range(&self, sym: &Symbol) -> Option<(i64, i64)> {
+        sym.ranges
+            .iter()
+            .find(|(_, _, cond)| self.tri(cond) > 0)
+            .and_then(|(low, high, _)| Some((parse_num(&self.str_val(low))?, parse_num(&self.str_val(high))?)))
+    }
+
+    fn tri(&self, expr: &Expr) -> u8 {
+        match expr {
+            Expr::Sym(s) => self.sym_tri(s),
+            Expr::Str(s) => parse_tri(s).unwrap_or(0),
+            Expr::Not(e) => 2 - self.tri(e),
+            Expr::And(a, b) => self.tri(a).min(self.tri(b)),
+            Expr::Or(a, b) => self.tri(a).max(self.tri(b)),
+            Expr::Cmp(op, a, b) => {
+                let (a, b) = (self.str_val(a), self.str_val(b));
+                let ord = match (parse_num(&a), parse_num(&b)) {
+                    (Some(x), Some(y)) => x.cmp(&y),
+                    _ => a.cmp(&b),
+                };
+                let holds = match *op {
+                    "=" => ord.is_eq(),
+                    "!=" => ord.is_ne(),
+                    "<" => ord.is_lt(),
+                    "<=" => ord.is_le(),
+                    ">" => ord.is_gt(),
+                    _ => ord.is_ge(),
+                };
+                if holds { 2 } else { 0 }
+            }
+        }
+    }
+
+    fn sym_tri(&self, name: &str) -> u8 {
+        match self.symbols.get(name) {
+            Some(s) if matches!(s.ty, KconfigType::Bool | KconfigType::Tristate) => {
+                self.values.get(name).and_then(|v| parse_tri(v)).unwrap_or(0)
+            }
+            Some(_) => 0,
+            None => parse_tri(name).unwrap_or(0),
+        }
+    }
+
+    /// Value of an operand in a comparison or non-bool default; undefined
+    /// names are constants (`default 4096`, `depends on IDF_TARGET = esp32`).
+    fn str_val(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Sym(s) => match self.symbols.get(s) {
+                Some(_) => self.values.get(s).cloned().unwrap_or_default(),
+                None => s.clone(),
+            },
+            Expr::Str(s) => s.clone(),
+            e => tri_name(self.tri(e)).to_string(),
+        }
+    }
+
+    fn check_value(&self, sym: &Symbol, value: &str) -> Result<(), String> {
+        let ok = match sym.ty {
+            KconfigType::Bool => matches!(value, "y" | "n"),
+            KconfigType::Tristate => parse_tri(value).is_some(),
+            KconfigType::Int => value.parse::<i64>().is_ok(),
+            KconfigType::Hex => value.starts_with("0x") && parse_num(value).is_some(),
+            KconfigType::String => true,
+            KconfigType::Unknown => false,
+        };
+        if !ok {
+            return Err(format!("'{}' is not a valid {:?} value", value, sym.ty));
+        }
+
+        if let (Some((low, high)), Some(v)) = (self.active_range(sym), parse_num(value)) {
+            if v < low || v > high {
+                return Err(format!("{} is outside the range {}..{}", value, low, high));
+            }
+        }
+        Ok(())
+    }
+
+    // ---- changes ----
+
+    /// Sets a user value, `None` reverts the symbol to its default. For a
+    /// choice member only `y` is accepted; it selects that member. Returns
+    /// every symbol whose value or visibility changed as a result.
+    pub fn set_value(&mut self, name: &str, value: Option<String>) -> Result<Vec<KconfigSymbolState>, String> {
+        let sym = self.symbols.get(name).ok_or_else(|| format!("Unknown config option: {}", name))?;
+
+        if let Some(v) = &value {
+            if self.visibility(name) == 0 {
+                let deps = Expr::all(sym.prompts.iter().map(|(_, c)| c.clone()));
+                return Err(format!("{} can't be changed, it depends on {}", name, deps));
+            }
+            self.check_value(sym, v)?;
+        }
+
+        let choice = sym.choice;
+        let before = self.states();
+        match choice {
+            Some(ci) => {
+                if value.as_deref().is_some_and(|v| v != "y") {
+                    return Err(format!("{} is part of a choice, select another option instead", name));
+                }
+                self.choices[ci].user = value.map(|_| name.to_string());
+            }
+            None => self.symbols.get_mut(name).unwrap().user = value,
+        }
+        self.evaluate();
+
+        Ok(self
+            .states()
+            .into_iter()
+            .filter(|s| {
+                before
+                    .iter()
+                    .find(|b| b.name == s.name)
+                    .map_or(true, |b| b.value != s.value || b.visible != s.visible)
+            })
+            .collect())
+    }
+
+    fn states(&self) -> Vec<KconfigSymbolState> {
+        self.order
+            .iter()
+            .map(|name| KconfigSymbolState {
+                name: name.clone(),
+                value: self.values.get(name).cloned().unwrap_or_default(),
+                visible: self.visibility(name) > 0,
+            })
+            .collect()
+    }
+
+    // ---- output ----
+
+    /// The evaluated menu tree, rooted at the main menu.
+    pub fn menu_tree(&self) -> KconfigNode {
+        self.node_view(0, true)
+    }
+
+    /// Options whose name, prompt or help text contains `query`.
+    pub fn search(&self, query: &str) -> Vec<KconfigNode> {
+        let query = query.to_lowercase();
+
+        (0..self.nodes.len())
+            .filter(|&id| {
+                let node = &self.nodes[id];
+                match &node.item {
+                    Item::Symbol(name) => {
+                        node.title.is_some()
+                            && [Some(name.as_str()), node.title.as_deref(), node.help.as_deref()]
+                                .iter()
+                                .flatten()
+                                .any(|t| t.to_lowercase().contains(&query))
+                    }
+                    _ => false,
+                }
+            })
+            .map(|id| self.node_view(id, false))
+            .collect()
+    }
+
+    fn node_view(&self, id: usize, recurse: bool) -> KconfigNode {
+        let node = &self.nodes[id];
+        let children = if recurse {
+            node.children
+                .iter()
+                .filter(|&&c| self.nodes[c].title.is_some())
+                .map(|&c| self.node_view(c, true))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let mut view = KconfigNode {
+            id,
+            kind: KconfigNodeKind::Menu,
+            title: node.title.clone().unwrap_or_default(),
+            symbol: None,
+            symbol_type: None,
+            value: None,
+            user_set: false,
+            visible: self.tri(&Expr::and(node.dep.clone(), node.visible_if.clone())) > 0,
+            help: node.help.clone(),
+            depends_on: Some(node.dep.to_string()).filter(|_| !node.dep.is_yes()),
+            selects: Vec::new(),
+            selected_by: Vec::new(),
+            defaults: Vec::new(),
+            range: None,
+            children,
+        };
+
+        match &node.item {
+            Item::Menu => {}
+            Item::Comment => view.kind = KconfigNodeKind::Comment,
+            Item::Choice(ci) => {
+                let choice = &self.choices[*ci];
+                view.kind = KconfigNodeKind::Choice;
+                view.symbol = choice.name.clone();
+                view.value = choice.selection.clone();
+                view.user_set = choice.user.is_some();
+                view.visible = self.choice_visibility(*ci) > 0;
+            }
+            Item::Symbol(name) => {
+                let sym = &self.symbols[name];
+                view.kind = KconfigNodeKind::Symbol;
+                view.symbol = Some(name.clone());
+                view.symbol_type = Some(sym.ty);
+                view.value = self.values.get(name).cloned();
+                view.user_set = match sym.choice {
+                    Some(ci) => self.choices[ci].user.as_deref() == Some(name),
+                    None => sym.user.is_some(),
+                };
+                view.visible = self.visibility(name) > 0;
+                view.selects = sym.selects.iter().map(|(s, _)| s.clone()).collect();
+                view.selected_by = sym.rev_dep.iter().map(|(s, _)| s.clone()).collect();
+                view.defaults = sym.default_text.clone();
+                view.range = sym
+                    .ranges
+                    .iter()
+                    .find(|(_, _, cond)| self.tri(cond) > 0)
+                    .map(|(low, high, _)| (self.str_val(low), self.str_val(high)));
+            }
+        }
+        view
+    }
+
+    /// Renders the current values as an sdkconfig file, in the layout
+    /// menuconfig writes: symbols whose dependencies hold, grouped by menu.
+    pub fn to_sdkconfig(&self) -> String {
+        let mut out = vec![
+            "#".to_string(),
+            "# Automatically generated file. DO NOT EDIT.".to_string(),
+            format!(
+                "# Espressif IoT Development Framework (ESP-IDF) {} Project Configuration",
+                self.idf_version.as_deref().unwrap_or("")
+            ),
+            "#".to_string(),
+        ];
+        let mut written = HashSet::new();
+
+        for &child in &self.nodes[0].children {
+            self.write_node(child, &mut out, &mut written);
+        }
+        out.join("\n") + "\n"
+    }
+
+    fn write_node(&self, id: usize, out: &mut Vec<String>, written: &mut HashSet<String>) {
+        let node = &self.nodes[id];
+
+        match &node.item {
+            Item::Menu => {
+                let mut body = Vec::new();
+                for &child in &node.children {
+                    self.write_node(child, &mut body, written);
+                }
+                if !body.is_empty() {
+                    let title = node.title.as_deref().unwrap_or("");
+                    out.extend(["#".to_string(), format!("# {}", title), "#".to_string()]);
+                    out.extend(body);
+                    out.push(format!("# end of {}", title));
+                    out.push(String::new());
+                }
+            }
+            Item::Choice(_) => {
+                for &child in &node.children {
+                    self.write_node(child, out, written);
+                }
+            }
+            Item::Comment => {}
+            Item::Symbol(name) => {
+                let sym = &self.symbols[name];
+                if sym.ty == KconfigType::Unknown || self.dir_dep(sym) == 0 || !written.insert(name.clone()) {
+                    return;
+                }
+
+                let value = self.values.get(name).map(String::as_str).unwrap_or("");
+                match sym.ty {
+                    KconfigType::Bool | KconfigType::Tristate if value == "n" => {
+                        out.push(format!("# CONFIG_{} is not set", name))
+                    }
+                    KconfigType::String => out.push(format!("CONFIG_{}={}", name, sdkconfig::quote(value))),
+                    _ if value.is_empty() => {}
+                    _ => out.push(format!("CONFIG_{}={}", name, value)),
+                }
+            }
+        }
+    }
+}
+
+fn parse_tri(s: &str) -> Option<u8> {
+    match s {
+        "n" => Some(0),
+        "m" => Some(1),
+        "y" => Some(2),
+        _ => None,
+    }
+}
+
+fn tri_name(v: u8) -> &'static str {
+    ["n", "m", "y"][v as usize]
+}
+
+fn parse_num(s: &str) -> Option<i64> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn format_num(v: i64, hex: bool) -> String {
+    if hex {
+        format!("0x{:x}", v)
+    } else {
+        v.to_string()
+    }
+}
+
+/// The generated `kconfigs.in`/`kconfigs_projbuild.in` that `$IDF_PATH/Kconfig`
+/// sources. A configured project has them in build/; otherwise they are
+/// generated from the IDF and project component directories.
+fn component_sources(project: &Path, idf_path: &Path) -> Result<(PathBuf, PathBuf), String> {
+    let build = project.join("build");
+    let (kconfigs, projbuild) = (build.join("kconfigs.in"), build.join("kconfigs_projbuild.in"));
+    if kconfigs.exists() && projbuild.exists() {
+        return Ok((kconfigs, projbuild));
+    }
+
+    let mut dirs = Vec::new();
+    for root in [idf_path.join("components"), project.join("components"), project.join("managed_components")] {
+        if let Ok(entries) = fs::read_dir(root) {
+            dirs.extend(entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()));
+        }
+    }
+    dirs.sort();
+    dirs.push(project.join("main"));
+
+    let list = |file: &str| {
+        dirs.iter()
+            .map(|d| d.join(file))
+            .filter(|f| f.is_file())
+            .map(|f| format!("source \"{}\"\n", f.display()))
+            .collect::<String>()
+    };
+
+    let out_dir = project_settings::editor_dir(project).join("kconfig");
+    fs::create_dir_all(&out_dir).map_err(|e| e.to_string())?;
+    project_settings::ensure_gitignore(project);
+    let (kconfigs, projbuild) = (out_dir.join("kconfigs.in"), out_dir.join("kconfigs_projbuild.in"));
+    fs::write(&kconfigs, list("Kconfig")).map_err(|e| e.to_string())?;
+    fs::write(&projbuild, list("Kconfig.projbuild")).map_err(|e| e.to_string())?;
+    Ok((kconfigs, projbuild))
+}
+
+// ---- parser ----
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Op(&'static str),
+}
+
+const OPERATORS: [&str; 11] = ["&&", "||", "!=", "<=", ">=", "!", "=", "<", ">", "(", ")"];
+
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '#' {
+            break;
+        } else if c == '"' || c == '\'' {
+            let mut s = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err("unterminated string".into()),
+                    Some(&q) if q == c => break,
+                    Some('\\') => {
+                        i += 1;
+                        s.extend(chars.get(i));
+                    }
+                    Some(&ch) => s.push(ch),
+                }
+                i += 1;
+            }
+            i += 1;
+            tokens.push(Token::Str(s));
+        } else if let Some(op) = OPERATORS.iter().find(|op| line_at(&chars, i).starts_with(*op)) {
+            tokens.push(Token::Op(op));
+            i += op.len();
+        } else {
+            let start = i;
+            while i < chars.len() && !chars[i].is_whitespace() && !"\"'#!=<>()&|".contains(chars[i]) {
+                i += 1;
+            }
+            if start == i {
+                return Err(format!("unexpected character '{}'", c));
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        }
+    }
+    Ok(tokens)
+}
+
+fn line_at(chars: &[char], i: usize) -> String {
+    chars[i..chars.len().min(i + 2)].iter().collect()
+}
+
+/// Recursive-descent parser over one line's tokens.
+struct Tokens {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Tokens {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn eat_op(&mut self, op: &'static str) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        match self.peek() {
+            Some(Token::Str(s)) => {
+                let s = s.clone();
+                self.pos += 1;
+                Some(s)
+            }
+            _ => None,
+        }
+    }
+
+    fn word(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Word(w)) => Ok(w),
+            t => Err(format!("expected a name, found {:?}", t)),
+        }
+    }
+
+    /// Optional trailing `if <expr>`.
+    fn condition(&mut self) -> Result<Option<Expr>, String> {
+        match self.peek() {
+            Some(Token::Word(w)) if w == "if" => {
+                self.pos += 1;
+                self.expr().map(Some)
+            }
+            None => Ok(None),
+            Some(t) => Err(format!("unexpected {:?}", t)),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.and_expr()?;
+        while self.eat_op("||") {
+            left = Expr::Or(Box::new(left), Box::new(self.and_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        while self.eat_op("&&") {
+            left = Expr::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat_op("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat_op("(") {
+            let e = self.expr()?;
+            if !self.eat_op(")") {
+                return Err("missing ')'".into());
+            }
+            return Ok(e);
+        }
+
+        let left = self.operand()?;
+        for op in ["=", "!=", "<", "<=", ">", ">="] {
+            if self.eat_op(op) {
+                return Ok(Expr::Cmp(op, Box::new(left), Box::new(self.operand()?)));
+            }
+        }
+        Ok(left)
+    }
+
+    fn operand(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Word(w)) => Ok(Expr::Sym(w)),
+            Some(Token::Str(s)) => Ok(Expr::Str(s)),
+            t => Err(format!("expected a symbol, found {:?}", t)),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum BlockKind {
+    Menu,
+    Choice,
+    If,
+}
+
+/// An open `menu`/`choice`/`if`; its dependencies apply to everything inside.
+struct Block {
+    kind: BlockKind,
+    node: Option<usize>,
+    deps: Vec<Expr>,
+    visible_if: Vec<Expr>,
+}
+
+/// The entry whose properties are being read.
+struct Current {
+    node: usize,
+    symbol: Option<String>,
+    choice: Option<usize>,
+    ty: Option<KconfigType>,
+    prompt: Option<(String, Option<Expr>)>,
+    defaults: Vec<(Expr, Option<Expr>)>,
+    selects: Vec<(String, Option<Expr>)>,
+    implies: Vec<(String, Option<Expr>)>,
+    ranges: Vec<(Expr, Expr, Option<Expr>)>,
+    depends: Vec<Expr>,
+    inherited: Vec<Expr>,
+    visible_if: Vec<Expr>,
+    /// set for `menu` and `choice`, whose `depends on` also covers children
+    opens_block: bool,
+}
+
+struct Parser {
+    env: HashMap<String, String>,
+    srctree: PathBuf,
+    tree: KconfigTree,
+    blocks: Vec<Block>,
+    current: Option<Current>,
+    file_stack: Vec<PathBuf>,
+}
+
+impl Parser {
+    fn new(env: HashMap<String, String>, srctree: PathBuf) -> Parser {
+        let root = Node {
+            item: Item::Menu,
+            title: Some("Main menu".to_string()),
+            dep: Expr::yes(),
+            visible_if: Expr::yes(),
+            help: None,
+            children: Vec::new(),
+        };
+        Parser {
+            env,
+            srctree,
+            tree: KconfigTree {
+                symbols: HashMap::new(),
+                order: Vec::new(),
+                choices: Vec::new(),
+                nodes: vec![root],
+                values: HashMap::new(),
+                idf_version: None,
+                warnings: Vec::new(),
+            },
+            blocks: Vec::new(),
+            current: None,
+            file_stack: Vec::new(),
+        }
+    }
+
+    fn finish(mut self) -> KconfigTree {
+        self.finalize();
+        if !self.blocks.is_empty() {
+            self.tree.warnings.push(format!("{} unterminated menu/choice/if block(s)", self.blocks.len()));
+        }
+
+        let selects: Vec<(String, String, Expr, bool)> = self
+            .tree
+            .order
+            .iter()
+            .flat_map(|name| {
+                let sym = &self.tree.symbols[name];
+                let strong = sym.selects.iter().map(move |(t, c)| (t.clone(), name.clone(), c.clone(), true));
+                let weak = sym.implies.iter().map(move |(t, c)| (t.clone(), name.clone(), c.clone(), false));
+                strong.chain(weak)
+            })
+            .collect();
+
+        for (target, selector, cond, strong) in selects {
+            match self.tree.symbols.get_mut(&target) {
+                Some(t) if strong => t.rev_dep.push((selector, cond)),
+                Some(t) => t.weak_rev_dep.push((selector, cond)),
+                None => self.tree.warnings.push(format!("{} selects undefined symbol {}", selector, target)),
+            }
+        }
+        self.tree
+    }
+
+    fn parse_file(&mut self, path: &Path, optional: bool) -> Result<(), String> {
+        let text = match fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(_) if optional => return Ok(()),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        if self.file_stack.iter().any(|p| p == path) {
+            return Err(format!("{} sources itself", path.display()));
+        }
+        self.file_stack.push(path.to_path_buf());
+
+        let lines = join_continuations(&text);
+        let mut i = 0;
+        while i < lines.len() {
+            let (lineno, line) = &lines[i];
+            i += 1;
+
+            let trimmed = line.trim();
+            if trimmed == "help" || trimmed == "---help---" {
+                let indent = indentation(line);
+                let (help, next) = read_help(&lines, i, indent);
+                i = next;
+                self.set_help(help);
+                continue;
+            }
+
+            if let Err(e) = self.parse_line(path, line) {
+                self.tree.warnings.push(format!("{}:{}: {}", path.display(), lineno, e));
+            }
+        }
+
+        self.finalize();
+        self.file_stack.pop();
+        Ok(())
+    }
+
+    fn parse_line(&mut self, path: &Path, line: &str) -> Result<(), String> {
+        let mut t = Tokens { tokens: tokenize(line)?, pos: 0 };
+        let keyword = match t.next() {
+            Some(Token::Word(w)) => w,
+            None => return Ok(()),
+            Some(tok) => return Err(format!("unexpected {:?}", tok)),
+        };
+
+        match keyword.as_str() {
+            "config" | "menuconfig" => {
+                let name = t.word()?;
+                self.start_symbol(name);
+            }
+            "choice" => {
+                let name = t.word().ok();
+                self.tree.choices.push(Choice {
+                    name,
+                    prompts: Vec::new(),
+                    defaults: Vec::new(),
+                    dir_dep: Expr::yes(),
+                    members: Vec::new(),
+                    user: None,
+                    selection: None,
+                });
+                let ci = self.tree.choices.len() - 1;
+                let node = self.start_item(Item::Choice(ci), true);
+                self.current.as_mut().unwrap().choice = Some(ci);
+                self.blocks.push(Block { kind: BlockKind::Choice, node: Some(node), deps: Vec::new(), visible_if: Vec::new() });
+            }
+            "endchoice" => self.end_block(BlockKind::Choice)?,
+            "menu" => {
+                let title = t.string().ok_or("menu without a title")?;
+                let node = self.start_item(Item::Menu, true);
+                self.tree.nodes[node].title = Some(title);
+                self.blocks.push(Block { kind: BlockKind::Menu, node: Some(node), deps: Vec::new(), visible_if: Vec::new() });
+            }
+            "endmenu" => self.end_block(BlockKind::Menu)?,
+            "if" => {
+                self.finalize();
+                let cond = t.expr()?;
+                self.blocks.push(Block { kind: BlockKind::If, node: None, deps: vec![cond], visible_if: Vec::new() });
+            }
+            "endif" => self.end_block(BlockKind::If)?,
+            "comment" => {
+                let text = t.string().ok_or("comment without text")?;
+                let node = self.start_item(Item::Comment, false);
+                self.tree.nodes[node].title = Some(text);
+            }
+            "mainmenu" => self.tree.nodes[0].title = t.string(),
+            "source" | "rsource" | "osource" | "orsource" => {
+                self.finalize();
+                let raw = t.string().ok_or("source without a path")?;
+                let expanded = self.expand_env(&raw);
+                if expanded.is_empty() {
+                    return Ok(());
+                }
+                let relative = keyword.contains("rsource");
+                let base = if relative {
+                    path.parent().map(Path::to_path_buf).unwrap_or_default()
+                } else {
+                    self.srctree.clone()
+                };
+                let optional = keyword.starts_with('o');
+
+                let files = expand_glob(&base.join(expanded));
+                if files.is_empty() && !optional {
+                    return Err(format!("sourced file not found: {}", raw));
+                }
+                for file in files {
+                    self.parse_file(&file, optional)?;
+                }
+            }
+            _ => self.parse_property(&keyword, &mut t)?,
+        }
+        Ok(())
+    }
+
+    fn parse_property(&mut self, keyword: &str, t: &mut Tokens) -> Result<(), String> {
+        let env_default = if keyword == "option" {
+            match t.next() {
+                Some(Token::Word(w)) if w == "env" => {
+                    t.eat_op("=");
+                    t.string().map(|var| self.expand_env(&format!("${}", var)))
+                }
+                _ => return Ok(()),
+            }
+        } else {
+            None
+        };
+
+        let ty = match keyword.trim_start_matches("def_") {
+            "bool" | "boolean" => Some(KconfigType::Bool),
+            "tristate" => Some(KconfigType::Tristate),
+            "int" => Some(KconfigType::Int),
+            "hex" => Some(KconfigType::Hex),
+            "string" => Some(KconfigType::String),
+            _ => None,
+        };
+
+        let expanded: Vec<Token> = t.tokens[t.pos..]
+            .iter()
+            .map(|tok| match tok {
+                Token::Str(s) => Token::Str(self.expand_env(s)),
+                other => other.clone(),
+            })
+            .collect();
+        let mut t = Tokens { tokens: expanded, pos: 0 };
+
+        let cur = self.current.as_mut().ok_or_else(|| format!("'{}' outside of an entry", keyword))?;
+
+        if let Some(value) = env_default {
+            cur.defaults.push((Expr::Str(value), None));
+            return Ok(());
+        }
+
+        if let Some(ty) = ty {
+            cur.ty = Some(ty);
+            if keyword.starts_with("def_") {
+                let value = t.expr()?;
+                cur.defaults.push((value, t.condition()?));
+            } else if let Some(prompt) = t.string() {
+                cur.prompt = Some((prompt, t.condition()?));
+            }
+            return Ok(());
+        }
+
+        match keyword {
+            "prompt" => {
+                let prompt = t.string().ok_or("prompt without text")?;
+                cur.prompt = Some((prompt, t.condition()?));
+            }
+            "default" => {
+                let value = t.expr()?;
+                cur.defaults.push((value, t.condition()?));
+            }
+            "depends" => {
+                if t.word()? != "on" {
+                    return Err("expected 'depends on'".into());
+                }
+                cur.depends.push(t.expr()?);
+            }
+            "visible" => {
+                if t.word()? != "if" {
+                    return Err("expected 'visible if'".into());
+                }
+                let cond = t.expr()?;
+                match self.blocks.last_mut() {
+                    Some(b) if cur.opens_block => b.visible_if.push(cond),
+                    _ => return Err("'visible if' outside of a menu".into()),
+                }
+            }
+            "select" | "imply" => {
+                let target = t.word()?;
+                let cond = t.condition()?;
+                if keyword == "select" {
+                    cur.selects.push((target, cond));
+                } else {
+                    cur.implies.push((target, cond));
+                }
+            }
+            "range" => {
+                let low = t.operand()?;
+                let high = t.operand()?;
+                cur.ranges.push((low, high, t.condition()?));
+            }
+            "optional" | "modules" | "transitional" => {}
+            other => return Err(format!("unknown keyword '{}'", other)),
+        }
+        Ok(())
+    }
+
+    fn start_symbol(&mut self, name: String) {
+        self.start_item(Item::Symbol(name.clone()), false);
+        let choice = match self.blocks.last() {
+            Some(Block { kind: BlockKind::Choice, node: Some(n), .. }) => match self.tree.nodes[*n].item {
+                Item::Choice(ci) => Some(ci),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let cur = self.current.as_mut().unwrap();
+        cur.symbol = Some(name.clone());
+        cur.choice = choice;
+
+        if !self.tree.symbols.contains_key(&name) {
+            self.tree.order.push(name.clone());
+            self.tree.symbols.insert(
+                name,
+                Symbol {
+                    ty: KconfigType::Unknown,
+                    prompts: Vec::new(),
+                    defaults: Vec::new(),
+                    default_text: Vec::new(),
+                    selects: Vec::new(),
+                    implies: Vec::new(),
+                    ranges: Vec::new(),
+                    dir_dep: Vec::new(),
+                    rev_dep: Vec::new(),
+                    weak_rev_dep: Vec::new(),
+                    choice,
+                    user: None,
+                },
+            );
+        }
+    }
+
+    /// Ends the previous entry and adds a node for a new one under the
+    /// innermost open menu or choice.
+    fn start_item(&mut self, item: Item, opens_block: bool) -> usize {
+        self.finalize();
+
+        let parent = self.blocks.iter().rev().find_map(|b| b.node).unwrap_or(0);
+        self.tree.nodes.push(Node {
+            item,
+            title: None,
+            dep: Expr::yes(),
+            visible_if: Expr::yes(),
+            help: None,
+            children: Vec::new(),
+        });
+        let id = self.tree.nodes.len() - 1;
+        self.tree.nodes[parent].children.push(id);
+
+        self.current = Some(Current {
+            node: id,
+            symbol: None,
+            choice: None,
+            ty: None,
+            prompt: None,
+            defaults: Vec::new(),
+            selects: Vec::new(),
+            implies: Vec::new(),
+            ranges: Vec::new(),
+            depends: Vec::new(),
+            inherited: self.blocks.iter().flat_map(|b| b.deps.iter().cloned()).collect(),
+            visible_if: self.blocks.iter().flat_map(|b| b.visible_if.iter().cloned()).collect(),
+            opens_block,
+        });
+        id
+    }
+
+    fn end_block(&mut self, kind: BlockKind) -> Result<(), String> {
+        self.finalize();
+        match self.blocks.pop() {
+            Some(b) if b.kind == kind => Ok(()),
+            Some(b) => {
+                self.blocks.push(b);
+                Err("mismatched end of block".into())
+            }
+            None => Err("end of block without a matching start".into()),
+        }
+    }
+
+    fn set_help(&mut self, help: String) {
+        if let Some(cur) = &self.current {
+            self.tree.nodes[cur.node].help = Some(help);
+        }
+    }
+
+    /// Folds the finished entry's properties into its symbol/choice, with the
+    /// entry's dependencies propagated into every condition.
+    fn finalize(&mut self) {
+        let cur = match self.current.take() {
+            Some(c) => c,
+            None => return,
+        };
+
+        let dep = Expr::all(cur.inherited.iter().chain(&cur.depends).cloned());
+        let visible_if = Expr::all(cur.visible_if.iter().cloned());
+        let cond = |c: &Option<Expr>| Expr::and(dep.clone(), c.clone().unwrap_or_else(Expr::yes));
+
+        let node = &mut self.tree.nodes[cur.node];
+        node.dep = dep.clone();
+        if cur.opens_block {
+            if let Some(block) = self.blocks.last_mut() {
+                block.deps.extend(cur.depends.iter().cloned());
+                node.visible_if = Expr::all(block.visible_if.iter().cloned());
+            }
+        }
+
+        if let Some(name) = &cur.symbol {
+            node.title = cur.prompt.as_ref().map(|(p, _)| p.clone());
+            let sym = self.tree.symbols.get_mut(name).unwrap();
+
+            if let Some(ty) = cur.ty {
+                sym.ty = ty;
+            }
+            sym.dir_dep.push(dep.clone());
+            if let Some((text, c)) = &cur.prompt {
+                sym.prompts.push((text.clone(), Expr::and(cond(c), visible_if.clone())));
+            }
+            for (value, c) in &cur.defaults {
+                sym.default_text.push(match c {
+                    Some(c) => format!("{} if {}", value, c),
+                    None => value.to_string(),
+                });
+                sym.defaults.push((value.clone(), cond(c)));
+            }
+            sym.selects.extend(cur.selects.iter().map(|(s, c)| (s.clone(), cond(c))));
+            sym.implies.extend(cur.implies.iter().map(|(s, c)| (s.clone(), cond(c))));
+            sym.ranges.extend(cur.ranges.iter().map(|(l, h, c)| (l.clone(), h.clone(), cond(c))));
+
+            if let Some(ci) = cur.choice {
+                let members = &mut self.tree.choices[ci].members;
+                if !members.contains(name) {
+                    members.push(name.clone());
+                }
+            }
+        } else if let Some(ci) = cur.choice {
+            let choice = &mut self.tree.choices[ci];
+            choice.dir_dep = dep.clone();
+            if let Some((text, c)) = &cur.prompt {
+                node.title = Some(text.clone());
+                choice.prompts.push(Expr::and(cond(c), visible_if));
+            }
+            for (value, c) in &cur.defaults {
+                if let Expr::Sym(member) = value {
+                    choice.defaults.push((member.clone(), cond(c)));
+                }
+            }
+        }
+    }
+
+    /// Expands `$VAR`, `${VAR}` and `$(VAR)`; unknown variables come from the
+    /// process environment.
+    fn expand_env(&self, s: &str) -> String {
+        let mut out = String::new();
+        let mut rest = s;
+
+        while let Some(pos) = rest.find('$') {
+            out.push_str(&rest[..pos]);
+            let after = &rest[pos + 1..];
+
+            let (name, len) = match after.chars().next() {
+                Some(open @ ('(' | '{')) => {
+                    let close = if open == '(' { ')' } else { '}' };
+                    match after.find(close) {
+                        Some(end) => (&after[1..end], end + 1),
+                        None => (after, after.len()),
+                    }
+                }
+                _ => {
+                    let end = after
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .unwrap_or(after.len());
+                    (&after[..end], end)
+                }
+            };
+
+            match self.env.get(name) {
+                Some(v) => out.push_str(v),
+                None => out.push_str(&std::env::var(name).unwrap_or_default()),
+            }
+            rest = &after[len..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Logical lines with their 1-based line numbers, backslash continuations joined.
+fn join_continuations(text: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let (lineno, mut joined) = pending.take().unwrap_or((i + 1, String::new()));
+        match line.strip_suffix('\\') {
+            Some(head) => {
+                joined.push_str(head);
+                pending = Some((lineno, joined));
+            }
+            None => {
+                joined.push_str(line);
+                lines.push((lineno, joined));
+            }
+        }
+    }
+    lines.extend(pending);
+    lines
+}
+
+fn indentation(line: &str) -> usize {
+    let mut width = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width = (width / 8 + 1) * 8,
+            _ => break,
+        }
+    }
+    width
+}
+
+/// Reads a help block starting at `start`: every line indented deeper than
+/// the `help` keyword (blank lines included) until the indentation drops.
+fn read_help(lines: &[(usize, String)], start: usize, keyword_indent: usize) -> (String, usize) {
+    let mut i = start;
+    let mut help: Vec<&str> = Vec::new();
+    let mut block_indent = None;
+
+    while i < lines.len() {
+        let line = &lines[i].1;
+        if line.trim().is_empty() {
+            help.push("");
+            i += 1;
+            continue;
+        }
+
+        let indent = indentation(line);
+        let min = *block_indent.get_or_insert(indent);
+        if indent <= keyword_indent || indent < min {
+            break;
+        }
+        help.push(line.trim());
+        i += 1;
+    }
+
+    while help.last() == Some(&"") {
+        help.pop();
+    }
+    (help.join("\n"), i)
+}
+
+/// Resolves `*`/`?` wildcards in any path component, sorted.
+fn expand_glob(path: &Path) -> Vec<PathBuf> {
+    if !path.to_string_lossy().contains(['*', '?']) {
+        return if path.exists() { vec![path.to_path_buf()] } else { Vec::new() };
+    }
+
+    let mut matches = vec![PathBuf::new()];
+    for component in path.components() {
+        let part = component.as_os_str().to_string_lossy();
+        if !part.contains(['*', '?']) {
+            for m in &mut matches {
+                m.push(component);
+            }
+            continue;
+        }
+
+        matches = matches
+            .iter()
+            .flat_map(|dir| fs::read_dir(dir).into_iter().flatten().flatten())
+            .map(|e| e.path())
+            .filter(|p| p.file_name().is_some_and(|n| wildcard_match(&part, &n.to_string_lossy())))
+            .collect();
+        matches.sort();
+    }
+    matches.into_iter().filter(|p| p.exists()).collect()
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (p, n): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    let (mut pi, mut ni) = (0, 0);
+    let (mut star, mut mark) = (None, 0);
+
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some(pi);
+            mark = ni;
+            pi += 1;
+        } else if let Some(s) = star {
+            pi = s + 1;
+            mark += 1;
+            ni = mark;
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::toolchain::ToolchainSource;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/kconfig")
+            .join(name)
+    }
+
+    fn load() -> KconfigTree {
+        let toolchain = Toolchain {
+            id: "test".into(),
+            version: Some("v5.3".into()),
+            idf_path: fixture("idf").to_string_lossy().to_string(),
+            python: None,
+            source: ToolchainSource::IdfPathEnv,
+        };
+        let tree = KconfigTree::load(&fixture("project"), &toolchain).unwrap();
+        assert!(tree.warnings.is_empty(), "{:?}", tree.warnings);
+        tree
+    }
+
+    fn value<'a>(tree: &'a KconfigTree, name: &str) -> &'a str {
+        tree.values.get(name).map(String::as_str).unwrap_or("")
+    }
+
+    #[test]
+    fn applies_sdkconfig_within_constraints() {
+        let tree = load();
+
+        assert_eq!(value(&tree, "IDF_TARGET"), "esp32");
+        assert_eq!(value(&tree, "WIFI_ENABLED"), "y");
+        assert_eq!(value(&tree, "NET_IPV6"), "n");
+        // out of range, so the default wins
+        assert_eq!(value(&tree, "WIFI_TX_BUFFERS"), "16");
+        // MESH depends on another target, so the choice falls back to its default
+        assert_eq!(value(&tree, "WIFI_MODE_MESH"), "n");
+        assert_eq!(value(&tree, "WIFI_MODE_STA"), "y");
+        assert_eq!(value(&tree, "WIFI_SSID"), "esp");
+        assert_eq!(value(&tree, "EXAMPLE_GREETING"), "hello \"world\"");
+    }
+
+    #[test]
+    fn select_forces_the_target_on() {
+        let mut tree = load();
+        assert_eq!(value(&tree, "NET_STACK"), "y");
+
+        tree.set_value("NET_STACK", Some("n".into())).unwrap();
+        assert_eq!(value(&tree, "NET_STACK"), "y");
+
+        let changed = tree.set_value("WIFI_ENABLED", Some("n".into())).unwrap();
+        assert_eq!(value(&tree, "NET_STACK"), "n");
+        assert!(changed.iter().any(|s| s.name == "NET_STACK"));
+        assert!(changed.iter().any(|s| s.name == "WIFI_TX_BUFFERS" && !s.visible));
+    }
+
+    #[test]
+    fn depends_on_hides_options() {
+        let mut tree = load();
+        tree.set_value("WIFI_ENABLED", Some("n".into())).unwrap();
+
+        let err = tree.set_value("WIFI_TX_BUFFERS", Some("8".into())).unwrap_err();
+        assert!(err.contains("depends on WIFI_ENABLED"), "{}", err);
+        assert_eq!(value(&tree, "WIFI_MODE_STA"), "n");
+
+        tree.set_value("NET_STACK", Some("y".into())).unwrap();
+        assert_eq!(value(&tree, "NET_IPV6"), "n");
+        tree.set_value("NET_IPV6", None).unwrap();
+        assert_eq!(value(&tree, "NET_IPV6"), "y");
+    }
+
+    #[test]
+    fn choice_keeps_one_member_selected() {
+        let mut tree = load();
+
+        tree.set_value("WIFI_MODE_AP", Some("y".into())).unwrap();
+        assert_eq!(value(&tree, "WIFI_MODE_AP"), "y");
+        assert_eq!(value(&tree, "WIFI_MODE_STA"), "n");
+
+        assert!(tree.set_value("WIFI_MODE_AP", Some("n".into())).is_err());
+        assert!(tree.set_value("WIFI_MODE_MESH", Some("y".into())).is_err());
+        assert!(tree.set_value("WIFI_TX_BUFFERS", Some("65".into())).is_err());
+    }
+
+    #[test]
+    fn sdkconfig_round_trips() {
+        let mut tree = load();
+        tree.set_value("WIFI_MODE_AP", Some("y".into())).unwrap();
+        tree.set_value("WIFI_TX_BUFFERS", Some("32".into())).unwrap();
+        let text = tree.to_sdkconfig();
+
+        assert!(text.contains("# CONFIG_NET_IPV6 is not set\n"));
+        assert!(text.contains("CONFIG_WIFI_TX_BUFFERS=32\n"));
+        assert!(text.contains("CONFIG_EXAMPLE_GREETING=\"hello \\\"world\\\"\"\n"));
+        // hidden by `depends on WIFI_MODE_STA`
+        assert!(!text.contains("WIFI_SSID"));
+
+        let path = std::env::temp_dir().join(format!("sdkconfig-{}", uuid::Uuid::new_v4()));
+        fs::write(&path, &text).unwrap();
+        let values = sdkconfig::read_all(&path);
+        fs::remove_file(&path).unwrap();
+
+        let mut reloaded = load();
+        reloaded.apply_user_values(values.unwrap());
+        reloaded.evaluate();
+        assert_eq!(reloaded.values, tree.values);
+        assert_eq!(reloaded.to_sdkconfig(), text);
+    }
+}
@@ -0,0 +1,149 @@
+use std::path::Path;
+
+/// A GNU ld map file: the memory regions from "Memory Configuration" and
+/// every input section placed by "Linker script and memory map".
+pub struct LinkerMap {
+    pub regions: Vec<MemoryRegion>,
+    pub sections: Vec<InputSection>,
+}
+
+pub struct MemoryRegion {
+    pub name: String,
+    pub origin: u64,
+    pub length: u64,
+}
+
+pub struct InputSection {
+    /// Output section it was placed in, e.g. `.iram0.text`.
+    pub output: String,
+    pub address: u64,
+    pub size: u64,
+    /// `libfreertos.a`, or `None` for objects linked directly.
+    pub archive: Option<String>,
+    /// Path of the archive as given to the linker, e.g. `esp-idf/freertos/libfreertos.a`.
+    pub archive_path: Option<String>,
+    pub object: String,
+}
+
+pub fn read(path: &Path) -> Result<LinkerMap, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(parse(&text))
+}
+
+pub fn parse(text: &str) -> LinkerMap {
+    #[derive(PartialEq)]
+    enum Part {
+        Preamble,
+        Memory,
+        Map,
+    }
+
+    let mut part = Part::Preamble;
+    let mut regions = Vec::new();
+    let mut sections: Vec<InputSection> = Vec::new();
+    let mut output: Option<String> = None;
+    // the last input section name was too long to share a line with its address
+    let mut pending = false;
+
+    for line in text.lines() {
+        match line.trim_end() {
+            "Memory Configuration" => {
+                part = Part::Memory;
+                continue;
+            }
+            "Linker script and memory map" => {
+                part = Part::Map;
+                continue;
+            }
+            _ => {}
+        }
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+
+        match part {
+            Part::Preamble => {}
+            Part::Memory => {
+                if let (Some(origin), Some(length)) = (
+                    tokens.get(1).and_then(|t| parse_hex(t)),
+                    tokens.get(2).and_then(|t| parse_hex(t)),
+                ) {
+                    if tokens[0] != "*default*" {
+                        regions.push(MemoryRegion { name: tokens[0].to_string(), origin, length });
+                    }
+                }
+            }
+            Part::Map => {
+                if !line.starts_with(' ') {
+                    // output section (or a LOAD/OUTPUT/group directive)
+                    output = Some(tokens[0].to_string()).filter(|n| n.starts_with('.'));
+                    pending = false;
+                    continue;
+                }
+                let out = match &output {
+                    Some(o) => o,
+                    None => continue,
+                };
+
+                if tokens[0] == "*fill*" {
+                    // padding is charged to whatever precedes it
+                    if let (Some(size), Some(last)) = (tokens.get(2).and_then(|t| parse_hex(t)), sections.last_mut()) {
+                        if &last.output == out {
+                            last.size += size;
+                        }
+                    }
+                    continue;
+                }
+
+                let rest = if tokens[0].starts_with('.') || tokens[0] == "COMMON" {
+                    if tokens.len() == 1 {
+                        pending = true;
+                        continue;
+                    }
+                    &tokens[1..]
+                } else if pending {
+                    &tokens[..]
+                } else {
+                    continue;
+                };
+
+                if let [address, size, file @ ..] = rest {
+                    if let (Some(address), Some(size), false) = (parse_hex(address), parse_hex(size), file.is_empty()) {
+                        if size > 0 {
+                            let (archive_path, object) = split_file(&file.join(" "));
+                            sections.push(InputSection {
+                                output: out.clone(),
+                                address,
+                                size,
+                                archive: archive_path.as_deref().map(file_name),
+                                archive_path,
+                                object,
+                            });
+                        }
+                    }
+                }
+                pending = false;
+            }
+        }
+    }
+
+    LinkerMap { regions, sections }
+}
+
+/// `esp-idf/main/libmain.a(main.c.obj)` -> (`esp-idf/main/libmain.a`, `main.c.obj`)
+fn split_file(file: &str) -> (Option<String>, String) {
+    match file.strip_suffix(')').and_then(|f| f.split_once('(')) {
+        Some((archive, object)) => (Some(archive.to_string()), object.to_string()),
+        None => (None, file_name(file)),
+    }
+}
+
+fn file_name(path: &str) -> String {
+    path.rsplit(['/', '\\']).next().unwrap_or(path).to_string()
+}
+
+fn parse_hex(s: &str) -> Option<u64> {
+    u64::from_str_radix(s.strip_prefix("0x")?, 16).ok()
+}
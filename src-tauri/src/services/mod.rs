@@ -1,4 +1,29 @@
-pub mod esp_idf;
+pub mod arduino_index;
+pub mod arduino_packages;
+pub mod artifacts;
+pub mod build_profiles;
+pub mod diagnostics;
+pub mod elf;
+pub mod encoding;
+pub mod file_tree;
+pub mod firmware_image;
+pub mod frameworks;
+pub mod fs_watcher;
+pub mod history;
+pub mod idf_env;
+pub mod ino_preprocessor;
+pub mod kconfig;
+pub mod linker_map;
 pub mod nats;
-pub mod process_stream;
+pub mod partition_table;
+pub mod process_runner;
+pub mod project_settings;
+pub mod replace;
 pub mod s3;
+pub mod sdkconfig;
+pub mod search;
+pub mod size_analysis;
+pub mod toolchain;
+pub mod transfer;
+pub mod trash;
+pub mod workspace;
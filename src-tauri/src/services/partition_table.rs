@@ -0,0 +1,507 @@
+use md5::{Digest, Md5};
+
+use crate::models::partition::{IssueSeverity, PartitionEntry, PartitionIssue};
+
+/// Where the bootloader expects the table unless the project moves it.
+pub const DEFAULT_TABLE_OFFSET: u32 = 0x8000;
+/// The table occupies one 4 KB sector; only the first 0xC00 bytes are used.
+const TABLE_SECTOR: u32 = 0x1000;
+const MAX_TABLE_LEN: usize = 0xC00;
+const ENTRY_LEN: usize = 32;
+const ENTRY_MAGIC: [u8; 2] = [0xAA, 0x50];
+const MD5_MAGIC: [u8; 2] = [0xEB, 0xEB];
+const MAX_NAME_LEN: usize = 16;
+
+const APP_ALIGN: u32 = 0x10000;
+const DATA_ALIGN: u32 = 0x1000;
+
+const FLAG_ENCRYPTED: u32 = 1 << 0;
+const FLAG_READONLY: u32 = 1 << 1;
+
+const TYPES: [(&str, u8); 2] = [("app", 0x00), ("data", 0x01)];
+
+const APP_SUBTYPES: [(&str, u8); 2] = [("factory", 0x00), ("test", 0x20)];
+
+const DATA_SUBTYPES: [(&str, u8); 11] = [
+    ("ota", 0x00),
+    ("phy", 0x01),
+    ("nvs", 0x02),
+    ("coredump", 0x03),
+    ("nvs_keys", 0x04),
+    ("efuse", 0x05),
+    ("undefined", 0x06),
+    ("esphttpd", 0x80),
+    ("fat", 0x81),
+    ("spiffs", 0x82),
+    ("littlefs", 0x83),
+];
+
+/// Parses a `partitions.csv` in the format `gen_esp32part.py` accepts.
+/// Empty offsets are filled in after the previous partition, aligned the
+/// same way the IDF tool does it.
+pub fn parse_csv(text: &str, table_offset: u32) -> Result<Vec<PartitionEntry>, String> {
+    let mut entries = Vec::new();
+    let mut next_offset = table_offset.saturating_add(TABLE_SECTOR);
+
+    for (idx, raw) in text.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = raw.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if fields.len() < 5 {
+            return Err(format!("Line {}: expected at least 5 columns (name, type, subtype, offset, size)", line_no));
+        }
+
+        let partition_type = fields[1].to_string();
+        let type_id = type_id(&partition_type).ok_or_else(|| format!("Line {}: unknown type '{}'", line_no, partition_type))?;
+
+        let offset = if fields[3].is_empty() {
+            let align = if type_id == 0x00 { APP_ALIGN } else { DATA_ALIGN };
+            align_up(next_offset, align).ok_or_else(|| format!("Line {}: no room left for '{}'", line_no, fields[0]))?
+        } else {
+            parse_number(fields[3]).ok_or_else(|| format!("Line {}: bad offset '{}'", line_no, fields[3]))?
+        };
+        let size = parse_number(fields[4]).ok_or_else(|| format!("Line {}: bad size '{}'", line_no, fields[4]))?;
+
+        let flags: Vec<&str> = fields.get(5).map(|f| f.split(':').map(str::trim).collect()).unwrap_or_default();
+        for flag in flags.iter().filter(|f| !f.is_empty()) {
+            if *flag != "encrypted" && *flag != "readonly" {
+                return Err(format!("Line {}: unknown flag '{}'", line_no, flag));
+            }
+        }
+
+        entries.push(PartitionEntry {
+            name: fields[0].to_string(),
+            partition_type,
+            subtype: fields[2].to_string(),
+            offset,
+            size,
+            encrypted: flags.contains(&"encrypted"),
+            readonly: flags.contains(&"readonly"),
+        });
+        next_offset = offset
+            .checked_add(size)
+            .ok_or_else(|| format!("Line {}: '{}' ends past the 4 GB address space", line_no, fields[0]))?;
+    }
+
+    Ok(entries)
+}
+
+pub fn to_csv(entries: &[PartitionEntry]) -> String {
+    let mut out = String::from("# Name,   Type, SubType, Offset,  Size, Flags\n");
+
+    for e in entries {
+        let mut flags = Vec::new();
+        if e.encrypted {
+            flags.push("encrypted");
+        }
+        if e.readonly {
+            flags.push("readonly");
+        }
+        out.push_str(&format!(
+            "{},{},{},{:#x},{},{}\n",
+            e.name,
+            e.partition_type,
+            e.subtype,
+            e.offset,
+            format_size(e.size),
+            flags.join(":")
+        ));
+    }
+
+    out
+}
+
+/// Reads the binary table (as flashed at 0x8000). Stops at the MD5 entry or
+/// the first erased (0xFF) entry, and checks the MD5 if there is one.
+pub fn parse_binary(data: &[u8]) -> Result<Vec<PartitionEntry>, String> {
+    let mut entries = Vec::new();
+
+    for (i, chunk) in data.chunks_exact(ENTRY_LEN).take(MAX_TABLE_LEN / ENTRY_LEN).enumerate() {
+        if chunk[..2] == MD5_MAGIC {
+            let digest = Md5::digest(&data[..i * ENTRY_LEN]);
+            if digest.as_slice() != &chunk[16..32] {
+                return Err("Partition table MD5 mismatch".into());
+            }
+            break;
+        }
+        if chunk.iter().all(|b| *b == 0xFF) {
+            break;
+        }
+        if chunk[..2] != ENTRY_MAGIC {
+            return Err(format!("Entry {} has no partition magic", i));
+        }
+
+        let type_id = chunk[2];
+        let subtype_id = chunk[3];
+        let flags = u32_at(chunk, 28);
+        let name_end = chunk[12..28].iter().position(|b| *b == 0).unwrap_or(MAX_NAME_LEN);
+
+        entries.push(PartitionEntry {
+            name: String::from_utf8_lossy(&chunk[12..12 + name_end]).to_string(),
+            partition_type: type_name(type_id),
+            subtype: subtype_name(type_id, subtype_id),
+            offset: u32_at(chunk, 4),
+            size: u32_at(chunk, 8),
+            encrypted: flags & FLAG_ENCRYPTED != 0,
+            readonly: flags & FLAG_READONLY != 0,
+        });
+    }
+
+    if entries.is_empty() {
+        return Err("No partition entries found".into());
+    }
+    Ok(entries)
+}
+
+/// Encodes the table the way `gen_esp32part.py` does: entries, an MD5
+/// entry, then 0xFF padding up to 0xC00 bytes.
+pub fn to_binary(entries: &[PartitionEntry]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(MAX_TABLE_LEN);
+
+    for e in entries {
+        let type_id = type_id(&e.partition_type).ok_or_else(|| format!("{}: unknown type '{}'", e.name, e.partition_type))?;
+        let subtype_id = subtype_id(type_id, &e.subtype).ok_or_else(|| format!("{}: unknown subtype '{}'", e.name, e.subtype))?;
+        if e.name.len() > MAX_NAME_LEN {
+            return Err(format!("{}: name longer than {} bytes", e.name, MAX_NAME_LEN));
+        }
+
+        let mut name = [0u8; MAX_NAME_LEN];
+        name[..e.name.len()].copy_from_slice(e.name.as_bytes());
+        let flags = if e.encrypted { FLAG_ENCRYPTED } else { 0 } | if e.readonly { FLAG_READONLY } else { 0 };
+
+        out.extend_from_slice(&ENTRY_MAGIC);
+        out.push(type_id);
+        out.push(subtype_id);
+        out.extend_from_slice(&e.offset.to_le_bytes());
+        out.extend_from_slice(&e.size.to_le_bytes());
+        out.extend_from_slice(&name);
+        out.extend_from_slice(&flags.to_le_bytes());
+    }
+
+    let digest = Md5::digest(&out);
+    out.extend_from_slice(&MD5_MAGIC);
+    out.extend_from_slice(&[0xFF; 14]);
+    out.extend_from_slice(digest.as_slice());
+
+    if out.len() > MAX_TABLE_LEN {
+        return Err("Too many partitions for one table".into());
+    }
+    out.resize(MAX_TABLE_LEN, 0xFF);
+    Ok(out)
+}
+
+/// Everything `gen_esp32part.py` or the bootloader would reject, plus the
+/// OTA/NVS layouts that build but can't work.
+pub fn validate(entries: &[PartitionEntry], table_offset: u32, flash_size: Option<u32>) -> Vec<PartitionIssue> {
+    let mut issues = Vec::new();
+    let mut error = |p: Option<&str>, msg: String| issues.push(issue(IssueSeverity::Error, p, msg));
+
+    let table_end = table_offset as u64 + TABLE_SECTOR as u64;
+
+    for e in entries {
+        let name = Some(e.name.as_str());
+        let type_id = match type_id(&e.partition_type) {
+            Some(t) => t,
+            None => {
+                error(name, format!("Unknown type '{}'", e.partition_type));
+                continue;
+            }
+        };
+        if subtype_id(type_id, &e.subtype).is_none() {
+            error(name, format!("Unknown subtype '{}' for type {}", e.subtype, e.partition_type));
+        }
+        if e.name.is_empty() || e.name.len() > MAX_NAME_LEN {
+            error(name, format!("Name must be 1 to {} characters", MAX_NAME_LEN));
+        }
+        if e.size == 0 {
+            error(name, "Size is zero".into());
+        }
+
+        let align = if type_id == 0x00 { APP_ALIGN } else { DATA_ALIGN };
+        if e.offset % align != 0 {
+            error(name, format!("Offset {:#x} is not aligned to {:#x}", e.offset, align));
+        }
+        if e.encrypted && e.size % DATA_ALIGN != 0 {
+            error(name, format!("Encrypted partitions need a size that is a multiple of {:#x}", DATA_ALIGN));
+        }
+        let end = e.offset as u64 + e.size as u64;
+        if (e.offset as u64) < table_end {
+            error(name, format!("Starts at {:#x}, inside the bootloader or partition table (ends {:#x})", e.offset, table_end));
+        }
+        if end > u32::MAX as u64 + 1 {
+            error(name, format!("Ends at {:#x}, past the 4 GB address space", end));
+        } else if let Some(flash) = flash_size {
+            if end > flash as u64 {
+                error(name, format!("Ends at {:#x}, past the {} flash", end, format_size(flash)));
+            }
+        }
+    }
+
+    let mut sorted: Vec<&PartitionEntry> = entries.iter().collect();
+    sorted.sort_by_key(|e| e.offset);
+    for pair in sorted.windows(2) {
+        if pair[0].offset as u64 + pair[0].size as u64 > pair[1].offset as u64 {
+            error(Some(&pair[1].name), format!("Overlaps '{}'", pair[0].name));
+        }
+    }
+
+    for (i, e) in entries.iter().enumerate() {
+        if entries[..i].iter().any(|o| o.name == e.name) {
+            error(Some(&e.name), "Duplicate partition name".into());
+        }
+    }
+
+    let is = |e: &&PartitionEntry, t: &str, s: &str| e.partition_type == t && e.subtype == s;
+    let ota_apps = entries.iter().filter(|e| e.partition_type == "app" && e.subtype.starts_with("ota_")).count();
+    let otadata: Vec<&PartitionEntry> = entries.iter().filter(|e| is(e, "data", "ota")).collect();
+
+    if ota_apps > 0 && otadata.is_empty() {
+        error(None, "OTA app partitions need an 'otadata' (data, ota) partition".into());
+    }
+    if otadata.len() > 1 {
+        error(None, "Only one otadata partition is allowed".into());
+    }
+    if let Some(od) = otadata.first() {
+        if od.size != 0x2000 {
+            error(Some(&od.name), "otadata must be exactly 0x2000 bytes".into());
+        }
+    }
+    if !entries.iter().any(|e| e.partition_type == "app") {
+        error(None, "No app partition".into());
+    }
+
+    if !entries.iter().any(|e| is(&e, "data", "nvs")) {
+        issues.push(issue(IssueSeverity::Warning, None, "No NVS partition; Wi-Fi and nvs_flash_init() need one".into()));
+    }
+    for e in entries.iter().filter(|e| is(e, "data", "nvs") && e.size < 0x3000) {
+        issues.push(issue(IssueSeverity::Warning, Some(&e.name), "NVS partitions should be at least 0x3000 bytes".into()));
+    }
+    if ota_apps == 1 {
+        issues.push(issue(IssueSeverity::Warning, None, "Only one OTA slot; OTA updates need at least ota_0 and ota_1".into()));
+    }
+
+    issues
+}
+
+/// "4MB" / "16MB" as written to `CONFIG_ESPTOOLPY_FLASHSIZE`.
+pub fn parse_flash_size(value: &str) -> Option<u32> {
+    let mb: u32 = value.trim().trim_end_matches("MB").parse().ok()?;
+    mb.checked_mul(1024 * 1024)
+}
+
+fn issue(severity: IssueSeverity, partition: Option<&str>, message: String) -> PartitionIssue {
+    PartitionIssue {
+        severity,
+        partition: partition.map(String::from),
+        message,
+    }
+}
+
+fn type_id(name: &str) -> Option<u8> {
+    TYPES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, id)| *id)
+        .or_else(|| parse_number(name).and_then(|n| u8::try_from(n).ok()))
+}
+
+fn type_name(id: u8) -> String {
+    TYPES
+        .iter()
+        .find(|(_, t)| *t == id)
+        .map(|(n, _)| n.to_string())
+        .unwrap_or_else(|| format!("{:#04x}", id))
+}
+
+fn subtype_id(type_id: u8, name: &str) -> Option<u8> {
+    if let Some(n) = parse_number(name) {
+        return u8::try_from(n).ok();
+    }
+    match type_id {
+        0x00 => {
+            if let Some(slot) = name.strip_prefix("ota_").and_then(|n| n.parse::<u8>().ok()) {
+                return (slot < 16).then_some(0x10 + slot);
+            }
+            APP_SUBTYPES.iter().find(|(n, _)| *n == name).map(|(_, id)| *id)
+        }
+        0x01 => DATA_SUBTYPES.iter().find(|(n, _)| *n == name).map(|(_, id)| *id),
+        _ => None,
+    }
+}
+
+fn subtype_name(type_id: u8, id: u8) -> String {
+    let known = match type_id {
+        0x00 if (0x10..0x20).contains(&id) => return format!("ota_{}", id - 0x10),
+        0x00 => APP_SUBTYPES.iter().find(|(_, s)| *s == id),
+        0x01 => DATA_SUBTYPES.iter().find(|(_, s)| *s == id),
+        _ => None,
+    };
+    known
+        .map(|(n, _)| n.to_string())
+        .unwrap_or_else(|| format!("{:#04x}", id))
+}
+
+/// `0x1000`, `4096`, `4K`, `1M`.
+fn parse_number(s: &str) -> Option<u32> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        return u32::from_str_radix(hex, 16).ok();
+    }
+    let (digits, mult) = match s.chars().last()? {
+        'k' | 'K' => (&s[..s.len() - 1], 1024),
+        'm' | 'M' => (&s[..s.len() - 1], 1024 * 1024),
+        _ => (s, 1),
+    };
+    digits.trim().parse::<u32>().ok()?.checked_mul(mult)
+}
+
+fn format_size(size: u32) -> String {
+    if size % (1024 * 1024) == 0 {
+        format!("{}M", size / (1024 * 1024))
+    } else if size % 1024 == 0 {
+        format!("{}K", size / 1024)
+    } else {
+        format!("{:#x}", size)
+    }
+}
+
+/// `None` if the aligned value doesn't fit in 32 bits.
+fn align_up(value: u32, align: u32) -> Option<u32> {
+    Some(value.checked_add(align - 1)? / align * align)
+}
+
+fn u32_at(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn fixture(name: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/partitions")
+            .join(name);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    fn entry(name: &str, partition_type: &str, subtype: &str, offset: u32, size: u32) -> PartitionEntry {
+        PartitionEntry {
+            name: name.into(),
+            partition_type: partition_type.into(),
+            subtype: subtype.into(),
+            offset,
+            size,
+            encrypted: false,
+            readonly: false,
+        }
+    }
+
+    fn errors(issues: &[PartitionIssue]) -> Vec<&str> {
+        issues
+            .iter()
+            .filter(|i| i.severity == IssueSeverity::Error)
+            .map(|i| i.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn fills_in_aligned_offsets() {
+        let entries = parse_csv(&fixture("ota.csv"), DEFAULT_TABLE_OFFSET).unwrap();
+        let offsets: Vec<(&str, u32, u32)> = entries.iter().map(|e| (e.name.as_str(), e.offset, e.size)).collect();
+
+        assert_eq!(
+            offsets,
+            vec![
+                ("nvs", 0x9000, 0x4000),
+                ("otadata", 0xd000, 0x2000),
+                ("phy_init", 0xf000, 0x1000),
+                ("factory", 0x10000, 0x100000),
+                ("ota_0", 0x110000, 0x100000),
+                ("ota_1", 0x210000, 0x100000),
+                ("nvs_key", 0x310000, 0x1000),
+                ("storage", 0x311000, 0x70000),
+            ]
+        );
+        assert!(entries[6].encrypted);
+        assert!(validate(&entries, DEFAULT_TABLE_OFFSET, Some(0x400000)).is_empty());
+    }
+
+    #[test]
+    fn csv_round_trips() {
+        let entries = parse_csv(&fixture("ota.csv"), DEFAULT_TABLE_OFFSET).unwrap();
+        let again = parse_csv(&to_csv(&entries), DEFAULT_TABLE_OFFSET).unwrap();
+        assert_eq!(entries, again);
+    }
+
+    #[test]
+    fn binary_round_trips() {
+        let entries = parse_csv(&fixture("ota.csv"), DEFAULT_TABLE_OFFSET).unwrap();
+        let bin = to_binary(&entries).unwrap();
+
+        assert_eq!(bin.len(), MAX_TABLE_LEN);
+        assert_eq!(&bin[..4], &[0xAA, 0x50, 0x01, 0x02]);
+        assert_eq!(u32_at(&bin, 4), 0x9000);
+        assert_eq!(&bin[entries.len() * ENTRY_LEN..][..2], &MD5_MAGIC);
+        assert_eq!(parse_binary(&bin).unwrap(), entries);
+    }
+
+    #[test]
+    fn binary_with_bad_md5_is_rejected() {
+        let entries = parse_csv(&fixture("ota.csv"), DEFAULT_TABLE_OFFSET).unwrap();
+        let mut bin = to_binary(&entries).unwrap();
+        bin[8] ^= 0x01;
+
+        assert!(parse_binary(&bin).unwrap_err().contains("MD5"));
+    }
+
+    #[test]
+    fn reports_overlaps_and_duplicates() {
+        let entries = vec![
+            entry("nvs", "data", "nvs", 0x9000, 0x6000),
+            entry("phy_init", "data", "phy", 0xe000, 0x1000),
+            entry("factory", "app", "factory", 0x10000, 0x100000),
+            entry("factory", "app", "ota_0", 0x110000, 0x100000),
+        ];
+        let issues = validate(&entries, DEFAULT_TABLE_OFFSET, Some(0x400000));
+        let errors = errors(&issues);
+
+        assert!(errors.contains(&"Overlaps 'nvs'"));
+        assert!(errors.contains(&"Duplicate partition name"));
+        assert!(errors.iter().any(|m| m.contains("otadata")));
+    }
+
+    #[test]
+    fn reports_misaligned_and_oversized_entries() {
+        let entries = vec![
+            entry("nvs", "data", "nvs", 0x9000, 0x6000),
+            entry("factory", "app", "factory", 0x18000, 0x400000),
+        ];
+        let issues = validate(&entries, DEFAULT_TABLE_OFFSET, Some(0x400000));
+        let errors = errors(&issues);
+
+        assert!(errors.iter().any(|m| m.contains("not aligned")));
+        assert!(errors.iter().any(|m| m.contains("past the 4M flash")));
+    }
+
+    #[test]
+    fn entries_past_4gb_are_errors_not_panics() {
+        let entries = vec![
+            entry("nvs", "data", "nvs", 0x9000, 0x6000),
+            entry("huge", "app", "factory", 0xFFFF0000, 0x20000),
+        ];
+        let issues = validate(&entries, DEFAULT_TABLE_OFFSET, None);
+        assert!(errors(&issues).iter().any(|m| m.contains("4 GB")));
+
+        let err = parse_csv("huge, data, nvs, 0xFFFFF000, 0x2000", DEFAULT_TABLE_OFFSET).unwrap_err();
+        assert!(err.contains("4 GB"));
+
+        let err = parse_csv("last, data, nvs, 0xFFFF0000, 0x1000\nnext, app, factory, , 1M", DEFAULT_TABLE_OFFSET).unwrap_err();
+        assert!(err.starts_with("Line 2"));
+    }
+}
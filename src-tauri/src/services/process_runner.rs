@@ -0,0 +1,499 @@
+use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::utils::process::{escalate_kill, forget_process_group, kill_process_group};
+
+/// After the child exits, how long to keep draining output that is still
+/// in flight before giving up on the readers.
+const DRAIN_GRACE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+    /// stdout and stderr merged by the terminal, in the order they were written.
+    Pty,
+}
+
+/// A piece of output as the child wrote it, ANSI sequences and `\r`
+/// progress updates included. `seq` orders chunks across streams.
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputChunk {
+    pub seq: u64,
+    pub stream: OutputStream,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExitInfo {
+    pub code: Option<i32>,
+    pub success: bool,
+    pub timed_out: bool,
+}
+
+/// What to run. Built like `std::process::Command`:
+/// `RunOptions::new("idf.py").arg("build").cwd(&project).pty(true)`.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub env: Vec<(String, String)>,
+    pub timeout: Option<Duration>,
+    pub pty: bool,
+    pub pty_size: Option<(u16, u16)>,
+}
+
+impl RunOptions {
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn cwd(mut self, dir: impl AsRef<Path>) -> Self {
+        self.cwd = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn envs<I>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        self.env.extend(vars);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Run inside a pseudo terminal, so tools keep their colours and
+    /// progress bars. stdout and stderr then arrive merged as `Pty`.
+    pub fn pty(mut self, pty: bool) -> Self {
+        self.pty = pty;
+        self
+    }
+
+    pub fn pty_size(mut self, rows: u16, cols: u16) -> Self {
+        self.pty_size = Some((rows, cols));
+        self
+    }
+}
+
+enum ChildHandle {
+    Pipe(std::process::Child),
+    Pty(Box<dyn portable_pty::Child + Send + Sync>),
+}
+
+/// A started process. Its output is only delivered through `wait`, which
+/// must be called to reap it.
+pub struct RunningProcess {
+    child: ChildHandle,
+    pid: Option<u32>,
+    rx: Receiver<(OutputStream, Vec<u8>)>,
+    timeout: Option<Duration>,
+    /// Dropping the master hangs up the pty, so it lives as long as we do
+    /// unless a caller takes it over.
+    master: Option<Box<dyn MasterPty + Send>>,
+    writer: Option<Box<dyn std::io::Write + Send>>,
+}
+
+pub fn spawn(opts: RunOptions) -> Result<RunningProcess, String> {
+    if opts.pty {
+        spawn_pty(opts)
+    } else {
+        spawn_pipe(opts)
+    }
+}
+
+/// Runs to completion and returns the exit info together with everything the
+/// process printed, for callers that don't stream.
+pub fn run_collect(opts: RunOptions) -> Result<(ExitInfo, String), String> {
+    let mut output = String::new();
+    let exit = spawn(opts)?.wait(|chunk| output.push_str(&chunk.data))?;
+    Ok((exit, output))
+}
+
+fn spawn_pipe(opts: RunOptions) -> Result<RunningProcess, String> {
+    let mut cmd = Command::new(&opts.program);
+    cmd.args(&opts.args)
+        .envs(opts.env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(dir) = &opts.cwd {
+        cmd.current_dir(dir);
+    }
+
+    // own process group, so a kill takes the whole tree down
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", opts.program, e))?;
+
+    let (tx, rx) = mpsc::channel();
+    if let Some(out) = child.stdout.take() {
+        spawn_reader(out, OutputStream::Stdout, tx.clone());
+    }
+    if let Some(err) = child.stderr.take() {
+        spawn_reader(err, OutputStream::Stderr, tx);
+    }
+
+    Ok(RunningProcess {
+        pid: Some(child.id()),
+        child: ChildHandle::Pipe(child),
+        rx,
+        timeout: opts.timeout,
+        master: None,
+        writer: None,
+    })
+}
+
+fn spawn_pty(opts: RunOptions) -> Result<RunningProcess, String> {
+    let (rows, cols) = opts.pty_size.unwrap_or((24, 120));
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(|e| format!("Failed to open pty: {}", e))?;
+
+    let mut cmd = CommandBuilder::new(&opts.program);
+    cmd.args(&opts.args);
+    for (k, v) in &opts.env {
+        cmd.env(k, v);
+    }
+    if let Some(dir) = &opts.cwd {
+        cmd.cwd(dir);
+    }
+
+    // the child is a session leader (setsid), so its pid is also its pgid
+    let child = pair
+        .slave
+        .spawn_command(cmd)
+        .map_err(|e| format!("Failed to start {}: {}", opts.program, e))?;
+    // keep no slave fd open here, otherwise the reader never sees EOF
+    drop(pair.slave);
+
+    let reader = pair.master.try_clone_reader().map_err(|e| e.to_string())?;
+    let writer = pair.master.take_writer().map_err(|e| e.to_string())?;
+
+    let (tx, rx) = mpsc::channel();
+    spawn_reader(reader, OutputStream::Pty, tx);
+
+    Ok(RunningProcess {
+        pid: child.process_id(),
+        child: ChildHandle::Pty(child),
+        rx,
+        timeout: opts.timeout,
+        master: Some(pair.master),
+        writer: Some(writer),
+    })
+}
+
+fn spawn_reader<R: Read + Send + 'static>(
+    mut reader: R,
+    stream: OutputStream,
+    tx: Sender<(OutputStream, Vec<u8>)>,
+) {
+    thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        loop {
+            match reader.read(&mut buf) {
+                // a pty master reports EIO instead of EOF once the child is gone
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if tx.send((stream, buf[..n].to_vec())).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+impl RunningProcess {
+    /// Process group id to hand to `kill_process_group`.
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// The pty's input side (pty mode only). Can be taken once.
+    pub fn take_writer(&mut self) -> Option<Box<dyn std::io::Write + Send>> {
+        self.writer.take()
+    }
+
+    /// The pty master, for resizing (pty mode only). Whoever takes it must
+    /// keep it alive for as long as the process runs.
+    pub fn take_master(&mut self) -> Option<Box<dyn MasterPty + Send>> {
+        self.master.take()
+    }
+
+    /// Delivers output chunks in order until the process exits and its output
+    /// is drained. Kills the process group if the timeout runs out.
+    pub fn wait(mut self, mut on_output: impl FnMut(OutputChunk)) -> Result<ExitInfo, String> {
+        let started = Instant::now();
+        let mut decoders = [Utf8Decoder::default(), Utf8Decoder::default(), Utf8Decoder::default()];
+        let mut seq = 0u64;
+        let mut timed_out = false;
+        let mut exited_at: Option<Instant> = None;
+
+        let mut emit = |stream: OutputStream, data: String| {
+            if !data.is_empty() {
+                on_output(OutputChunk { seq, stream, data });
+                seq += 1;
+            }
+        };
+
+        // the child closing its output doesn't mean it exited
+        let mut closed = false;
+        loop {
+            if closed {
+                thread::sleep(Duration::from_millis(100));
+            } else {
+                match self.rx.recv_timeout(Duration::from_millis(100)) {
+                    // a chatty child mustn't starve the timeout and exit checks
+                    Ok((stream, bytes)) => {
+                        let data = decoders[stream as usize].push(&bytes);
+                        emit(stream, data);
+                    }
+                    Err(RecvTimeoutError::Disconnected) => closed = true,
+                    Err(RecvTimeoutError::Timeout) => {}
+                }
+            }
+
+            if let (Some(limit), false) = (self.timeout, timed_out) {
+                if started.elapsed() > limit {
+                    if let Some(pid) = self.pid {
+                        let _ = kill_process_group(pid);
+                    }
+                    timed_out = true;
+                }
+            }
+
+            // a background grandchild can hold the output open forever
+            match exited_at {
+                Some(t) if closed || t.elapsed() > DRAIN_GRACE => break,
+                Some(_) => {}
+                None => {
+                    if self.try_wait()? {
+                        if closed {
+                            break;
+                        }
+                        exited_at = Some(Instant::now());
+                    }
+                }
+            }
+        }
+
+        if let Some(pid) = self.pid {
+            forget_process_group(pid);
+        }
+
+        for (i, stream) in [OutputStream::Stdout, OutputStream::Stderr, OutputStream::Pty].into_iter().enumerate() {
+            let rest = decoders[i].finish();
+            emit(stream, rest);
+        }
+
+        let (code, success) = match &mut self.child {
+            ChildHandle::Pipe(c) => {
+                let status = c.wait().map_err(|e| e.to_string())?;
+                (status.code(), status.success())
+            }
+            ChildHandle::Pty(c) => {
+                let status = c.wait().map_err(|e| e.to_string())?;
+                (Some(status.exit_code() as i32), status.success())
+            }
+        };
+
+        Ok(ExitInfo {
+            code,
+            success: success && !timed_out,
+            timed_out,
+        })
+    }
+
+    /// Reaps the child if it exited, first escalating a pending kill while
+    /// its pid still can't have been reused.
+    fn try_wait(&mut self) -> Result<bool, String> {
+        if let Some(pid) = self.pid {
+            escalate_kill(pid);
+        }
+        match &mut self.child {
+            ChildHandle::Pipe(c) => c.try_wait().map(|s| s.is_some()).map_err(|e| e.to_string()),
+            ChildHandle::Pty(c) => c.try_wait().map(|s| s.is_some()).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Turns raw chunks into `String`s without splitting multi-byte characters
+/// that straddle two reads.
+#[derive(Default)]
+struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    fn push(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let mut out = String::new();
+
+        loop {
+            match std::str::from_utf8(&self.pending) {
+                Ok(s) => {
+                    out.push_str(s);
+                    self.pending.clear();
+                    break;
+                }
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    out.push_str(&String::from_utf8_lossy(&self.pending[..valid]));
+                    match e.error_len() {
+                        Some(bad) => {
+                            out.push('\u{FFFD}');
+                            self.pending.drain(..valid + bad);
+                        }
+                        // incomplete sequence at the end, wait for the next read
+                        None => {
+                            self.pending.drain(..valid);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+    fn finish(&mut self) -> String {
+        let rest = String::from_utf8_lossy(&self.pending).to_string();
+        self.pending.clear();
+        rest
+    }
+}
+
+/// Cuts a chunk stream into display lines. A bare `\r` (progress bars)
+/// restarts the current line instead of ending it.
+#[derive(Default)]
+pub struct LineSplitter {
+    current: String,
+    pending_cr: bool,
+}
+
+impl LineSplitter {
+    pub fn push(&mut self, data: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for c in data.chars() {
+            // `\r\n` may be split across two chunks, so `\r` is only
+            // resolved once the next character is known
+            if c == '\n' {
+                self.pending_cr = false;
+                lines.push(std::mem::take(&mut self.current));
+                continue;
+            }
+            if self.pending_cr {
+                self.pending_cr = false;
+                self.current.clear();
+            }
+            if c == '\r' {
+                self.pending_cr = true;
+            } else {
+                self.current.push(c);
+            }
+        }
+
+        lines
+    }
+
+    pub fn finish(&mut self) -> Option<String> {
+        self.pending_cr = false;
+        if self.current.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.current))
+        }
+    }
+}
+
+/// Drops ANSI CSI sequences (colours, cursor moves) from a line.
+pub fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' && chars.peek() == Some(&'[') {
+            chars.next();
+            // CSI sequence ends at the first byte in 0x40..=0x7e
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_out_a_child_that_keeps_printing() {
+        let opts = RunOptions::new("sh")
+            .arg("-c")
+            .arg("while :; do echo tick; sleep 0.02; done")
+            .timeout(Duration::from_millis(300));
+
+        let started = Instant::now();
+        let (exit, output) = run_collect(opts).unwrap();
+
+        assert!(exit.timed_out);
+        assert!(!exit.success);
+        assert!(output.contains("tick"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn kills_a_child_that_ignores_sigterm() {
+        let opts = RunOptions::new("sh")
+            .arg("-c")
+            .arg("trap '' TERM; exec >/dev/null 2>&1; while :; do sleep 0.05; done")
+            .timeout(Duration::from_millis(200));
+
+        let (exit, _) = run_collect(opts).unwrap();
+
+        assert!(exit.timed_out);
+    }
+}
@@ -0,0 +1,46 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::project::ProjectSettings;
+
+/// Folder inside every project where the editor keeps its own files.
+pub const EDITOR_DIR: &str = ".veditor";
+
+pub fn editor_dir(project_path: &Path) -> PathBuf {
+    project_path.join(EDITOR_DIR)
+}
+
+/// Folders under [`EDITOR_DIR`] the editor regenerates or that only make
+/// sense on this machine; project.json and profiles/ stay versioned.
+const GENERATED_DIRS: &[&str] = &["history/", "trash/", "sketch/", "kconfig/"];
+
+/// Writes `.veditor/.gitignore` for the generated folders, unless the
+/// project already has one. Best effort: a missing ignore file must not
+/// stop the operation creating the folder.
+pub fn ensure_gitignore(project_path: &Path) {
+    let file = editor_dir(project_path).join(".gitignore");
+    if file.exists() {
+        return;
+    }
+    let data: String = GENERATED_DIRS.iter().map(|dir| format!("{}\n", dir)).collect();
+    if let Err(e) = fs::create_dir_all(editor_dir(project_path)).and_then(|_| fs::write(&file, data)) {
+        eprintln!("Failed to write {}: {}", file.display(), e);
+    }
+}
+
+fn settings_file(project_path: &Path) -> PathBuf {
+    editor_dir(project_path).join("project.json")
+}
+
+pub fn load(project_path: &Path) -> ProjectSettings {
+    fs::read_to_string(settings_file(project_path))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(project_path: &Path, settings: &ProjectSettings) -> Result<(), String> {
+    fs::create_dir_all(editor_dir(project_path)).map_err(|e| e.to_string())?;
+    let data = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    fs::write(settings_file(project_path), data).map_err(|e| e.to_string())
+}
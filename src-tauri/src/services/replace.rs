@@ -0,0 +1,231 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::models::search::{FileReplacement, ReplaceEdit, ReplacePreview, ReplaceResult, SearchOptions};
+use crate::services::search;
+use crate::utils::fs::{content_hash, write_atomic};
+
+/// Every edit replacing the matches of `options` with `replacement` in the
+/// project's files. With `options.regex`, `$1`/`${name}` in the
+/// replacement expand to capture groups. Nothing is written.
+pub fn preview(root: &Path, options: &SearchOptions, replacement: &str) -> Result<ReplacePreview, String> {
+    let regex = search::build_regex(options)?;
+    let max_results = options.max_results.unwrap_or(search::DEFAULT_MAX_RESULTS);
+
+    let mut preview = ReplacePreview {
+        files: Vec::new(),
+        edits: 0,
+        truncated: false,
+    };
+
+    for path in search::project_files(root, &options.include, &options.exclude)? {
+        let data = match search::read_text_bytes(&path) {
+            Some(data) => data,
+            None => continue,
+        };
+        let hash = content_hash(&data);
+        // files that aren't valid UTF-8 can't be rewritten without damage
+        let text = match String::from_utf8(data) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+
+        let mut edits = file_edits(&text, &regex, replacement, options.regex);
+        let remaining = max_results - preview.edits;
+        if edits.len() > remaining {
+            edits.truncate(remaining);
+            preview.truncated = true;
+        }
+
+        if !edits.is_empty() {
+            preview.edits += edits.len();
+            preview.files.push(FileReplacement {
+                path: path.to_string_lossy().to_string(),
+                hash,
+                edits,
+            });
+        }
+        if preview.truncated {
+            break;
+        }
+    }
+
+    Ok(preview)
+}
+
+fn file_edits(text: &str, regex: &Regex, replacement: &str, expand: bool) -> Vec<ReplaceEdit> {
+    let mut edits = Vec::new();
+    let mut offset = 0;
+
+    for (i, raw) in text.split_inclusive('\n').enumerate() {
+        let line = raw.strip_suffix('\n').unwrap_or(raw);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        for caps in regex.captures_iter(line) {
+            let m = caps.get(0).expect("group 0 is the whole match");
+            if m.as_str().is_empty() {
+                continue;
+            }
+
+            let replacement = if expand {
+                let mut expanded = String::new();
+                caps.expand(replacement, &mut expanded);
+                expanded
+            } else {
+                replacement.to_string()
+            };
+            edits.push(ReplaceEdit {
+                line: i + 1,
+                column: line[..m.start()].chars().count() + 1,
+                start: offset + m.start(),
+                end: offset + m.end(),
+                before: line.to_string(),
+                after: format!("{}{}{}", &line[..m.start()], replacement, &line[m.end()..]),
+                replacement,
+            });
+        }
+        offset += raw.len();
+    }
+    edits
+}
+
+/// Applies the selected edits of a preview. Files whose content no longer
+/// matches the preview's hash are skipped and reported; the others are
+/// all rewritten or, if one write fails, all restored.
+pub fn apply(root: &Path, files: &[FileReplacement]) -> Result<ReplaceResult, String> {
+    let root = root.canonicalize().map_err(|e| e.to_string())?;
+
+    let mut result = ReplaceResult {
+        files_changed: Vec::new(),
+        edits_applied: 0,
+        rejected: Vec::new(),
+    };
+    // (path, original content, new content)
+    let mut pending: Vec<(PathBuf, Vec<u8>, String)> = Vec::new();
+
+    for file in files.iter().filter(|f| !f.edits.is_empty()) {
+        let path = PathBuf::from(&file.path);
+        let inside = path.canonicalize().is_ok_and(|p| p.starts_with(&root));
+        if !inside {
+            return Err(format!("{} is not in the project", file.path));
+        }
+
+        let data = fs::read(&path).map_err(|e| format!("{}: {}", file.path, e))?;
+        if content_hash(&data) != file.hash {
+            result.rejected.push(file.path.clone());
+            continue;
+        }
+        let text = std::str::from_utf8(&data).map_err(|_| format!("{} is not UTF-8 text", file.path))?;
+        let new_text = apply_edits(text, &file.edits).map_err(|e| format!("{}: {}", file.path, e))?;
+
+        result.edits_applied += file.edits.len();
+        pending.push((path, data, new_text));
+    }
+
+    for (i, (path, _, new_text)) in pending.iter().enumerate() {
+        if let Err(e) = write_atomic(path, new_text.as_bytes()) {
+            for (path, original, _) in &pending[..i] {
+                let _ = write_atomic(path, original);
+            }
+            return Err(format!("{} (no files were changed)", e));
+        }
+    }
+
+    result.files_changed = pending
+        .into_iter()
+        .map(|(path, _, _)| path.to_string_lossy().to_string())
+        .collect();
+    Ok(result)
+}
+
+fn apply_edits(text: &str, edits: &[ReplaceEdit]) -> Result<String, String> {
+    let mut edits: Vec<&ReplaceEdit> = edits.iter().collect();
+    edits.sort_by_key(|e| e.start);
+
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for edit in edits {
+        let valid = edit.start >= last
+            && edit.start <= edit.end
+            && edit.end <= text.len()
+            && text.is_char_boundary(edit.start)
+            && text.is_char_boundary(edit.end);
+        if !valid {
+            return Err("edits overlap or don't fit the file".into());
+        }
+        out.push_str(&text[last..edit.start]);
+        out.push_str(&edit.replacement);
+        last = edit.end;
+    }
+    out.push_str(&text[last..]);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(start: usize, end: usize, replacement: &str) -> ReplaceEdit {
+        ReplaceEdit {
+            line: 1,
+            column: start + 1,
+            start,
+            end,
+            before: String::new(),
+            after: String::new(),
+            replacement: replacement.into(),
+        }
+    }
+
+    #[test]
+    fn edits_point_into_the_original_text() {
+        let text = "let a = foo;\r\nfoo(foo)\n// é foo\n";
+        let edits = file_edits(text, &Regex::new("foo").unwrap(), "bar", false);
+
+        let spots: Vec<(usize, usize, usize)> = edits.iter().map(|e| (e.line, e.column, e.start)).collect();
+        assert_eq!(spots, vec![(1, 9, 8), (2, 1, 14), (2, 5, 18), (3, 6, 29)]);
+        assert!(edits.iter().all(|e| &text[e.start..e.end] == "foo"));
+        assert_eq!(edits[1].before, "foo(foo)");
+        assert_eq!(edits[1].after, "bar(foo)");
+
+        assert_eq!(apply_edits(text, &edits).unwrap(), "let a = bar;\r\nbar(bar)\n// é bar\n");
+    }
+
+    #[test]
+    fn captures_expand_only_in_regex_mode() {
+        let text = "items.len() + names.len()\n";
+        let regex = Regex::new(r"(?P<obj>\w+)\.len\(\)").unwrap();
+
+        let expanded = file_edits(text, &regex, "len(${obj})", true);
+        assert_eq!(apply_edits(text, &expanded).unwrap(), "len(items) + len(names)\n");
+
+        let literal = file_edits(text, &regex, "len($1)", false);
+        assert_eq!(literal[0].replacement, "len($1)");
+    }
+
+    #[test]
+    fn empty_matches_are_skipped() {
+        let edits = file_edits("abc\nxx\n", &Regex::new("x*").unwrap(), "y", true);
+        assert_eq!(edits.len(), 1);
+        assert_eq!((edits[0].line, edits[0].start, edits[0].end), (2, 4, 6));
+    }
+
+    #[test]
+    fn edits_apply_in_offset_order() {
+        let edits = vec![edit(6, 11, "there"), edit(0, 5, "hi")];
+        assert_eq!(apply_edits("hello world", &edits).unwrap(), "hi there");
+    }
+
+    #[test]
+    fn overlapping_or_misplaced_edits_are_rejected() {
+        let text = "hello wörld";
+
+        assert!(apply_edits(text, &[edit(0, 5, "a"), edit(3, 8, "b")]).is_err());
+        assert!(apply_edits(text, &[edit(6, 40, "a")]).is_err());
+        assert!(apply_edits(text, &[edit(5, 4, "a")]).is_err());
+        // inside the two bytes of 'ö'
+        assert!(apply_edits(text, &[edit(8, 9, "o")]).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::Path;
+
+/// Value of `CONFIG_<name>` in an sdkconfig file, with string quotes removed.
+/// `None` if the file or the option is missing (or "is not set").
+pub fn read_value(sdkconfig: &Path, name: &str) -> Option<String> {
+    let text = fs::read_to_string(sdkconfig).ok()?;
+    let key = format!("CONFIG_{}=", name);
+
+    text.lines()
+        .find_map(|l| l.strip_prefix(key.as_str()))
+        .map(|v| v.trim().trim_matches('"').to_string())
+}
+
+/// Sets options in an existing sdkconfig. `Some(v)` writes `CONFIG_<name>=v`
+/// (callers quote strings themselves), `None` writes `# CONFIG_<name> is not
+/// set`. Only touches the file when something changes, so an unchanged
+/// config doesn't force a reconfigure.
+pub fn set_values(sdkconfig: &Path, values: &[(&str, Option<&str>)]) -> Result<(), String> {
+    let text = fs::read_to_string(sdkconfig).map_err(|e| e.to_string())?;
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+
+    for (name, value) in values {
+        let set_prefix = format!("CONFIG_{}=", name);
+        let unset_line = format!("# CONFIG_{} is not set", name);
+        let new_line = match value {
+            Some(v) => format!("{}{}", set_prefix, v),
+            None => unset_line.clone(),
+        };
+
+        match lines.iter().position(|l| l.starts_with(&set_prefix) || *l == unset_line) {
+            Some(i) => lines[i] = new_line,
+            None => lines.push(new_line),
+        }
+    }
+
+    let new_text = lines.join("\n") + "\n";
+    if new_text != text {
+        fs::write(sdkconfig, new_text).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// All options in an sdkconfig (or sdkconfig.defaults) file, keyed without
+/// the `CONFIG_` prefix. "is not set" lines read as `n` and string values
+/// are unquoted.
+pub fn read_all(sdkconfig: &Path) -> Result<Vec<(String, String)>, String> {
+    let text = fs::read_to_string(sdkconfig).map_err(|e| e.to_string())?;
+    let mut values = Vec::new();
+
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line
+            .strip_prefix("# CONFIG_")
+            .and_then(|l| l.strip_suffix(" is not set"))
+        {
+            values.push((name.to_string(), "n".to_string()));
+        } else if let Some((name, value)) = line.strip_prefix("CONFIG_").and_then(|l| l.split_once('=')) {
+            values.push((name.to_string(), unquote(value)));
+        }
+    }
+    Ok(values)
+}
+
+/// Quotes a string value the way kconfig writes it.
+pub fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn unquote(value: &str) -> String {
+    let inner = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(v) => v,
+        None => return value.to_string(),
+    };
+
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
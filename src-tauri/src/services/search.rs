@@ -0,0 +1,145 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use regex::{Regex, RegexBuilder};
+
+use crate::models::search::{SearchMatch, SearchOptions};
+use crate::services::project_settings::EDITOR_DIR;
+
+/// Folders never searched, on top of what .gitignore excludes. The build
+/// output at the project root is skipped too.
+const SKIPPED_DIRS: [&str; 2] = [".git", EDITOR_DIR];
+
+/// Matches reported when the options don't set a limit.
+pub const DEFAULT_MAX_RESULTS: usize = 10_000;
+
+/// Bigger files are most likely generated or binary.
+const MAX_FILE_SIZE: u64 = 8 * 1024 * 1024;
+
+/// The regex for `options`; literal text is escaped, whole words are
+/// wrapped in `\b`.
+pub fn build_regex(options: &SearchOptions) -> Result<Regex, String> {
+    if options.query.is_empty() {
+        return Err("Search text is empty".into());
+    }
+
+    let mut pattern = if options.regex {
+        options.query.clone()
+    } else {
+        regex::escape(&options.query)
+    };
+    if options.whole_word {
+        pattern = format!(r"\b(?:{})\b", pattern);
+    }
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| format!("Invalid search pattern: {}", e))
+}
+
+/// Files under `root` to search, in a stable order. Honours .gitignore
+/// (with or without a git repo) and the include/exclude globs, which are
+/// relative to `root`.
+pub fn project_files(
+    root: &Path,
+    include: &[String],
+    exclude: &[String],
+) -> Result<impl Iterator<Item = PathBuf>, String> {
+    let include = if include.is_empty() { None } else { Some(glob_set(include)?) };
+    let exclude = glob_set(exclude)?;
+    let base = root.to_path_buf();
+
+    let walk = WalkBuilder::new(root)
+        .hidden(false)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            !(is_dir && (SKIPPED_DIRS.contains(&name.as_ref()) || (name == "build" && entry.depth() == 1)))
+        })
+        .build();
+
+    Ok(walk.flatten().filter_map(move |entry| {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            return None;
+        }
+        let relative = entry.path().strip_prefix(&base).ok()?;
+        if exclude.is_match(relative) || include.as_ref().is_some_and(|set| !set.is_match(relative)) {
+            return None;
+        }
+        Some(entry.into_path())
+    }))
+}
+
+/// Like .gitignore: a pattern without `/` (`*.c`, `test`) matches at any
+/// depth, one with a `/` (`main/*.c`, `/sdkconfig`) from the root, and a
+/// folder matches everything inside it.
+pub fn glob_set(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.trim().trim_start_matches("./").trim_end_matches('/');
+        if pattern.is_empty() {
+            continue;
+        }
+        let pattern = match pattern.strip_prefix('/') {
+            Some(anchored) => anchored.to_string(),
+            None if pattern.contains('/') => pattern.to_string(),
+            None => format!("**/{}", pattern),
+        };
+
+        for p in [pattern.clone(), format!("{}/**", pattern)] {
+            let glob = Glob::new(&p).map_err(|e| format!("Invalid glob '{}': {}", pattern, e))?;
+            builder.add(glob);
+        }
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// The contents of `path`, `None` for binary or oversized files.
+pub fn read_text_bytes(path: &Path) -> Option<Vec<u8>> {
+    let size = fs::metadata(path).ok()?.len();
+    if size > MAX_FILE_SIZE {
+        return None;
+    }
+    let data = fs::read(path).ok()?;
+    if data.iter().take(8000).any(|&b| b == 0) {
+        return None;
+    }
+    Some(data)
+}
+
+/// Reads `path` as text, `None` for binary or oversized files.
+pub fn read_text(path: &Path) -> Option<String> {
+    read_text_bytes(path).map(|data| String::from_utf8_lossy(&data).into_owned())
+}
+
+/// Every match of `regex` in `text`, line by line, with `context` lines
+/// around each.
+pub fn find_matches(text: &str, regex: &Regex, context: usize) -> Vec<SearchMatch> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut matches = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        for m in regex.find_iter(line) {
+            if m.as_str().is_empty() {
+                continue;
+            }
+            matches.push(SearchMatch {
+                line: i + 1,
+                column: line[..m.start()].chars().count() + 1,
+                length: m.as_str().chars().count(),
+                text: line.to_string(),
+                before: lines[i.saturating_sub(context)..i].iter().map(|l| l.to_string()).collect(),
+                after: lines[i + 1..(i + 1 + context).min(lines.len())]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+            });
+        }
+    }
+    matches
+}
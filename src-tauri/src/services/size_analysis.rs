@@ -0,0 +1,257 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::size::{MemoryKind, MemoryUsage, SectionSize, SizeDelta, SizeDiff, SizeEntry, SizeReport, SymbolSize};
+use crate::services::elf::{self, SHF_ALLOC, STT_FUNC, STT_OBJECT};
+use crate::services::linker_map::{self, InputSection};
+
+/// How many symbols `SizeReport::largest_symbols` lists.
+const LARGEST_SYMBOLS: usize = 100;
+
+/// Size report for the app ELF and linker map in `dir` (an
+/// `artifacts/build-<n>` folder or `build/`).
+pub fn report(dir: &Path, build_number: Option<u32>) -> Result<SizeReport, String> {
+    let mut report = analyze(dir, build_number)?;
+    report.largest_symbols.truncate(LARGEST_SYMBOLS);
+    Ok(report)
+}
+
+/// Compares two builds. Unlike `report`, every symbol is compared, not just
+/// the largest ones.
+pub fn compare(base_dir: &Path, base_build: u32, dir: &Path, build_number: Option<u32>) -> Result<SizeDiff, String> {
+    let before = analyze(base_dir, Some(base_build))?;
+    let after = analyze(dir, build_number)?;
+
+    let entries = |list: &[SizeEntry]| list.iter().map(|e| (e.name.clone(), e.total)).collect::<Vec<_>>();
+    let memory = |r: &SizeReport| r.memory.iter().map(|m| (format!("{:?}", m.kind), m.used)).collect::<Vec<_>>();
+    let symbols = |r: &SizeReport| r.largest_symbols.iter().map(|s| (s.name.clone(), s.size)).collect::<Vec<_>>();
+
+    Ok(SizeDiff {
+        base_build,
+        build_number,
+        memory: deltas(memory(&before), memory(&after)),
+        components: deltas(entries(&before.components), entries(&after.components)),
+        archives: deltas(entries(&before.archives), entries(&after.archives)),
+        objects: deltas(entries(&before.objects), entries(&after.objects)),
+        symbols: deltas(symbols(&before), symbols(&after)),
+    })
+}
+
+/// Output section name -> memory, after the ESP-IDF linker scripts
+/// (`.iram0.text`, `.dram0.bss`, `.flash.rodata`, `.rtc.data`, ...).
+fn classify(section: &str) -> MemoryKind {
+    if section.starts_with(".iram") {
+        MemoryKind::Iram
+    } else if section.starts_with(".dram") || section == ".noinit" {
+        MemoryKind::Dram
+    } else if section.starts_with(".flash.text") {
+        MemoryKind::FlashCode
+    } else if section.starts_with(".flash") {
+        MemoryKind::FlashData
+    } else if section.starts_with(".rtc") {
+        MemoryKind::Rtc
+    } else if section.starts_with(".ext_ram") {
+        MemoryKind::ExtRam
+    } else {
+        MemoryKind::Other
+    }
+}
+
+fn analyze(dir: &Path, build_number: Option<u32>) -> Result<SizeReport, String> {
+    let elf_path = find_app_elf(dir).ok_or_else(|| format!("No application ELF found in {}", dir.display()))?;
+    let map_path = elf_path.with_extension("map");
+    if !map_path.is_file() {
+        return Err(format!("Linker map {} not found", map_path.display()));
+    }
+
+    let elf = elf::read(&elf_path)?;
+    let map = linker_map::read(&map_path)?;
+
+    // sections that take space on the chip; NOLOAD flash sections don't
+    let occupies = |s: &elf::ElfSection| {
+        let kind = classify(&s.name);
+        s.flags & SHF_ALLOC != 0
+            && s.size > 0
+            && !(s.nobits && matches!(kind, MemoryKind::FlashCode | MemoryKind::FlashData))
+    };
+
+    let mut sections = Vec::new();
+    let mut used: HashMap<MemoryKind, u64> = HashMap::new();
+    let mut regions: HashMap<MemoryKind, HashSet<usize>> = HashMap::new();
+
+    for s in elf.sections.iter().filter(|s| occupies(s)) {
+        let kind = classify(&s.name);
+        *used.entry(kind).or_default() += s.size as u64;
+
+        if let Some(i) = map
+            .regions
+            .iter()
+            .position(|r| (r.origin..r.origin + r.length).contains(&(s.address as u64)))
+        {
+            regions.entry(kind).or_default().insert(i);
+        }
+
+        sections.push(SectionSize {
+            name: s.name.clone(),
+            address: s.address,
+            size: s.size as u64,
+            kind,
+        });
+    }
+
+    let mut memory: Vec<MemoryUsage> = used
+        .into_iter()
+        .map(|(kind, used)| {
+            let mut indices: Vec<usize> = regions.get(&kind).map(|set| set.iter().copied().collect()).unwrap_or_default();
+            indices.sort_unstable();
+            MemoryUsage {
+                kind,
+                used,
+                total: Some(indices.iter().map(|&i| map.regions[i].length).sum()).filter(|_| !indices.is_empty()),
+                regions: indices.iter().map(|&i| map.regions[i].name.clone()).collect(),
+            }
+        })
+        .collect();
+    memory.sort_by_key(|m| m.kind as u8);
+
+    let counted: HashSet<&str> = sections.iter().map(|s| s.name.as_str()).collect();
+    let mut components: HashMap<String, SizeEntry> = HashMap::new();
+    let mut archives: HashMap<String, SizeEntry> = HashMap::new();
+    let mut objects: HashMap<String, SizeEntry> = HashMap::new();
+
+    for input in map.sections.iter().filter(|i| counted.contains(i.output.as_str())) {
+        let kind = classify(&input.output);
+        let archive = input.archive.clone().unwrap_or_else(|| "(exe)".to_string());
+        let object = format!("{}:{}", archive, input.object);
+
+        add(components.entry(component_name(input)).or_default(), kind, input.size);
+        add(archives.entry(archive).or_default(), kind, input.size);
+        add(objects.entry(object).or_default(), kind, input.size);
+    }
+
+    let mut inputs: Vec<&InputSection> = map.sections.iter().collect();
+    inputs.sort_by_key(|i| i.address);
+
+    let mut symbols: Vec<SymbolSize> = elf
+        .symbols
+        .iter()
+        .filter(|s| s.size > 0 && (s.kind == STT_FUNC || s.kind == STT_OBJECT))
+        .filter_map(|s| {
+            let section = elf.sections.get(s.section?).filter(|sec| occupies(sec))?;
+            let input = containing(&inputs, s.value as u64);
+            Some(SymbolSize {
+                name: s.name.clone(),
+                size: s.size as u64,
+                address: s.value,
+                section: section.name.clone(),
+                kind: classify(&section.name),
+                archive: input.and_then(|i| i.archive.clone()),
+                object: input.map(|i| i.object.clone()),
+            })
+        })
+        .collect();
+    symbols.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(SizeReport {
+        build_number,
+        elf: elf_path.to_string_lossy().to_string(),
+        memory,
+        sections,
+        components: sorted(components),
+        archives: sorted(archives),
+        objects: sorted(objects),
+        largest_symbols: symbols,
+    })
+}
+
+/// The app ELF named in project_description.json, or else any ELF that
+/// isn't the bootloader.
+fn find_app_elf(dir: &Path) -> Option<PathBuf> {
+    let named = fs::read_to_string(dir.join("project_description.json"))
+        .ok()
+        .and_then(|d| serde_json::from_str::<serde_json::Value>(&d).ok())
+        .and_then(|d| d["app_elf"].as_str().and_then(|e| Path::new(e).file_name()).map(|n| dir.join(n)))
+        .filter(|p| p.is_file());
+    if named.is_some() {
+        return named;
+    }
+
+    let mut elfs: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("elf"))
+        .filter(|p| p.file_name().and_then(|n| n.to_str()) != Some("bootloader.elf"))
+        .collect();
+    elfs.sort();
+    elfs.into_iter().next()
+}
+
+/// `esp-idf/freertos/libfreertos.a` -> `freertos`; archives from outside the
+/// build (toolchain libs) go by their archive name.
+fn component_name(input: &InputSection) -> String {
+    match &input.archive_path {
+        Some(path) => {
+            let parts: Vec<&str> = path.split(['/', '\\']).collect();
+            match parts.iter().position(|p| *p == "esp-idf") {
+                Some(i) if i + 2 < parts.len() => parts[i + 1].to_string(),
+                _ => input.archive.clone().unwrap_or_default(),
+            }
+        }
+        None => "(exe)".to_string(),
+    }
+}
+
+fn containing<'a>(inputs: &[&'a InputSection], address: u64) -> Option<&'a InputSection> {
+    let idx = inputs.partition_point(|i| i.address <= address);
+    let input = *inputs.get(idx.checked_sub(1)?)?;
+    (address < input.address + input.size).then_some(input)
+}
+
+fn add(entry: &mut SizeEntry, kind: MemoryKind, size: u64) {
+    match kind {
+        MemoryKind::Iram => entry.iram += size,
+        MemoryKind::Dram => entry.dram += size,
+        MemoryKind::FlashCode => entry.flash_code += size,
+        MemoryKind::FlashData => entry.flash_data += size,
+        MemoryKind::Rtc => entry.rtc += size,
+        MemoryKind::ExtRam => entry.ext_ram += size,
+        MemoryKind::Other => entry.other += size,
+    }
+    entry.total += size;
+}
+
+fn sorted(entries: HashMap<String, SizeEntry>) -> Vec<SizeEntry> {
+    let mut list: Vec<SizeEntry> = entries
+        .into_iter()
+        .map(|(name, entry)| SizeEntry { name, ..entry })
+        .collect();
+    list.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.name.cmp(&b.name)));
+    list
+}
+
+/// Changed entries, biggest change first. Entries sharing a name (static
+/// functions in different files) are summed.
+fn deltas(before: Vec<(String, u64)>, after: Vec<(String, u64)>) -> Vec<SizeDelta> {
+    let mut sizes: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for (name, size) in before {
+        sizes.entry(name).or_default().0 += size;
+    }
+    for (name, size) in after {
+        sizes.entry(name).or_default().1 += size;
+    }
+
+    let mut list: Vec<SizeDelta> = sizes
+        .into_iter()
+        .filter(|(_, (b, a))| b != a)
+        .map(|(name, (before, after))| SizeDelta {
+            name,
+            before,
+            after,
+            delta: after as i64 - before as i64,
+        })
+        .collect();
+    list.sort_by(|a, b| b.delta.abs().cmp(&a.delta.abs()).then_with(|| a.name.cmp(&b.name)));
+    list
+}
@@ -0,0 +1,191 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::toolchain::{Toolchain, ToolchainSource};
+use crate::services::project_settings;
+
+/// Finds every ESP-IDF checkout we can use, from `IDF_PATH`, `~/esp/*` and
+/// the Espressif installer's `~/.espressif/idf-env.json`. The `IDF_PATH`
+/// install comes first, the rest are sorted newest version first.
+pub fn discover() -> Vec<Toolchain> {
+    let mut found: Vec<Toolchain> = Vec::new();
+
+    let mut push = |path: PathBuf, python: Option<PathBuf>, source: ToolchainSource| {
+        let path = match path.canonicalize() {
+            Ok(p) => p,
+            Err(_) => return,
+        };
+        if !is_idf_root(&path) || found.iter().any(|t| Path::new(&t.idf_path) == path) {
+            return;
+        }
+        found.push(make_toolchain(&path, python, source));
+    };
+
+    if let Some(p) = std::env::var_os("IDF_PATH") {
+        push(PathBuf::from(p), None, ToolchainSource::IdfPathEnv);
+    }
+
+    let home = match dirs::home_dir() {
+        Some(h) => h,
+        None => return found,
+    };
+
+    for (path, python) in read_idf_env_json(&home.join(".espressif/idf-env.json")) {
+        push(path, python, ToolchainSource::IdfEnvJson);
+    }
+
+    if let Ok(entries) = fs::read_dir(home.join("esp")) {
+        for e in entries.flatten() {
+            let dir = e.path();
+            // ~/esp/esp-idf as well as ~/esp/v5.2/esp-idf
+            push(dir.join("esp-idf"), None, ToolchainSource::EspDir);
+            push(dir, None, ToolchainSource::EspDir);
+        }
+    }
+
+    found.sort_by(|a, b| {
+        let env_first = (b.source == ToolchainSource::IdfPathEnv).cmp(&(a.source == ToolchainSource::IdfPathEnv));
+        env_first.then_with(|| {
+            version_key(b.version.as_deref().unwrap_or("")).cmp(&version_key(a.version.as_deref().unwrap_or("")))
+        })
+    });
+
+    found
+}
+
+/// The toolchain a project builds with: its pinned one if set, otherwise the
+/// first discovered install.
+pub fn for_project(project_path: &Path) -> Result<Toolchain, String> {
+    match project_settings::load(project_path).toolchain_id {
+        Some(id) => find(&id),
+        None => default_toolchain(),
+    }
+}
+
+pub fn default_toolchain() -> Result<Toolchain, String> {
+    discover()
+        .into_iter()
+        .next()
+        .ok_or_else(|| "No ESP-IDF installation found (checked IDF_PATH, ~/esp and ~/.espressif)".into())
+}
+
+pub fn find(id: &str) -> Result<Toolchain, String> {
+    discover()
+        .into_iter()
+        .find(|t| t.id == id)
+        .ok_or_else(|| format!("ESP-IDF toolchain not found: {}", id))
+}
+
+pub fn export_script(toolchain: &Toolchain) -> PathBuf {
+    Path::new(&toolchain.idf_path).join("export.sh")
+}
+
+pub fn idf_py(toolchain: &Toolchain) -> PathBuf {
+    Path::new(&toolchain.idf_path).join("tools/idf.py")
+}
+
+fn is_idf_root(path: &Path) -> bool {
+    path.join("tools/idf.py").is_file()
+}
+
+fn make_toolchain(path: &Path, python: Option<PathBuf>, source: ToolchainSource) -> Toolchain {
+    let version = read_version(path);
+    let python = python
+        .filter(|p| p.exists())
+        .or_else(|| find_python_env(version.as_deref()));
+
+    Toolchain {
+        id: path.to_string_lossy().to_string(),
+        version,
+        idf_path: path.to_string_lossy().to_string(),
+        python: python.map(|p| p.to_string_lossy().to_string()),
+        source,
+    }
+}
+
+/// Reads `IDF_VERSION_MAJOR/MINOR/PATCH` from `tools/cmake/version.cmake`.
+fn read_version(idf_path: &Path) -> Option<String> {
+    let text = fs::read_to_string(idf_path.join("tools/cmake/version.cmake")).ok()?;
+
+    let component = |name: &str| -> Option<String> {
+        let key = format!("set({} ", name);
+        text.lines()
+            .find_map(|l| l.trim().strip_prefix(key.as_str()))
+            .map(|v| v.trim_end_matches(')').trim().to_string())
+    };
+
+    Some(format!(
+        "{}.{}.{}",
+        component("IDF_VERSION_MAJOR")?,
+        component("IDF_VERSION_MINOR")?,
+        component("IDF_VERSION_PATCH").unwrap_or_else(|| "0".into())
+    ))
+}
+
+fn version_key(version: &str) -> Vec<u32> {
+    version
+        .split('.')
+        .filter_map(|p| p.parse().ok())
+        .collect()
+}
+
+/// Picks the python venv `install.sh` created for this IDF version, i.e.
+/// `~/.espressif/python_env/idf<major>.<minor>_py<ver>_env`. With several
+/// python versions installed the newest one wins.
+fn find_python_env(version: Option<&str>) -> Option<PathBuf> {
+    let version = version?;
+    let mut parts = version.split('.');
+    let prefix = format!("idf{}.{}_py", parts.next()?, parts.next()?);
+
+    let env_root = match std::env::var_os("IDF_TOOLS_PATH") {
+        Some(p) => PathBuf::from(p).join("python_env"),
+        None => dirs::home_dir()?.join(".espressif/python_env"),
+    };
+
+    fs::read_dir(env_root)
+        .ok()?
+        .flatten()
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            let py_version = name.strip_prefix(prefix.as_str())?.trim_end_matches("_env").to_string();
+            let python = python_in_env(&e.path());
+            python.exists().then(|| (version_key(&py_version), python))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, python)| python)
+}
+
+fn python_in_env(env: &Path) -> PathBuf {
+    if cfg!(windows) {
+        env.join("Scripts/python.exe")
+    } else {
+        env.join("bin/python")
+    }
+}
+
+/// `idf-env.json` lists installs under `idfInstalled`, either as an object
+/// keyed by id (older installers) or as an array (EIM).
+fn read_idf_env_json(path: &Path) -> Vec<(PathBuf, Option<PathBuf>)> {
+    let json: serde_json::Value = match fs::read_to_string(path)
+        .ok()
+        .and_then(|d| serde_json::from_str(&d).ok())
+    {
+        Some(v) => v,
+        None => return Vec::new(),
+    };
+
+    let entries: Vec<&serde_json::Value> = match &json["idfInstalled"] {
+        serde_json::Value::Object(map) => map.values().collect(),
+        serde_json::Value::Array(list) => list.iter().collect(),
+        _ => return Vec::new(),
+    };
+
+    entries
+        .into_iter()
+        .filter_map(|e| {
+            let path = e["path"].as_str()?;
+            let python = e["python"].as_str().map(PathBuf::from);
+            Some((PathBuf::from(path), python))
+        })
+        .collect()
+}
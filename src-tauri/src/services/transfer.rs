@@ -0,0 +1,180 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::models::explorer::TransferProgress;
+use crate::models::workspace::FsError;
+
+/// Minimum time between two progress reports.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Counts what a copy has done and reports it now and then.
+pub struct Progress<F: FnMut(&TransferProgress)> {
+    report: TransferProgress,
+    last: Instant,
+    emit: F,
+}
+
+impl<F: FnMut(&TransferProgress)> Progress<F> {
+    pub fn new(source: &Path, destination: &Path, emit: F) -> Self {
+        Progress {
+            report: TransferProgress {
+                source: source.to_string_lossy().to_string(),
+                destination: destination.to_string_lossy().to_string(),
+                files_done: 0,
+                files_total: 0,
+                bytes_done: 0,
+                bytes_total: 0,
+                done: false,
+            },
+            last: Instant::now(),
+            emit,
+        }
+    }
+
+    /// Sizes up `path` before it's copied.
+    fn start(&mut self, path: &Path) {
+        let (files, bytes) = measure(path);
+        self.report.files_total = files;
+        self.report.bytes_total = bytes;
+        (self.emit)(&self.report);
+    }
+
+    fn file_done(&mut self, bytes: u64) {
+        self.report.files_done += 1;
+        self.report.bytes_done += bytes;
+        if self.last.elapsed() >= PROGRESS_INTERVAL {
+            self.last = Instant::now();
+            (self.emit)(&self.report);
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.report.done = true;
+        (self.emit)(&self.report);
+    }
+}
+
+/// Files (symlinks included) and bytes under `path`, without following
+/// symlinks.
+fn measure(path: &Path) -> (u64, u64) {
+    let meta = match fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(_) => return (0, 0),
+    };
+    if meta.file_type().is_symlink() {
+        return (1, 0);
+    }
+    if !meta.is_dir() {
+        return (1, meta.len());
+    }
+    fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| measure(&entry.path()))
+        .fold((0, 0), |(files, bytes), (f, b)| (files + f, bytes + b))
+}
+
+/// Copies `from` to `to`, recursively for folders. Symlinks are copied as
+/// links, so a copy never reaches outside the tree it was asked for. A
+/// failed copy leaves nothing behind at `to`.
+pub fn copy<F: FnMut(&TransferProgress)>(from: &Path, to: &Path, progress: &mut Progress<F>) -> Result<(), FsError> {
+    progress.start(from);
+    copy_entry(from, to, progress).map_err(|err| {
+        remove(to);
+        err
+    })
+}
+
+fn copy_entry<F: FnMut(&TransferProgress)>(from: &Path, to: &Path, progress: &mut Progress<F>) -> Result<(), FsError> {
+    let meta = fs::symlink_metadata(from).map_err(|e| FsError::io(from, e))?;
+
+    if meta.file_type().is_symlink() {
+        let target = fs::read_link(from).map_err(|e| FsError::io(from, e))?;
+        symlink(&target, to, from.is_dir()).map_err(|e| FsError::io(to, e))?;
+        progress.file_done(0);
+    } else if meta.is_dir() {
+        fs::create_dir(to).map_err(|e| FsError::io(to, e))?;
+        for entry in fs::read_dir(from).map_err(|e| FsError::io(from, e))? {
+            let entry = entry.map_err(|e| FsError::io(from, e))?;
+            copy_entry(&entry.path(), &to.join(entry.file_name()), progress)?;
+        }
+        fs::set_permissions(to, meta.permissions()).map_err(|e| FsError::io(to, e))?;
+    } else {
+        let bytes = fs::copy(from, to).map_err(|e| FsError::io(from, e))?;
+        progress.file_done(bytes);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path, _is_dir: bool) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path, is_dir: bool) -> std::io::Result<()> {
+    if is_dir {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+/// Moves `from` to `to`: a rename when both are on the same filesystem,
+/// otherwise a copy followed by removing `from`.
+pub fn move_entry<F: FnMut(&TransferProgress)>(from: &Path, to: &Path, progress: &mut Progress<F>) -> Result<(), FsError> {
+    match fs::rename(from, to) {
+        Ok(()) => return Ok(()),
+        Err(e) if is_cross_device(&e) => {}
+        Err(e) => return Err(FsError::io(from, e)),
+    }
+    copy(from, to, progress)?;
+    let removed = match fs::symlink_metadata(from) {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(from),
+        _ => fs::remove_file(from),
+    };
+    removed.map_err(|e| FsError::io(from, e))
+}
+
+#[cfg(unix)]
+fn is_cross_device(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(libc::EXDEV)
+}
+
+#[cfg(windows)]
+fn is_cross_device(e: &io::Error) -> bool {
+    // ERROR_NOT_SAME_DEVICE
+    e.raw_os_error() == Some(17)
+}
+
+fn remove(path: &Path) {
+    let _ = match fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(path),
+        _ => fs::remove_file(path),
+    };
+}
+
+/// `path`, or the first free `<name> copy[ N]<.ext>` next to it.
+pub fn free_path(path: &Path) -> PathBuf {
+    if fs::symlink_metadata(path).is_err() {
+        return path.to_path_buf();
+    }
+
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    // folders and dotfiles keep their whole name in front of " copy"
+    let (stem, ext) = match name.rfind('.') {
+        Some(i) if i > 0 && !path.is_dir() => (&name[..i], &name[i..]),
+        _ => (name.as_str(), ""),
+    };
+
+    (1..)
+        .map(|n| {
+            let suffix = if n == 1 { " copy".to_string() } else { format!(" copy {}", n) };
+            path.with_file_name(format!("{}{}{}", stem, suffix, ext))
+        })
+        .find(|candidate| fs::symlink_metadata(candidate).is_err())
+        .unwrap()
+}
@@ -0,0 +1,138 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use uuid::Uuid;
+
+use crate::models::trash::TrashEntry;
+use crate::models::workspace::{FsError, FsErrorKind};
+use crate::services::project_settings::{editor_dir, ensure_gitignore};
+
+/// Serialises updates of the trash indexes.
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+/// Deleted items live in `<project>/.veditor/trash/<id>`, listed in
+/// `index.json` next to them.
+fn trash_dir(project_path: &Path) -> PathBuf {
+    editor_dir(project_path).join("trash")
+}
+
+fn load_index(project_path: &Path) -> Vec<TrashEntry> {
+    fs::read_to_string(trash_dir(project_path).join("index.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(project_path: &Path, entries: &[TrashEntry]) -> Result<(), FsError> {
+    let file = trash_dir(project_path).join("index.json");
+    let data = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
+    fs::write(&file, data).map_err(|e| FsError::io(&file, e))
+}
+
+/// Moves `path` (inside `project_path`, both canonical) into the project's
+/// trash.
+pub fn move_to_trash(project_path: &Path, path: &Path) -> Result<TrashEntry, FsError> {
+    let _lock = INDEX_LOCK.lock().unwrap();
+    let meta = fs::symlink_metadata(path).map_err(|e| FsError::io(path, e))?;
+    let relative = path
+        .strip_prefix(project_path)
+        .map_err(|_| FsError::new(FsErrorKind::OutsideWorkspace, path, format!("{} is outside the project", path.display())))?;
+
+    let dir = trash_dir(project_path);
+    fs::create_dir_all(&dir).map_err(|e| FsError::io(&dir, e))?;
+    ensure_gitignore(project_path);
+    let entry = TrashEntry {
+        id: Uuid::new_v4().simple().to_string(),
+        name: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        original_path: relative.to_string_lossy().to_string(),
+        deleted_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+        is_dir: meta.is_dir(),
+    };
+    let trashed = dir.join(&entry.id);
+    fs::rename(path, &trashed).map_err(|e| FsError::io(path, e))?;
+
+    // an item the index doesn't list could never be restored
+    let mut entries = load_index(project_path);
+    entries.push(entry.clone());
+    if let Err(e) = save_index(project_path, &entries) {
+        let _ = fs::rename(&trashed, path);
+        return Err(e);
+    }
+    Ok(entry)
+}
+
+/// The project's trash, most recently deleted first.
+pub fn list(project_path: &Path) -> Vec<TrashEntry> {
+    let mut entries = load_index(project_path);
+    entries.sort_by_key(|e| Reverse(e.deleted_at));
+    entries
+}
+
+/// Puts a trashed item back where it was and returns that path. Fails if
+/// something else has taken its place since.
+pub fn restore(project_path: &Path, id: &str) -> Result<PathBuf, FsError> {
+    let _lock = INDEX_LOCK.lock().unwrap();
+    let mut entries = load_index(project_path);
+    let index = entries.iter().position(|e| e.id == id).ok_or_else(|| {
+        FsError::new(FsErrorKind::NotFound, project_path, format!("Trash entry {} not found", id))
+    })?;
+
+    let target = project_path.join(&entries[index].original_path);
+    if fs::symlink_metadata(&target).is_ok() {
+        return Err(FsError::new(
+            FsErrorKind::AlreadyExists,
+            &target,
+            format!("{} already exists", target.display()),
+        ));
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| FsError::io(parent, e))?;
+    }
+    let trashed = trash_dir(project_path).join(id);
+    fs::rename(&trashed, &target).map_err(|e| FsError::io(&trashed, e))?;
+
+    entries.remove(index);
+    if let Err(e) = save_index(project_path, &entries) {
+        let _ = fs::rename(&target, &trashed);
+        return Err(e);
+    }
+    Ok(target)
+}
+
+/// Deletes trashed items for good: those in `ids`, or all of them.
+pub fn empty(project_path: &Path, ids: Option<&[String]>) -> Result<(), FsError> {
+    let _lock = INDEX_LOCK.lock().unwrap();
+    let mut entries = load_index(project_path);
+    let dir = trash_dir(project_path);
+
+    let mut result = Ok(());
+    entries.retain(|entry| {
+        if ids.is_some_and(|ids| !ids.contains(&entry.id)) {
+            return true;
+        }
+        let path = dir.join(&entry.id);
+        let removed = match fs::symlink_metadata(&path) {
+            Ok(meta) if meta.is_dir() => fs::remove_dir_all(&path),
+            Ok(_) => fs::remove_file(&path),
+            Err(_) => Ok(()),
+        };
+        match removed {
+            Ok(()) => false,
+            Err(e) => {
+                result = Err(FsError::io(&path, e));
+                true
+            }
+        }
+    });
+
+    if dir.is_dir() {
+        save_index(project_path, &entries)?;
+    }
+    result
+}
@@ -0,0 +1,168 @@
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::models::workspace::{FsError, FsErrorKind};
+
+/// The project roots a window may touch. Every path coming from the
+/// webview goes through `resolve` or `resolve_entry` before it is used.
+pub struct Sandbox {
+    roots: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    /// `roots` must already be canonical.
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        Sandbox { roots }
+    }
+
+    /// `path` with `..` and symlinks resolved, which has to end up inside
+    /// an open project. A path that doesn't exist yet is resolved up to its
+    /// deepest existing folder.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, FsError> {
+        let original = Path::new(path);
+        let resolved = canonicalize_lenient(original)?;
+        self.check(&resolved, original)?;
+        Ok(resolved)
+    }
+
+    /// The entry `path` names, without following a symlink in its last
+    /// component; for renaming and deleting. Project roots themselves are
+    /// refused.
+    pub fn resolve_entry(&self, path: &str) -> Result<PathBuf, FsError> {
+        let original = Path::new(path);
+        let name = match original.components().next_back() {
+            Some(Component::Normal(name)) => name,
+            _ => return Err(FsError::new(FsErrorKind::InvalidPath, original, format!("Invalid path: {}", path))),
+        };
+        let parent = original.parent().unwrap_or(original);
+        let resolved = canonicalize_lenient(parent)?.join(name);
+
+        self.check(&resolved, original)?;
+        if self.roots.contains(&resolved) {
+            return Err(FsError::new(
+                FsErrorKind::InvalidPath,
+                original,
+                format!("{} is a project folder", path),
+            ));
+        }
+        Ok(resolved)
+    }
+
+    /// The open project `path` (already resolved) belongs to.
+    pub fn root_of(&self, path: &Path) -> Option<&Path> {
+        self.roots
+            .iter()
+            .filter(|r| path.starts_with(r))
+            .max_by_key(|r| r.components().count())
+            .map(PathBuf::as_path)
+    }
+
+    fn check(&self, resolved: &Path, original: &Path) -> Result<(), FsError> {
+        if self.root_of(resolved).is_some() {
+            return Ok(());
+        }
+        Err(FsError::new(
+            FsErrorKind::OutsideWorkspace,
+            original,
+            format!("{} is outside the open projects", original.display()),
+        ))
+    }
+}
+
+/// Like `canonicalize`, but missing trailing components are allowed (they
+/// are appended as given, and can't be `..`). Dangling symlinks are an
+/// error, writing through one could land anywhere.
+fn canonicalize_lenient(path: &Path) -> Result<PathBuf, FsError> {
+    if !path.is_absolute() {
+        return Err(FsError::new(
+            FsErrorKind::InvalidPath,
+            path,
+            format!("{} is not an absolute path", path.display()),
+        ));
+    }
+
+    let mut base = path.to_path_buf();
+    let mut missing: Vec<OsString> = Vec::new();
+    while fs::symlink_metadata(&base).is_err() {
+        match base.components().next_back() {
+            Some(Component::Normal(name)) => {
+                missing.push(name.to_os_string());
+                base.pop();
+            }
+            _ => {
+                return Err(FsError::new(
+                    FsErrorKind::InvalidPath,
+                    path,
+                    format!("Invalid path: {}", path.display()),
+                ))
+            }
+        }
+    }
+
+    let mut resolved = base.canonicalize().map_err(|e| FsError::io(path, e))?;
+    for name in missing.into_iter().rev() {
+        resolved.push(name);
+    }
+    Ok(resolved)
+}
+
+/// Canonical form of a project folder about to be opened.
+pub fn project_root(path: &str) -> Result<PathBuf, FsError> {
+    let original = Path::new(path);
+    let root = original.canonicalize().map_err(|e| FsError::io(original, e))?;
+    if !root.is_dir() {
+        return Err(FsError::new(FsErrorKind::InvalidPath, original, format!("{} is not a folder", path)));
+    }
+    // a root that is `/` or the home folder would make the sandbox pointless
+    let home = dirs::home_dir().and_then(|h| h.canonicalize().ok());
+    if root.parent().is_none() || home.as_deref() == Some(root.as_path()) {
+        return Err(FsError::new(
+            FsErrorKind::InvalidPath,
+            original,
+            format!("{} can't be opened as a project", path),
+        ));
+    }
+    Ok(root)
+}
+
+fn trusted_file() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Failed to find home directory")?;
+    Ok(home.join(".esp-projects").join("trusted_projects.json"))
+}
+
+/// Project folders the user picked in a dialog or created, which the
+/// webview may open again later. Kept out of the recent projects list
+/// because the webview writes that one.
+fn load_trusted() -> Vec<PathBuf> {
+    trusted_file()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn trust(root: &Path) -> Result<(), String> {
+    let mut trusted = load_trusted();
+    if trusted.iter().any(|t| t == root) {
+        return Ok(());
+    }
+    trusted.push(root.to_path_buf());
+
+    let path = trusted_file()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(&trusted).map_err(|e| e.to_string())?;
+    fs::write(path, data).map_err(|e| e.to_string())
+}
+
+/// Whether the webview may open `root` on its own: projects in
+/// ~/esp-projects and ones the user picked before.
+pub fn is_trusted(root: &Path) -> bool {
+    let projects_dir = dirs::home_dir().and_then(|h| h.join("esp-projects").canonicalize().ok());
+    if projects_dir.is_some_and(|dir| root.starts_with(&dir) && root != dir) {
+        return true;
+    }
+    load_trusted().iter().any(|t| t == root)
+}
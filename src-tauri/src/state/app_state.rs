@@ -1,9 +1,84 @@
-use std::sync::Mutex;
+use notify::RecommendedWatcher;
+use portable_pty::MasterPty;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::models::build::{BuildJobInfo, BuildStatus};
+use crate::models::diagnostic::Diagnostic;
+use crate::models::explorer::ExplorerOperation;
+use crate::services::kconfig::KconfigTree;
 
 #[derive(Default)]
 
 pub struct AppState {
     pub active_project : Mutex <Option<String>>,
     pub selected_controllers : Mutex<Vec<String>>,
+    pub builds : Mutex<HashMap<String, BuildJob>>,
+    pub diagnostics : Mutex<HashMap<String, Vec<Diagnostic>>>,
+    pub terminals : Mutex<HashMap<String, TerminalSession>>,
+    pub kconfig : Mutex<HashMap<String, KconfigTree>>,
+    /// Cancel flags of running `search_project` walks, keyed by search id.
+    pub searches : Mutex<HashMap<String, Arc<AtomicBool>>>,
+    pub watchers : Mutex<HashMap<String, ProjectWatcher>>,
+    /// What the editor itself recently wrote or removed, so the watcher can
+    /// tell its own changes from external ones.
+    pub own_changes : Mutex<HashMap<PathBuf, OwnChange>>,
+    /// Canonical roots of the projects open in each window, keyed by window
+    /// label; the filesystem commands refuse paths outside them.
+    pub workspaces : Mutex<HashMap<String, Vec<PathBuf>>>,
+    /// Explorer operations that can be undone, newest last, keyed by window
+    /// label.
+    pub explorer_history : Mutex<HashMap<String, Vec<ExplorerOperation>>>,
+
+}
+
+/// A build started by `build_project`, keyed by job id in `AppState::builds`.
+/// `pid` is the child's process group id and is cleared once the child exits.
+pub struct BuildJob {
+    pub project_path : String,
+    pub status : BuildStatus,
+    pub pid : Option<u32>,
+    pub exit_code : Option<i32>,
+}
+
+impl BuildJob {
+    pub fn info(&self, job_id: &str) -> BuildJobInfo {
+        BuildJobInfo {
+            job_id: job_id.to_string(),
+            project_path: self.project_path.clone(),
+            status: self.status.clone(),
+            exit_code: self.exit_code,
+        }
+    }
+}
+
+/// An interactive shell started by `terminal_spawn`, keyed by session id in
+/// `AppState::terminals`. Sessions belong to the window that opened them.
+pub struct TerminalSession {
+    pub window_label : String,
+    pub pid : Option<u32>,
+    /// Locked on its own, so a shell that stops reading only blocks its
+    /// own writes and not the session map.
+    pub writer : Arc<Mutex<Box<dyn Write + Send>>>,
+    pub master : Box<dyn MasterPty + Send>,
+}
+
+/// An entry of `AppState::own_changes`. Dropped once the watcher has seen
+/// it, or after a while if it never does.
+pub struct OwnChange {
+    /// Hash of the content written, `None` when the path was deleted or
+    /// moved away.
+    pub hash : Option<String>,
+    pub at : Instant,
+}
 
+/// The project watch started by `watch_project`, keyed by window label in
+/// `AppState::watchers`. Dropping it stops the watch.
+pub struct ProjectWatcher {
+    pub project_path : String,
+    pub _watcher : Arc<Mutex<RecommendedWatcher>>,
 }
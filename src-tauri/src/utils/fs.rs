@@ -1,22 +1,59 @@
+use std::borrow::Cow;
+use std::fs::Metadata;
+use std::io::Write;
 use std::path::Path;
-use crate::models::artifact::Artifact;
+use std::time::UNIX_EPOCH;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
 
-pub fn find_bins(build_dir: &Path) -> Vec<Artifact> {
-    let mut artifacts = Vec::new();
+/// Hex SHA-256 of `data`, used to notice files that changed on disk.
+pub fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Replaces `path` with `data` through a temporary file in the same folder
+/// and a rename, so readers see either the old or the new content. An
+/// existing file keeps its permissions.
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<(), String> {
+    let dir = path.parent().ok_or("Invalid file path")?;
+    let name = path.file_name().ok_or("Invalid file path")?.to_string_lossy();
+    let tmp = dir.join(format!(".{}.{}.tmp", name, Uuid::new_v4().simple()));
 
-    if let Ok(entries) = std::fs::read_dir(build_dir) {
-        for e in entries.flatten() {
-            let path = e.path();
-            if path.extension().and_then(|e| e.to_str()) == Some("bin") {
-                let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
-                artifacts.push(Artifact {
-                    name: path.file_name().unwrap().to_string_lossy().to_string(),
-                    path: path.to_string_lossy().to_string(),
-                    size_bytes: size,
-                });
-            }
+    let result = (|| {
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        if let Ok(meta) = std::fs::metadata(path) {
+            std::fs::set_permissions(&tmp, meta.permissions())?;
         }
-    }
+        std::fs::rename(&tmp, path)
+    })();
+
+    result.map_err(|e| {
+        let _ = std::fs::remove_file(&tmp);
+        format!("{}: {}", path.display(), e)
+    })
+}
 
-    artifacts
+/// Last modification of a file, in milliseconds since the epoch.
+pub fn modified_ms(meta: &Metadata) -> Option<u64> {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+}
+
+/// `content` with the line endings `existing` uses throughout; unchanged
+/// when `existing` mixes them or has no line breaks.
+pub fn match_line_endings<'a>(content: &'a str, existing: &[u8]) -> Cow<'a, str> {
+    let crlf = existing.windows(2).filter(|w| w == b"\r\n").count();
+    let lf = existing.iter().filter(|&&b| b == b'\n').count() - crlf;
+
+    if crlf > 0 && lf == 0 && content.contains('\n') {
+        Cow::Owned(content.replace("\r\n", "\n").replace('\n', "\r\n"))
+    } else if lf > 0 && crlf == 0 && content.contains("\r\n") {
+        Cow::Owned(content.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(content)
+    }
 }
@@ -0,0 +1,74 @@
+#[cfg(unix)]
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::sync::Mutex;
+#[cfg(unix)]
+use std::time::{Duration, Instant};
+
+/// How long a group gets to exit after `SIGTERM` before it is killed.
+#[cfg(unix)]
+const KILL_GRACE: Duration = Duration::from_secs(3);
+
+/// Groups sent `SIGTERM`, and when; escalated by whoever waits on the leader.
+#[cfg(unix)]
+static TERMINATING: Mutex<Option<HashMap<u32, Instant>>> = Mutex::new(None);
+
+/// Sends `SIGTERM` to the whole process group led by `pid`. The waiter that
+/// owns the leader follows up with `SIGKILL` through `escalate_kill` so
+/// `idf.py`'s ninja/cmake children go too.
+#[cfg(unix)]
+pub fn kill_process_group(pid: u32) -> Result<(), String> {
+    if unsafe { libc::killpg(pid as libc::pid_t, libc::SIGTERM) } != 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+
+    let mut terminating = TERMINATING.lock().unwrap();
+    terminating.get_or_insert_with(HashMap::new).entry(pid).or_insert_with(Instant::now);
+    Ok(())
+}
+
+/// Sends `SIGKILL` to the group led by `pid` once its `SIGTERM` grace period
+/// is over. Only call this while the leader hasn't been reaped: until then
+/// its pid, and so the group id, can't be reused.
+#[cfg(unix)]
+pub fn escalate_kill(pid: u32) {
+    let terminating = TERMINATING.lock().unwrap();
+    let expired = terminating
+        .as_ref()
+        .and_then(|t| t.get(&pid))
+        .is_some_and(|sent| sent.elapsed() > KILL_GRACE);
+    if expired {
+        unsafe {
+            libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+        }
+    }
+}
+
+/// Drops the bookkeeping for a group whose leader has been reaped.
+#[cfg(unix)]
+pub fn forget_process_group(pid: u32) {
+    if let Some(terminating) = TERMINATING.lock().unwrap().as_mut() {
+        terminating.remove(&pid);
+    }
+}
+
+#[cfg(windows)]
+pub fn kill_process_group(pid: u32) -> Result<(), String> {
+    let status = std::process::Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &pid.to_string()])
+        .status()
+        .map_err(|e| e.to_string())?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("taskkill failed for pid {}", pid))
+    }
+}
+
+/// `taskkill /F` is already forceful.
+#[cfg(windows)]
+pub fn escalate_kill(_pid: u32) {}
+
+#[cfg(windows)]
+pub fn forget_process_group(_pid: u32) {}
@@ -0,0 +1,20 @@
+// prefixed char and string literals can hold braces too
+wchar_t w = L'{';
+char16_t c16 = u'{';
+char32_t c32 = U'}';
+char8_t c8 = u8'{';
+const wchar_t *wide = L"{";
+
+// digit separators are not char literals
+const long big = 1'000'000;
+const unsigned mask = 0xFF'FF;
+
+void setup() {
+  Serial.begin(115200);
+}
+
+void loop() {
+  Serial.println(sum(big, mask) + w);
+}
+
+int sum(int a, int b) { return a + b; }
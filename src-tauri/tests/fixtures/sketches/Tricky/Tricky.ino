@@ -0,0 +1,74 @@
+#include <Arduino.h>
+#define LED 2
+#define SQUARE(x) \
+  ((x) * (x))
+
+/* a block comment with a fake function:
+void commented() {
+}
+*/
+volatile int pulses = 0;
+int pins[] = {2, 4, 5};
+const char *banner = "void notAFunction() {";
+
+int alreadyDeclared(int value);
+
+class Blinker {
+public:
+  Blinker(int pin) : pin_(pin) {}
+  void toggle() { digitalWrite(pin_, !digitalRead(pin_)); }
+  int pin() const;
+
+private:
+  int pin_;
+};
+
+int Blinker::pin() const {
+  return pin_;
+}
+
+Blinker blinker(LED);
+
+void IRAM_ATTR onPulse() {
+  pulses++;
+}
+
+// default arguments only work when defined before use
+void report(char tag = '-') {
+  Serial.printf("%c %d\n", tag, pulses);
+}
+
+void setup() {
+  attachInterrupt(digitalPinToInterrupt(15), onPulse, RISING);
+  Serial.println(banner);
+  Serial.println(alreadyDeclared(3));
+  Serial.println(clampTo(SQUARE(pulses), 100));
+  Serial.println(largest<int>(1, 2));
+  report();
+}
+
+void loop() {
+  blinker.toggle();
+  report('x');
+  delay(scaled(250));
+}
+
+int alreadyDeclared(int value) { return value * 2; }
+
+int clampTo(int value,
+            int limit) {
+  return value > limit ? limit : value;
+}
+
+template <typename T> T largest(T a, T b) {
+  return a > b ? a : b;
+}
+
+static inline unsigned long scaled(unsigned long ms) {
+  // braces in a char literal: '{'
+  return ms * (LED == 2 ? '}' - '{' : 1);
+}
+
+#if defined(ESP32)
+void espOnly() {}
+#endif
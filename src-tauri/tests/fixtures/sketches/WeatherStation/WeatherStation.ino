@@ -0,0 +1,30 @@
+// Reads a DHT22 every 10 s and publishes the values over Wi-Fi.
+#include <WiFi.h>
+#include "config.h"
+
+struct Reading {
+  float temperature;
+  float humidity;
+  unsigned long takenAt;
+};
+
+Reading lastReading = {0, 0, 0};
+const char *ssid = WIFI_SSID;
+
+void setup() {
+  Serial.begin(115200);
+  connectWifi(ssid);
+  initSensor();
+}
+
+void loop() {
+  if (millis() - lastReading.takenAt > 10000) {
+    lastReading = readSensor();
+    printReading(lastReading);
+    publish(lastReading);
+  }
+}
+
+void printReading(const Reading &r) {
+  Serial.printf("T=%.1f H=%.1f\n", r.temperature, r.humidity);
+}
@@ -0,0 +1,2 @@
+#define WIFI_SSID "lab"
+#define WIFI_PASSWORD "secret"
@@ -0,0 +1,24 @@
+void connectWifi(const char *name) {
+  WiFi.begin(name, WIFI_PASSWORD);
+  while (WiFi.status() != WL_CONNECTED) {
+    delay(500);
+    Serial.print(".");
+  }
+  Serial.println(" connected");
+}
+
+bool publish(const Reading &r)
+{
+  if (WiFi.status() != WL_CONNECTED) {
+    return false;
+  }
+  // {"t": .., "h": ..}
+  String body = "{\"t\":" + String(r.temperature) + ",\"h\":" + String(r.humidity) + "}";
+  return post("/readings", body);
+}
+
+static bool post(const char *path,
+                 const String &body) {
+  Serial.printf("POST %s %s\n", path, body.c_str());
+  return true;
+}
@@ -0,0 +1,15 @@
+#include <DHT.h>
+
+DHT dht(4, DHT22);
+
+void initSensor() {
+  dht.begin();
+}
+
+Reading readSensor() {
+  Reading r;
+  r.temperature = dht.readTemperature();
+  r.humidity = dht.readHumidity();
+  r.takenAt = millis();
+  return r;
+}